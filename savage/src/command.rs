@@ -1,32 +1,49 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
-use std::str::FromStr;
+use std::{cell::RefCell, rc::Rc, str::FromStr};
 
 use chumsky::prelude::*;
 use savage_core::{
-    expression::Expression,
-    parse::{parser as expression, Error},
+    expression::{Expression, RationalRepresentation},
+    parse::{identifier, parser_with_spans as expression, Error, Mode, Spans},
 };
 
+use crate::MAX_PRECISION;
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum Command {
     EvaluateExpression(Expression),
     DefineVariable(String, Expression),
     DefineFunction(String, Vec<String>, Expression),
     ShowHelp(Option<String>),
+    ShowCategoryHelp(String),
+    ListFunctions,
+    SetSeed(u64),
+    ShowFullOutput(Option<usize>),
+    SetRepresentation(Option<RationalRepresentation>),
+    SetPrecision(Option<usize>),
+    SetMode(Mode),
+    CallFunction(String, Vec<Expression>),
+    ShowSteps(Expression),
+    SaveSession(String),
+    LoadSession(String),
 }
 
-fn parser() -> impl Parser<char, Command, Error = Error> {
-    text::ident()
+/// Functions that can be called with commands of the form `:function argument...`,
+/// which are shortcuts for common operations on expressions.
+const FUNCTION_COMMANDS: [&str; 2] = ["diff", "simplify"];
+
+fn parser(spans: Rc<RefCell<Spans>>, mode: Mode) -> impl Parser<char, Command, Error = Error> {
+    identifier()
         .padded()
         .then_ignore(just('='))
-        .then(expression())
+        .then(expression(spans.clone(), mode))
         .map(|(identifier, expression)| Command::DefineVariable(identifier, expression))
-        .or(text::ident()
+        .or(identifier()
             .padded()
             .then(
-                text::ident()
+                identifier()
                     .padded()
                     .separated_by(just(','))
                     .padded()
@@ -34,30 +51,171 @@ fn parser() -> impl Parser<char, Command, Error = Error> {
                     .padded(),
             )
             .then_ignore(just('='))
-            .then(expression())
+            .then(expression(spans.clone(), mode))
             .map(|((identifier, argument_identifiers), expression)| {
                 Command::DefineFunction(identifier, argument_identifiers, expression)
             }))
-        .or(expression().map(Command::EvaluateExpression))
+        .or(expression(spans.clone(), mode).map(Command::EvaluateExpression))
+        .or(just('?')
+            .padded()
+            .ignore_then(just('@'))
+            .ignore_then(
+                filter(|c: &char| *c != '"')
+                    .repeated()
+                    .collect()
+                    .delimited_by(just('"'), just('"'))
+                    .or(identifier())
+                    .padded(),
+            )
+            .map(Command::ShowCategoryHelp))
         .or(just('?')
             .padded()
-            .ignore_then(text::ident().padded().or_not())
+            .ignore_then(identifier().padded().or_not())
             .map(Command::ShowHelp))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("functions").padded())
+            .to(Command::ListFunctions))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("seed").padded())
+            .ignore_then(text::int(10).padded())
+            .try_map(|seed: String, span| {
+                seed.parse()
+                    .map(Command::SetSeed)
+                    .map_err(|_| Error::custom(span, "Seed is too large"))
+            }))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("full").padded())
+            .ignore_then(text::int(10).padded().or_not())
+            .try_map(|index: Option<String>, span| match index {
+                Some(index) => index
+                    .parse()
+                    .map(|index| Command::ShowFullOutput(Some(index)))
+                    .map_err(|_| Error::custom(span, "Index is too large")),
+                None => Ok(Command::ShowFullOutput(None)),
+            }))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("repr").padded())
+            .ignore_then(
+                text::keyword("fraction")
+                    .to(RationalRepresentation::Fraction)
+                    .or(text::keyword("decimal").to(RationalRepresentation::Decimal))
+                    .padded()
+                    .or_not(),
+            )
+            .map(Command::SetRepresentation))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("precision").padded())
+            .ignore_then(text::int(10).padded().or_not())
+            .try_map(|precision: Option<String>, span| match precision {
+                Some(precision) => match precision.parse() {
+                    Ok(0) => Err(Error::custom(span, "Precision must be positive")),
+                    Ok(precision) if precision <= MAX_PRECISION => {
+                        Ok(Command::SetPrecision(Some(precision)))
+                    }
+                    _ => Err(Error::custom(
+                        span,
+                        format!("Precision must be at most {}", MAX_PRECISION),
+                    )),
+                },
+                None => Ok(Command::SetPrecision(None)),
+            }))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("mode").padded())
+            .ignore_then(
+                text::keyword("standard")
+                    .to(Mode::Standard)
+                    .or(text::keyword("programmer").to(Mode::Programmer))
+                    .padded()
+                    .or_not(),
+            )
+            .map(|mode| Command::SetMode(mode.unwrap_or_default())))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("session").padded())
+            .ignore_then(
+                text::keyword("save")
+                    .to(Command::SaveSession as fn(String) -> Command)
+                    .or(text::keyword("load").to(Command::LoadSession as fn(String) -> Command))
+                    .padded(),
+            )
+            .then(
+                // The path is the rest of the line, which may contain any characters.
+                any()
+                    .repeated()
+                    .at_least(1)
+                    .collect::<String>()
+                    .try_map(|path, span| {
+                        let path = path.trim_end().to_owned();
+
+                        if path.is_empty() {
+                            Err(Error::custom(span, "Missing path"))
+                        } else {
+                            Ok(path)
+                        }
+                    }),
+            )
+            .map(|(command, path)| command(path)))
+        .or(just(':')
+            .padded()
+            .ignore_then(text::keyword("steps").padded())
+            .ignore_then(expression(spans.clone(), mode))
+            .map(Command::ShowSteps))
+        .or(just(':')
+            .padded()
+            .ignore_then(
+                identifier()
+                    .try_map(|identifier, span| {
+                        if FUNCTION_COMMANDS.contains(&identifier.as_str()) {
+                            Ok(identifier)
+                        } else {
+                            Err(Error::custom(span, "Unknown command"))
+                        }
+                    })
+                    .padded(),
+            )
+            // Arguments are separated by whitespace, so e.g. `:diff x^2 x`
+            // is equivalent to `diff(x^2, x)`.
+            .then(expression(spans, mode).padded().repeated().at_least(1))
+            .map(|(identifier, arguments)| Command::CallFunction(identifier, arguments)))
+}
+
+/// Returns the command parsed from the string using the syntax of the given mode
+/// for expressions, and the spans in the string that sub-expressions were parsed from,
+/// or errors if the string cannot be parsed.
+pub fn parse_with_spans(string: &str, mode: Mode) -> Result<(Command, Spans), Vec<Error>> {
+    let spans = Rc::new(RefCell::new(Spans::default()));
+
+    let command = parser(spans.clone(), mode)
+        .then_ignore(end())
+        .parse(string)?;
+
+    let spans = spans.borrow().clone();
+
+    Ok((command, spans))
 }
 
 impl FromStr for Command {
     type Err = Vec<Error>;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        parser().then_ignore(end()).parse(string)
+        parse_with_spans(string, Mode::Standard).map(|(command, _)| command)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use savage_core::helpers::*;
+    use savage_core::{
+        expression::RationalRepresentation::*, functions::function_expression, helpers::*,
+        parse::Mode,
+    };
 
-    use crate::command::{Command, Command::*};
+    use crate::command::{parse_with_spans, Command, Command::*};
 
     #[track_caller]
     fn t(string: &str, command: Command) {
@@ -93,8 +251,124 @@ mod tests {
             ),
         );
 
+        t("θ = 1/2", DefineVariable("θ".to_owned(), int(1) / int(2)));
+        t(
+            "f(α, β) = α * β",
+            DefineFunction(
+                "f".to_owned(),
+                vec!["α".to_owned(), "β".to_owned()],
+                var("α") * var("β"),
+            ),
+        );
+
         t(" ?  ", ShowHelp(None));
         t("?is_prime  ", ShowHelp(Some("is_prime".to_owned())));
         t("?  is_prime", ShowHelp(Some("is_prime".to_owned())));
+
+        t(
+            "?@\"linear algebra\"",
+            ShowCategoryHelp("linear algebra".to_owned()),
+        );
+        t(" ? @arithmetic ", ShowCategoryHelp("arithmetic".to_owned()));
+        assert!("?@".parse::<Command>().is_err());
+        assert!("?@linear algebra".parse::<Command>().is_err());
+
+        t(":functions", ListFunctions);
+        t(" :  functions ", ListFunctions);
+
+        t(":seed 42", SetSeed(42));
+        t(" : seed  0 ", SetSeed(0));
+        assert!(":seed 18446744073709551616".parse::<Command>().is_err());
+
+        t(":full", ShowFullOutput(None));
+        t(" : full 3 ", ShowFullOutput(Some(3)));
+
+        t(":repr fraction", SetRepresentation(Some(Fraction)));
+        t(" : repr  decimal ", SetRepresentation(Some(Decimal)));
+        t(":repr", SetRepresentation(None));
+        assert!(":repr fractions".parse::<Command>().is_err());
+
+        t(":precision 20", SetPrecision(Some(20)));
+        t(" : precision  3 ", SetPrecision(Some(3)));
+        t(":precision", SetPrecision(None));
+        assert!(":precision 0".parse::<Command>().is_err());
+        assert!(":precision -1".parse::<Command>().is_err());
+        t(":precision 1000", SetPrecision(Some(1000)));
+        assert!(":precision 1001".parse::<Command>().is_err());
+        assert!(":precision 4294967296".parse::<Command>().is_err());
+        assert!(":precision 18446744073709551616"
+            .parse::<Command>()
+            .is_err());
+
+        t(":mode programmer", SetMode(Mode::Programmer));
+        t(" : mode  standard ", SetMode(Mode::Standard));
+        t(":mode", SetMode(Mode::Standard));
+        assert!(":mode programming".parse::<Command>().is_err());
+
+        t(
+            ":diff x^2 x",
+            CallFunction("diff".to_owned(), vec![pow(var("x"), int(2)), var("x")]),
+        );
+        t(
+            " : diff  sin(x) * x   x ",
+            CallFunction(
+                "diff".to_owned(),
+                vec![fun(var("sin"), [var("x")]) * var("x"), var("x")],
+            ),
+        );
+        t(
+            ":simplify (x + 0) * (1 * y)",
+            CallFunction(
+                "simplify".to_owned(),
+                vec![(var("x") + int(0)) * (int(1) * var("y"))],
+            ),
+        );
+        t(
+            ":diff x - 1 x",
+            CallFunction("diff".to_owned(), vec![var("x") - int(1), var("x")]),
+        );
+        assert!(":diff".parse::<Command>().is_err());
+        t(
+            ":steps (x + 0) * (2 + 3)",
+            ShowSteps((var("x") + int(0)) * (int(2) + int(3))),
+        );
+        assert!(":steps".parse::<Command>().is_err());
+
+        t(
+            ":session save session.txt",
+            SaveSession("session.txt".to_owned()),
+        );
+        t(
+            " : session  load  my session.txt ",
+            LoadSession("my session.txt".to_owned()),
+        );
+        assert!(":session save".parse::<Command>().is_err());
+        assert!(":session restore session.txt".parse::<Command>().is_err());
+        assert!(":integrate x^2 x".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn modes() {
+        let p = |string, mode| parse_with_spans(string, mode).map(|(command, _)| command);
+
+        assert_eq!(
+            p("2 ^ 3", Mode::Standard),
+            Ok(EvaluateExpression(pow(int(2), int(3)))),
+        );
+        assert_eq!(
+            p("2 ^ 3", Mode::Programmer),
+            Ok(EvaluateExpression(fun(
+                function_expression("bitxor").unwrap(),
+                [int(2), int(3)],
+            ))),
+        );
+        assert_eq!(
+            p("f(x) = x ** 2", Mode::Programmer),
+            Ok(DefineFunction(
+                "f".to_owned(),
+                vec!["x".to_owned()],
+                pow(var("x"), int(2)),
+            )),
+        );
     }
 }
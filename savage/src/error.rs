@@ -0,0 +1,429 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::{ops::Range, str::FromStr};
+
+use ariadne::{Color, Fmt, Label, Report, ReportKind};
+use chumsky::Error as _;
+use savage_core::{
+    evaluate::Error as EvaluateError,
+    expression::Expression,
+    parse::{Error as ParseError, ErrorReason},
+};
+
+/// Format in which errors are printed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ErrorFormat {
+    /// Human-readable reports intended for display in a terminal.
+    Pretty,
+    /// One JSON object per error and line, intended for consumption by other programs.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "pretty" => Ok(ErrorFormat::Pretty),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("Unknown error format \"{}\".", string)),
+        }
+    }
+}
+
+/// Returns the message describing the parse error.
+fn parse_error_message(error: &ParseError) -> String {
+    // Heavily based on https://github.com/zesterer/chumsky/blob/463226372cf293d45bd5df52bf25d5028243066e/examples/json.rs#L114-L173
+    if let ErrorReason::Custom(message) = error.reason() {
+        message.clone()
+    } else {
+        format!(
+            "{}, expected {}",
+            if error.found().is_some() {
+                "Unexpected token"
+            } else {
+                "Unexpected end of input"
+            },
+            if error.expected().len() == 0 {
+                "something else".to_string()
+            } else {
+                error
+                    .expected()
+                    .map(|expected| match expected {
+                        Some(expected) => expected.to_string(),
+                        None => "end of input".to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+        )
+    }
+}
+
+/// Combines the parse errors for a single input into the one that is most useful to report,
+/// or returns `None` if there are no errors. Errors at the same span are merged so that
+/// all expected tokens are listed. Otherwise, the error that occurred furthest into the input
+/// is chosen, because errors at earlier positions are usually caused by parser alternatives
+/// that failed before the one that got closest to the actual mistake.
+pub fn merge_parse_errors(errors: Vec<ParseError>) -> Option<ParseError> {
+    errors.into_iter().reduce(|a, b| {
+        if a.span() == b.span() {
+            a.merge(b)
+        } else if (b.span().start, b.span().end) > (a.span().start, a.span().end) {
+            b
+        } else {
+            a
+        }
+    })
+}
+
+pub fn format_parse_error(error: ParseError) -> Report {
+    let report = Report::build(ReportKind::Error, (), error.span().start)
+        .with_message(parse_error_message(&error))
+        .with_label(
+            Label::new(error.span())
+                .with_message(match error.reason() {
+                    ErrorReason::Custom(message) => message.clone(),
+                    _ => format!(
+                        "Unexpected {}",
+                        error
+                            .found()
+                            .map(|c| format!("token {}", c.fg(Color::Red)))
+                            .unwrap_or_else(|| "end of input".to_string()),
+                    ),
+                })
+                .with_color(Color::Red),
+        );
+
+    let report = match error.reason() {
+        ErrorReason::Unclosed { span, delimiter } => report.with_label(
+            Label::new(span.clone())
+                .with_message(format!(
+                    "Unclosed delimiter {}",
+                    delimiter.fg(Color::Yellow),
+                ))
+                .with_color(Color::Yellow),
+        ),
+        ErrorReason::Unexpected => report,
+        ErrorReason::Custom(_) => report,
+    };
+
+    report.finish()
+}
+
+/// Returns the name of the function called by the expression,
+/// or `None` if the expression is not a call of a named function.
+fn function_name(expression: &Expression) -> Option<&str> {
+    match expression {
+        Expression::FunctionValue(function, _) => match function.as_ref() {
+            Expression::Variable(identifier) | Expression::Function(identifier, _) => {
+                Some(identifier)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the message describing the evaluation error. Unlike the error's `Display` output,
+/// the messages for calls with an invalid number of arguments or an argument of the wrong type
+/// name the function (e.g. "det expects 1 argument but got 2", or
+/// "argument 1 to det must be a square matrix").
+pub fn evaluate_error_message(error: &EvaluateError) -> String {
+    match error {
+        EvaluateError::InvalidNumberOfArguments {
+            expression,
+            min_number,
+            max_number,
+            given_number,
+        } => {
+            if let Some(name) = function_name(expression) {
+                return format!(
+                    "{} expects {} {} but got {}",
+                    name,
+                    if min_number == max_number {
+                        min_number.to_string()
+                    } else {
+                        format!("{} to {}", min_number, max_number)
+                    },
+                    if *max_number == 1 {
+                        "argument"
+                    } else {
+                        "arguments"
+                    },
+                    given_number,
+                );
+            }
+        }
+        EvaluateError::InvalidArgumentType {
+            expression,
+            index,
+            parameter,
+            ..
+        } => {
+            if let Some(name) = function_name(expression) {
+                return format!("argument {} to {} must be {}", index + 1, name, parameter,);
+            }
+        }
+        _ => (),
+    }
+
+    error.to_string()
+}
+
+pub fn format_evaluate_error(error: &EvaluateError, span: Range<usize>) -> Report {
+    use EvaluateError::*;
+
+    Report::build(ReportKind::Error, (), span.start)
+        .with_message(evaluate_error_message(error))
+        .with_label(
+            Label::new(span)
+                .with_message(match error {
+                    InvalidOperand { .. } => "Invalid operand".to_owned(),
+                    IncompatibleOperands { .. } => "Incompatible operands".to_owned(),
+                    DivisionByZero { .. } => "Division by zero".to_owned(),
+                    ZeroToThePowerOfZero { .. } => "Zero to the power of zero".to_owned(),
+                    IndexOutOfBounds { .. } => "Index out of bounds".to_owned(),
+                    InvalidNumberOfArguments { .. } => "Invalid number of arguments".to_owned(),
+                    InvalidArgument { .. } => "Invalid argument".to_owned(),
+                    InvalidArgumentType { parameter, .. } => format!("Expected {}", parameter),
+                    NoSolution { .. } => "No solution".to_owned(),
+                })
+                .with_color(Color::Red),
+        )
+        .finish()
+}
+
+/// Returns the string as a JSON string literal.
+fn json_string(string: &str) -> String {
+    let mut json = String::from('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+
+    json
+}
+
+/// Returns a JSON object describing an error of the given kind
+/// that is not associated with a part of the input.
+pub fn error_json(kind: &str, message: &str) -> String {
+    format!(
+        "{{\"kind\": {}, \"message\": {}}}",
+        json_string(kind),
+        json_string(message),
+    )
+}
+
+/// Returns a JSON object describing the parse error.
+pub fn parse_error_json(error: &ParseError) -> String {
+    format!(
+        "{{\"kind\": \"parse\", \"span\": {{\"start\": {}, \"end\": {}}}, \"reason\": {}, \"message\": {}}}",
+        error.span().start,
+        error.span().end,
+        json_string(match error.reason() {
+            ErrorReason::Unexpected => "unexpected",
+            ErrorReason::Unclosed { .. } => "unclosed_delimiter",
+            ErrorReason::Custom(_) => "custom",
+        }),
+        json_string(&parse_error_message(error)),
+    )
+}
+
+/// Returns a JSON object describing the evaluation error,
+/// which was caused by the part of the input given by `span` (if available).
+pub fn evaluate_error_json(error: &EvaluateError, span: Option<Range<usize>>) -> String {
+    use EvaluateError::*;
+
+    format!(
+        "{{\"kind\": \"evaluate\", \"span\": {}, \"reason\": {}, \"message\": {}}}",
+        span.map_or_else(
+            || "null".to_owned(),
+            |span| format!("{{\"start\": {}, \"end\": {}}}", span.start, span.end),
+        ),
+        json_string(match error {
+            InvalidOperand { .. } => "invalid_operand",
+            IncompatibleOperands { .. } => "incompatible_operands",
+            DivisionByZero { .. } => "division_by_zero",
+            ZeroToThePowerOfZero { .. } => "zero_to_the_power_of_zero",
+            IndexOutOfBounds { .. } => "index_out_of_bounds",
+            InvalidNumberOfArguments { .. } => "invalid_number_of_arguments",
+            InvalidArgument { .. } => "invalid_argument",
+            InvalidArgumentType { .. } => "invalid_argument_type",
+            NoSolution { .. } => "no_solution",
+        }),
+        json_string(&evaluate_error_message(error)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use savage_core::{
+        evaluate::default_context, expression::Expression, parse::Error as ParseError,
+    };
+
+    use crate::{
+        command::Command,
+        error::{
+            evaluate_error_json, evaluate_error_message, merge_parse_errors, parse_error_json,
+        },
+    };
+
+    #[test]
+    fn json() {
+        let error = ParseError::custom(2..5, "Invalid \"number\"");
+
+        assert_eq!(
+            parse_error_json(&error),
+            r#"{"kind": "parse", "span": {"start": 2, "end": 5}, "reason": "custom", "message": "Invalid \"number\""}"#,
+        );
+
+        let error = "1 / 0"
+            .parse::<Expression>()
+            .unwrap()
+            .evaluate(&default_context())
+            .unwrap_err();
+
+        assert_eq!(
+            evaluate_error_json(&error, None),
+            r#"{"kind": "evaluate", "span": null, "reason": "division_by_zero", "message": "Division of 1 by 0, which is zero, in 1 / 0"}"#,
+        );
+
+        assert_eq!(
+            evaluate_error_json(&error, Some(4..5)),
+            r#"{"kind": "evaluate", "span": {"start": 4, "end": 5}, "reason": "division_by_zero", "message": "Division of 1 by 0, which is zero, in 1 / 0"}"#,
+        );
+    }
+
+    #[test]
+    fn arity() {
+        let message = |string: &str| {
+            evaluate_error_message(
+                &string
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&default_context())
+                    .unwrap_err(),
+            )
+        };
+
+        assert_eq!(message("det([[1]], 2)"), "det expects 1 argument but got 2",);
+        assert_eq!(
+            message("round_to(1)"),
+            "round_to expects 2 arguments but got 1"
+        );
+        assert_eq!(
+            message("determinant()"),
+            "determinant expects 1 argument but got 0",
+        );
+        assert_eq!(
+            message("1 / 0"),
+            "Division of 1 by 0, which is zero, in 1 / 0"
+        );
+
+        let error = "det([[1]], 2)"
+            .parse::<Expression>()
+            .unwrap()
+            .evaluate(&default_context())
+            .unwrap_err();
+
+        assert_eq!(
+            evaluate_error_json(&error, Some(0..13)),
+            r#"{"kind": "evaluate", "span": {"start": 0, "end": 13}, "reason": "invalid_number_of_arguments", "message": "det expects 1 argument but got 2"}"#,
+        );
+    }
+
+    #[test]
+    fn argument_types() {
+        let error = |string: &str| {
+            string
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(&default_context())
+                .unwrap_err()
+        };
+
+        assert_eq!(
+            evaluate_error_message(&error("det([[1, 2]])")),
+            "argument 1 to det must be a square matrix",
+        );
+        assert_eq!(
+            evaluate_error_message(&error("round_to(1, -1)")),
+            "argument 2 to round_to must be a non-negative integer",
+        );
+        assert_eq!(
+            evaluate_error_message(&error("div(1, 0)")),
+            "Invalid argument 0 in div(1, 0)",
+        );
+
+        assert_eq!(
+            evaluate_error_json(&error("det([[1, 2]])"), Some(4..12)),
+            r#"{"kind": "evaluate", "span": {"start": 4, "end": 12}, "reason": "invalid_argument_type", "message": "argument 1 to det must be a square matrix"}"#,
+        );
+    }
+
+    #[test]
+    fn merge() {
+        assert_eq!(merge_parse_errors(vec![]), None);
+
+        let error = merge_parse_errors("1 + * 2".parse::<Expression>().unwrap_err()).unwrap();
+
+        assert_eq!(error.span(), 4..5);
+
+        // Errors reported by the expression parser and by the command parser
+        // for the same input, which fail at the same or at different positions.
+        let errors = |string: &str| {
+            let mut errors = string.parse::<Expression>().unwrap_err();
+            errors.append(&mut string.parse::<Command>().unwrap_err());
+            errors
+        };
+
+        let errors_at_same_span = errors(")");
+        let expected = errors_at_same_span
+            .iter()
+            .flat_map(|error| error.expected().cloned())
+            .collect::<HashSet<_>>();
+
+        assert_eq!(errors_at_same_span.len(), 2);
+        assert!(errors_at_same_span
+            .iter()
+            .all(|error| error.span() == (0..1)));
+
+        let merged_error = merge_parse_errors(errors_at_same_span).unwrap();
+
+        assert_eq!(merged_error.span(), 0..1);
+        assert_eq!(merged_error.found(), Some(&')'));
+        assert_eq!(
+            merged_error.expected().cloned().collect::<HashSet<_>>(),
+            expected
+        );
+        assert!(expected.contains(&Some('(')));
+        assert!(expected.contains(&Some(':')));
+
+        let errors_at_different_spans = errors("a = = 1");
+
+        assert_eq!(errors_at_different_spans[0].span(), 3..4);
+        assert_eq!(errors_at_different_spans[1].span(), 4..5);
+        assert_eq!(
+            merge_parse_errors(errors_at_different_spans.clone()),
+            Some(errors_at_different_spans[1].clone()),
+        );
+        assert_eq!(
+            merge_parse_errors(errors_at_different_spans.into_iter().rev().collect()),
+            merge_parse_errors(errors("a = = 1")),
+        );
+    }
+}
@@ -17,7 +17,7 @@ use crossterm::{
     },
 };
 use lazy_static::lazy_static;
-use savage_core::functions::functions;
+use savage_core::functions::{all_metadata, functions_by_category};
 use termimad::{Area, Error, MadSkin, MadView};
 
 const HELP_HEADER: &str = include_str!("../help/header.md");
@@ -27,9 +27,7 @@ lazy_static! {
     pub static ref FUNCTION_HELP_TEXTS: HashMap<String, String> = {
         let mut texts = HashMap::new();
 
-        for function in functions() {
-            let metadata = function.metadata;
-
+        for metadata in all_metadata() {
             let text = format!(
                 "**{}** - {}\n\n*Syntax:*\n```\n{}({})\n```\n\n*Examples:*\n```\n{}\n```\n\n*Categories:*\n{}\n",
                 metadata.name,
@@ -50,7 +48,9 @@ lazy_static! {
                 metadata.categories.join(", "),
             );
 
-            texts.insert(metadata.name.to_owned(), text);
+            for identifier in metadata.identifiers() {
+                texts.insert(identifier.to_owned(), text.clone());
+            }
         }
 
         texts
@@ -60,8 +60,8 @@ lazy_static! {
 
         text.push_str("\n---\n\n");
 
-        for function in functions() {
-            text.push_str(&FUNCTION_HELP_TEXTS[function.metadata.name]);
+        for metadata in all_metadata() {
+            text.push_str(&FUNCTION_HELP_TEXTS[metadata.name]);
             text.push_str("\n---\n\n");
         }
 
@@ -73,6 +73,21 @@ lazy_static! {
     };
 }
 
+/// Returns the help text for all functions associated with the given category,
+/// or `None` if no function is associated with that category.
+pub fn category_help_text(category: &str) -> Option<String> {
+    let functions = functions_by_category().remove(category)?;
+
+    let mut text = format!("# Category: {}\n\n---\n\n", category);
+
+    for metadata in functions {
+        text.push_str(&FUNCTION_HELP_TEXTS[metadata.name]);
+        text.push_str("\n---\n\n");
+    }
+
+    Some(text)
+}
+
 fn view_area() -> Area {
     let mut area = Area::full_screen();
 
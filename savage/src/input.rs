@@ -14,12 +14,14 @@ use rustyline::{
 use rustyline_derive::{Completer, Helper, Hinter};
 use savage_core::{expression::Expression, parse::ErrorReason};
 
+#[derive(PartialEq, Eq, Debug)]
 enum TokenType {
     Literal,
     Variable,
     Operator,
     Bracket,
     Separator,
+    Ellipsis,
     Whitespace,
     Invalid,
 }
@@ -31,10 +33,11 @@ fn tokenize(input: &str) -> Vec<(String, TokenType)> {
         static ref REGEX: Regex = Regex::new(
             &[
                 r"(?P<literal>[0-9]+(?:\.[0-9]+)?|true|false)",
-                r"(?P<variable>[a-zA-Z_][a-zA-Z0-9_]*)",
+                r"(?P<variable>[\p{Alphabetic}_][\p{Alphabetic}\p{N}_]*)",
                 r"(?P<operator>[+\-*/%^!=<>&|]+)",
                 r"(?P<bracket>[()\[\]])",
                 r"(?P<separator>,)",
+                r"(?P<ellipsis>\.\.\.)",
                 r"(?P<whitespace>\s+)",
             ]
             .join("|"),
@@ -57,6 +60,8 @@ fn tokenize(input: &str) -> Vec<(String, TokenType)> {
             Bracket
         } else if captures.name("separator").is_some() {
             Separator
+        } else if captures.name("ellipsis").is_some() {
+            Ellipsis
         } else if captures.name("whitespace").is_some() {
             Whitespace
         } else {
@@ -144,6 +149,8 @@ impl Highlighter for InputHelper {
                 Operator => Purple.into(),
                 Bracket => Style::new(),
                 Separator => Style::new(),
+                // Ellipses only occur in abbreviated outputs.
+                Ellipsis => Style::new().dimmed(),
                 Whitespace => Style::new(),
                 Invalid => Red.into(),
             };
@@ -181,7 +188,7 @@ impl Validator for InputHelper {
 
         if let Err(errors) = input.parse::<Expression>() {
             for error in errors {
-                if error.reason() == &ErrorReason::Unexpected && error.found() == None {
+                if error.reason() == &ErrorReason::Unexpected && error.found().is_none() {
                     return Ok(ValidationResult::Incomplete);
                 }
             }
@@ -190,3 +197,30 @@ impl Validator for InputHelper {
         Ok(ValidationResult::Valid(None))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{tokenize, TokenType::*};
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize("θ^2+α_1 *(x1, ?)"),
+            vec![
+                ("θ".to_owned(), Variable),
+                ("^".to_owned(), Operator),
+                ("2".to_owned(), Literal),
+                ("+".to_owned(), Operator),
+                ("α_1".to_owned(), Variable),
+                (" ".to_owned(), Whitespace),
+                ("*".to_owned(), Operator),
+                ("(".to_owned(), Bracket),
+                ("x1".to_owned(), Variable),
+                (",".to_owned(), Separator),
+                (" ".to_owned(), Whitespace),
+                ("?".to_owned(), Invalid),
+                (")".to_owned(), Bracket),
+            ],
+        );
+    }
+}
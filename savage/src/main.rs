@@ -2,101 +2,471 @@
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
 mod command;
+mod error;
 mod help;
 mod input;
+mod session;
 
 use std::{
     collections::{HashMap, HashSet},
-    fs,
+    env, fs,
+    io::stdin,
     iter::FromIterator,
+    process,
     rc::Rc,
 };
 
 use ansi_term::Style;
-use ariadne::{Color, Fmt, Label, Report, ReportKind, Source};
+use ariadne::Source;
+use crossterm::tty::IsTty;
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
 use rustyline::{error::ReadlineError, highlight::Highlighter, Editor};
 use savage_core::{
-    evaluate::{default_context, Error as EvaluateError},
-    expression::{Expression, Vector},
-    parse::{Error as ParseError, ErrorReason},
+    evaluate::{default_context, seed_context, Error as EvaluateError},
+    expression::{Expression, Function, RationalRepresentation, Vector},
+    functions::functions_by_category,
+    helpers::{fun, var},
+    parse::{Error as ParseError, Mode},
+    print::DisplayOptions,
 };
 
 use crate::{
-    command::Command,
-    help::{show_help, FUNCTION_HELP_TEXTS, HELP_TEXT},
+    error::{
+        error_json, evaluate_error_json, evaluate_error_message, format_evaluate_error,
+        format_parse_error, merge_parse_errors, parse_error_json, ErrorFormat,
+    },
+    help::{category_help_text, show_help, FUNCTION_HELP_TEXTS, HELP_TEXT},
     input::InputHelper,
+    session::{Definition, Session},
 };
 
+/// Options used for displaying outputs, which abbreviate huge vectors and matrices.
+/// The `:full` command displays outputs without abbreviation.
+const OUTPUT_DISPLAY_OPTIONS: DisplayOptions = DisplayOptions {
+    max_elements: Some(20),
+    repeating_decimals: false,
+    significant_digits: None,
+    fully_parenthesized: false,
+};
+
+/// Number of significant digits to which non-terminating decimals in outputs are rounded,
+/// unless changed with the `:precision` command.
+const DEFAULT_PRECISION: usize = 15;
+
+/// Largest number of significant digits that can be set with the `:precision` command.
+const MAX_PRECISION: usize = 1000;
+
 lazy_static! {
     static ref RESERVED_IDENTIFIERS: HashSet<String> =
-        HashSet::from(["true", "false", "out"].map(str::to_owned));
+        HashSet::from(["true", "false", "out", "ans"].map(str::to_owned));
 }
 
-fn format_parse_error(error: ParseError) -> Report {
-    // Heavily based on https://github.com/zesterer/chumsky/blob/463226372cf293d45bd5df52bf25d5028243066e/examples/json.rs#L114-L173
-    let message = if let ErrorReason::Custom(message) = error.reason() {
-        message.clone()
+/// Returns an error message if the expression assigned to the variable with the given identifier
+/// refers to that variable, but the variable has no previous value in the context.
+/// If it does have a previous value, that value is used when evaluating the expression,
+/// so e.g. `a = a + 1` increments `a`.
+fn check_self_reference(
+    identifier: &str,
+    expression: &Expression,
+    context: &HashMap<String, Expression>,
+) -> Result<(), String> {
+    if !expression.free_of(identifier) && !context.contains_key(identifier) {
+        Err(format!(
+            "The variable {} cannot be defined in terms of itself, because it does not have a value yet.",
+            identifier,
+        ))
     } else {
-        format!(
-            "{}, expected {}",
-            if error.found().is_some() {
-                "Unexpected token"
-            } else {
-                "Unexpected end of input"
-            },
-            if error.expected().len() == 0 {
-                "something else".to_string()
+        Ok(())
+    }
+}
+
+/// Returns the result of evaluating the expression if it is purely a reference
+/// to previous outputs (`out` or `out[i]` with a valid literal index `i`),
+/// or `None` otherwise. Outputs are stored in evaluated form, so this avoids
+/// walking (and cloning) the potentially huge stored expressions again.
+/// Outputs containing variables that are defined in the context (e.g. `a + 2`
+/// after `a = 1`) would change if evaluated again, so references to them
+/// are not resolved here.
+fn output_reference(
+    expression: &Expression,
+    outputs: &[Rc<Expression>],
+    context: &HashMap<String, Expression>,
+) -> Option<Expression> {
+    use savage_core::expression::Expression::*;
+
+    let unchanged = |output: &Expression| {
+        output
+            .variables()
+            .iter()
+            .all(|identifier| !context.contains_key(identifier))
+    };
+
+    match expression {
+        Variable(identifier) if identifier == "out" => {
+            if outputs.iter().all(|output| unchanged(output)) {
+                Some(Vector(savage_core::expression::Vector::from_vec(
+                    outputs.iter().map(|output| (**output).clone()).collect(),
+                )))
             } else {
-                error
-                    .expected()
-                    .map(|expected| match expected {
-                        Some(expected) => expected.to_string(),
-                        None => "end of input".to_string(),
-                    })
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            },
-        )
+                None
+            }
+        }
+        VectorElement(vector, i) => match (&**vector, &**i) {
+            (Variable(identifier), Integer(i)) if identifier == "out" => outputs
+                .get(usize::try_from(i).ok()?)
+                .filter(|output| unchanged(output))
+                .map(|output| (**output).clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns a shared pointer to the output, reusing the pointer of a previous output
+/// that is structurally identical to it if there is one, so that repeated results
+/// are stored only once. Expressions cannot be hashed, so previous outputs
+/// are found by linear search.
+fn intern_output(output: Expression, outputs: &[Rc<Expression>]) -> Rc<Expression> {
+    outputs
+        .iter()
+        .find(|previous_output| ***previous_output == output)
+        .cloned()
+        .unwrap_or_else(|| Rc::new(output))
+}
+
+/// Stores the output after the previous outputs, and updates the context
+/// so that `out` refers to all outputs and `ans` to the most recent one.
+fn store_output(
+    output: Expression,
+    outputs: &mut Vec<Rc<Expression>>,
+    context: &mut HashMap<String, Expression>,
+) {
+    outputs.push(intern_output(output, outputs));
+
+    let output = outputs.last().unwrap();
+
+    // Only the new output is appended, rather than cloning all outputs again.
+    let out = match context.remove("out") {
+        Some(Expression::Vector(out)) => out,
+        _ => Vector::from_vec(Vec::new()),
     };
+    let length = out.len();
 
-    let report = Report::build(ReportKind::Error, (), error.span().start)
-        .with_message(message)
-        .with_label(
-            Label::new(error.span())
-                .with_message(match error.reason() {
-                    ErrorReason::Custom(message) => message.clone(),
-                    _ => format!(
-                        "Unexpected {}",
-                        error
-                            .found()
-                            .map(|c| format!("token {}", c.fg(Color::Red)))
-                            .unwrap_or_else(|| "end of input".to_string()),
-                    ),
-                })
-                .with_color(Color::Red),
-        );
+    context.insert(
+        "out".to_owned(),
+        Expression::Vector(out.insert_row(length, (**output).clone())),
+    );
 
-    let report = match error.reason() {
-        ErrorReason::Unclosed { span, delimiter } => report.with_label(
-            Label::new(span.clone())
-                .with_message(format!(
-                    "Unclosed delimiter {}",
-                    delimiter.fg(Color::Yellow),
-                ))
-                .with_color(Color::Yellow),
-        ),
-        ErrorReason::Unexpected => report,
-        ErrorReason::Custom(_) => report,
+    context.insert("ans".to_owned(), (**output).clone());
+}
+
+/// Prints the output with the given index, formatted using the given display options.
+/// If a representation is given, all rational numbers in the output are printed
+/// using that representation, regardless of how they were entered.
+fn print_output(
+    editor: &Editor<InputHelper>,
+    index: usize,
+    output: &Expression,
+    representation: Option<&RationalRepresentation>,
+    options: DisplayOptions,
+) {
+    let string = match representation {
+        Some(representation) => output
+            .with_representation(representation)
+            .display(options)
+            .to_string(),
+        None => output.display(options).to_string(),
     };
 
-    report.finish()
+    println!(
+        "{}{}",
+        Style::new().bold().paint(format!("out[{}]: ", index)),
+        editor.helper().unwrap().highlight(&string, usize::MAX),
+    );
+}
+
+/// Prints the error message, which is not associated with a part of the input,
+/// in the given format. The kind of error is only included in JSON output.
+fn print_error(kind: &str, message: &str, error_format: ErrorFormat) {
+    match error_format {
+        ErrorFormat::Pretty => println!("Error: {}", message),
+        ErrorFormat::Json => println!("{}", error_json(kind, message)),
+    }
+}
+
+/// Prints the evaluation error for the given input line,
+/// which is parsed using the syntax of the given mode, in the given format.
+fn print_evaluate_error(error: EvaluateError, line: &str, mode: Mode, error_format: ErrorFormat) {
+    // Spans are only needed in the (rare) case of an error,
+    // so the line is simply parsed again to obtain them.
+    let span = command::parse_with_spans(line, mode)
+        .ok()
+        .and_then(|(_, spans)| spans.get(error.offending_expression()));
+
+    match error_format {
+        ErrorFormat::Pretty => {
+            if let Some(span) = span {
+                format_evaluate_error(&error, span)
+                    .print(Source::from(line))
+                    .expect("unable to print evaluation error");
+            } else {
+                println!("Error: {}", evaluate_error_message(&error));
+            }
+        }
+        ErrorFormat::Json => println!("{}", evaluate_error_json(&error, span)),
+    }
+}
+
+/// Prints the parse errors for the given input line in the given format,
+/// merged into a single report.
+fn print_parse_errors(errors: Vec<ParseError>, line: &str, error_format: ErrorFormat) {
+    if let Some(error) = merge_parse_errors(errors) {
+        match error_format {
+            ErrorFormat::Pretty => format_parse_error(error)
+                .print(Source::from(line))
+                .expect("unable to print parse error"),
+            ErrorFormat::Json => println!("{}", parse_error_json(&error)),
+        }
+    }
+}
+
+/// Error that occurred while trying to define a variable or function.
+#[derive(Debug)]
+enum DefinitionError {
+    /// Definition that is not allowed, with a message explaining why.
+    Invalid(String),
+    /// Error that occurred while evaluating the assigned expression
+    /// (boxed because evaluation errors are much larger than messages).
+    Evaluate(Box<EvaluateError>),
+}
+
+/// Prints the definition error for the given input line,
+/// which is parsed using the syntax of the given mode, in the given format.
+fn print_definition_error(
+    error: DefinitionError,
+    line: &str,
+    mode: Mode,
+    error_format: ErrorFormat,
+) {
+    match error {
+        DefinitionError::Invalid(message) => print_error("definition", &message, error_format),
+        DefinitionError::Evaluate(error) => print_evaluate_error(*error, line, mode, error_format),
+    }
+}
+
+/// Returns an error listing the variables if there are any.
+fn check_undefined_variables(variables: HashSet<String>) -> Result<(), DefinitionError> {
+    if variables.is_empty() {
+        Ok(())
+    } else {
+        Err(DefinitionError::Invalid(format!(
+            "The assigned expression contains the undefined variable(s) {}.",
+            Vec::from_iter(variables).join(", "),
+        )))
+    }
+}
+
+/// Assigns the value of the expression to the variable with the given identifier
+/// in the context, and returns that value.
+fn define_variable(
+    identifier: &str,
+    expression: &Expression,
+    context: &mut HashMap<String, Expression>,
+) -> Result<Expression, DefinitionError> {
+    if RESERVED_IDENTIFIERS.contains(identifier) {
+        return Err(DefinitionError::Invalid(format!(
+            "\"{}\" is a reserved identifier and cannot be used as a variable name.",
+            identifier,
+        )));
+    }
+
+    check_self_reference(identifier, expression, context).map_err(DefinitionError::Invalid)?;
+
+    let value = expression
+        .evaluate(context)
+        .map_err(|error| DefinitionError::Evaluate(Box::new(error)))?;
+
+    check_undefined_variables(value.variables())?;
+
+    context.insert(identifier.to_owned(), value.clone());
+
+    Ok(value)
+}
+
+/// Defines the function with the given identifier and argument identifiers
+/// in the context, and returns its body, which is the result of evaluating
+/// the expression with the arguments left undefined.
+fn define_function(
+    identifier: &str,
+    argument_identifiers: &[String],
+    expression: &Expression,
+    context: &mut HashMap<String, Expression>,
+) -> Result<Expression, DefinitionError> {
+    if RESERVED_IDENTIFIERS.contains(identifier) {
+        return Err(DefinitionError::Invalid(format!(
+            "\"{}\" is a reserved identifier and cannot be used as a function name.",
+            identifier,
+        )));
+    }
+
+    let mut inner_context = context.clone();
+
+    for argument_identifier in argument_identifiers {
+        if RESERVED_IDENTIFIERS.contains(argument_identifier) {
+            return Err(DefinitionError::Invalid(format!(
+                "\"{}\" is a reserved identifier and cannot be used as an argument name.",
+                argument_identifier,
+            )));
+        }
+
+        if argument_identifiers
+            .iter()
+            .filter(|&id| id == argument_identifier)
+            .count()
+            > 1
+        {
+            return Err(DefinitionError::Invalid(format!(
+                "The name \"{}\" is used for more than one argument.",
+                argument_identifier,
+            )));
+        }
+
+        inner_context.remove(argument_identifier);
+    }
+
+    let body = expression
+        .evaluate(&inner_context)
+        .map_err(|error| DefinitionError::Evaluate(Box::new(error)))?;
+
+    let mut variables = body.variables();
+
+    for argument_identifier in argument_identifiers {
+        variables.remove(argument_identifier);
+    }
+
+    check_undefined_variables(variables)?;
+
+    let argument_identifiers = argument_identifiers.to_vec();
+    let expression = body.clone();
+
+    context.insert(
+        identifier.to_owned(),
+        Expression::Function(
+            identifier.to_owned(),
+            Rc::new(Function::new(move |self_expression, arguments, _| {
+                if arguments.len() != argument_identifiers.len() {
+                    return Err(EvaluateError::InvalidNumberOfArguments {
+                        expression: self_expression.clone(),
+                        min_number: argument_identifiers.len(),
+                        max_number: argument_identifiers.len(),
+                        given_number: arguments.len(),
+                    });
+                }
+
+                // Both the default context and the outer context the function is being
+                // evaluated in can be ignored, since it was already checked that the
+                // expression contains no variables other than the argument identifiers.
+                let mut context = HashMap::new();
+
+                for (identifier, argument) in argument_identifiers.iter().zip(arguments) {
+                    context.insert(identifier.clone(), argument.clone());
+                }
+
+                expression.evaluate(&context)
+            })),
+        ),
+    );
+
+    Ok(body)
+}
+
+/// Appends the definition to the definitions, replacing any previous definition
+/// of a variable or function with the same identifier.
+fn record_definition(definition: Definition, definitions: &mut Vec<Definition>) {
+    definitions
+        .retain(|previous_definition| previous_definition.identifier() != definition.identifier());
+
+    definitions.push(definition);
+}
+
+/// Makes the definitions of the session in order in the context, which is expected
+/// to be a default context, and stores the outputs of the session, or returns
+/// an error message if a definition fails. Outputs are evaluated without the session's
+/// definitions, so that they remain the same even if they contain variables
+/// that were defined later.
+fn restore_session(
+    session: &Session,
+    context: &mut HashMap<String, Expression>,
+    outputs: &mut Vec<Rc<Expression>>,
+) -> Result<(), String> {
+    for definition in &session.definitions {
+        let result = match definition {
+            Definition::Variable(identifier, value) => define_variable(identifier, value, context),
+            Definition::Function(identifier, argument_identifiers, body) => {
+                define_function(identifier, argument_identifiers, body, context)
+            }
+        };
+
+        result.map_err(|error| {
+            format!(
+                "Unable to restore the definition of {}: {}",
+                definition.identifier(),
+                match error {
+                    DefinitionError::Invalid(message) => message,
+                    DefinitionError::Evaluate(error) => evaluate_error_message(&error),
+                },
+            )
+        })?;
+    }
+
+    context.insert(
+        "out".to_owned(),
+        Expression::Vector(Vector::from_vec(Vec::new())),
+    );
+
+    let output_context = default_context();
+
+    for output in &session.outputs {
+        let output = output
+            .evaluate(&output_context)
+            .map_err(|error| evaluate_error_message(&error))?;
+
+        store_output(output, outputs, context);
+    }
+
+    Ok(())
 }
 
 fn main() {
     use crate::command::Command::*;
 
+    // Errors are printed as JSON by default if input is not coming from a terminal,
+    // as that usually means that Savage is being run by a script or another program.
+    let mut error_format = if stdin().is_tty() {
+        ErrorFormat::Pretty
+    } else {
+        ErrorFormat::Json
+    };
+
+    for argument in env::args().skip(1) {
+        match argument
+            .strip_prefix("--errors=")
+            .map(str::parse::<ErrorFormat>)
+        {
+            Some(Ok(format)) => error_format = format,
+            Some(Err(message)) => {
+                eprintln!("Error: {}", message);
+                process::exit(1);
+            }
+            None => {
+                eprintln!("Error: Unknown argument \"{}\".", argument);
+                eprintln!("Usage: savage [--errors=pretty|json]");
+                process::exit(1);
+            }
+        }
+    }
+
     let history_path = ProjectDirs::from("com.worldwidemann", "", "Savage")
         .expect("unable to locate data directory")
         .data_dir()
@@ -120,16 +490,30 @@ fn main() {
         Style::new().bold().paint("Ctrl+C"),
     );
 
-    let mut outputs = Vec::new();
+    let mut outputs = Vec::<Rc<Expression>>::new();
 
     let mut context = default_context();
 
+    // Representation that overrides the preferred representations
+    // of rational numbers in outputs, as set with the `:repr` command.
+    let mut representation = None;
+
+    // Number of significant digits for non-terminating decimals in outputs,
+    // as set with the `:precision` command.
+    let mut precision = DEFAULT_PRECISION;
+
+    // Syntax used for parsing expressions, as set with the `:mode` command.
+    let mut mode = Mode::default();
+
+    // Definitions of variables and functions, which are saved by the `:session` command.
+    let mut definitions = Vec::new();
+
     context.insert(
         "out".to_owned(),
-        Expression::Vector(Vector::from_vec(outputs.clone())),
+        Expression::Vector(Vector::from_vec(Vec::new())),
     );
 
-    'outer: loop {
+    loop {
         println!();
 
         match editor.readline("in: ") {
@@ -142,126 +526,75 @@ fn main() {
 
                 editor.add_history_entry(line);
 
-                match line.parse::<Command>() {
-                    Ok(EvaluateExpression(expression)) => match expression.evaluate(&context) {
-                        Ok(output) => {
-                            println!(
-                                "{}{}",
-                                Style::new()
-                                    .bold()
-                                    .paint(format!("out[{}]: ", outputs.len())),
-                                editor
-                                    .helper()
-                                    .unwrap()
-                                    .highlight(&output.to_string(), usize::MAX),
-                            );
+                match command::parse_with_spans(line, mode).map(|(command, _)| command) {
+                    Ok(command @ (EvaluateExpression(_) | CallFunction(_, _))) => {
+                        let expression = match command {
+                            EvaluateExpression(expression) => expression,
+                            // Function commands are evaluated like the equivalent function calls.
+                            CallFunction(identifier, arguments) => fun(var(identifier), arguments),
+                            _ => unreachable!(),
+                        };
 
-                            outputs.push(output);
-
-                            context.insert(
-                                "out".to_owned(),
-                                Expression::Vector(Vector::from_vec(outputs.clone())),
-                            );
-                        }
-                        Err(error) => println!("Error: {:#?}", error),
-                    },
-                    Ok(DefineVariable(identifier, expression)) => {
-                        if RESERVED_IDENTIFIERS.contains(&identifier) {
-                            println!("Error: \"{}\" is a reserved identifier and cannot be used as a variable name.", identifier);
-                            continue;
-                        }
-
-                        match expression.evaluate(&context) {
-                            Ok(expression) => {
-                                let variables = expression.variables();
-
-                                if !variables.is_empty() {
-                                    println!("Error: The assigned expression contains the undefined variable(s) {}.", Vec::from_iter(variables).join(", "));
-                                    continue;
-                                }
+                        match output_reference(&expression, &outputs, &context)
+                            .map_or_else(|| expression.evaluate(&context), Ok)
+                        {
+                            Ok(output) => {
+                                print_output(
+                                    &editor,
+                                    outputs.len(),
+                                    &output,
+                                    representation.as_ref(),
+                                    DisplayOptions {
+                                        significant_digits: Some(precision),
+                                        ..OUTPUT_DISPLAY_OPTIONS
+                                    },
+                                );
 
-                                context.insert(identifier, expression);
+                                store_output(output, &mut outputs, &mut context);
                             }
-                            Err(error) => println!("Error: {:#?}", error),
+                            Err(error) => print_evaluate_error(error, line, mode, error_format),
                         }
                     }
-                    Ok(DefineFunction(identifier, argument_identifiers, expression)) => {
-                        if RESERVED_IDENTIFIERS.contains(&identifier) {
-                            println!("Error: \"{}\" is a reserved identifier and cannot be used as a function name.", identifier);
-                            continue;
-                        }
+                    Ok(ShowSteps(expression)) => {
+                        // The expression is not evaluated, as evaluation would
+                        // already apply the simplifications whose steps are shown.
+                        let output =
+                            Expression::Vector(Vector::from_vec(expression.simplify_steps()));
 
-                        let mut inner_context = context.clone();
-
-                        for argument_identifier in &argument_identifiers {
-                            if RESERVED_IDENTIFIERS.contains(argument_identifier) {
-                                println!("Error: \"{}\" is a reserved identifier and cannot be used as an argument name.", argument_identifier);
-                                continue 'outer;
-                            }
-
-                            if argument_identifiers
-                                .iter()
-                                .filter(|&id| id == argument_identifier)
-                                .count()
-                                > 1
-                            {
-                                println!(
-                                    "Error: The name \"{}\" is used for more than one argument.",
-                                    argument_identifier,
-                                );
-                                continue 'outer;
-                            }
+                        print_output(
+                            &editor,
+                            outputs.len(),
+                            &output,
+                            representation.as_ref(),
+                            DisplayOptions {
+                                significant_digits: Some(precision),
+                                ..OUTPUT_DISPLAY_OPTIONS
+                            },
+                        );
 
-                            inner_context.remove(argument_identifier);
+                        store_output(output, &mut outputs, &mut context);
+                    }
+                    Ok(DefineVariable(identifier, expression)) => {
+                        match define_variable(&identifier, &expression, &mut context) {
+                            Ok(value) => record_definition(
+                                Definition::Variable(identifier, value),
+                                &mut definitions,
+                            ),
+                            Err(error) => print_definition_error(error, line, mode, error_format),
                         }
-
-                        match expression.evaluate(&inner_context) {
-                            Ok(expression) => {
-                                let mut variables = expression.variables();
-
-                                for argument_identifier in &argument_identifiers {
-                                    variables.remove(argument_identifier);
-                                }
-
-                                if !variables.is_empty() {
-                                    println!("Error: The assigned expression contains the undefined variable(s) {}.", Vec::from_iter(variables).join(", "));
-                                    continue;
-                                }
-
-                                context.insert(
-                                    identifier.clone(),
-                                    Expression::Function(
-                                        identifier,
-                                        Rc::new(move |self_expression, arguments, _| {
-                                            if arguments.len() != argument_identifiers.len() {
-                                                return Err(
-                                                    EvaluateError::InvalidNumberOfArguments {
-                                                        expression: self_expression.clone(),
-                                                        min_number: argument_identifiers.len(),
-                                                        max_number: argument_identifiers.len(),
-                                                        given_number: arguments.len(),
-                                                    },
-                                                );
-                                            }
-
-                                            // Both the default context and the outer context the function is being
-                                            // evaluated in can be ignored, since it was already checked that the
-                                            // expression contains no variables other than the argument identifiers.
-                                            let mut context = HashMap::new();
-
-                                            for (identifier, argument) in
-                                                argument_identifiers.iter().zip(arguments)
-                                            {
-                                                context
-                                                    .insert(identifier.clone(), argument.clone());
-                                            }
-
-                                            expression.evaluate(&context)
-                                        }),
-                                    ),
-                                );
-                            }
-                            Err(error) => println!("Error: {:#?}", error),
+                    }
+                    Ok(DefineFunction(identifier, argument_identifiers, expression)) => {
+                        match define_function(
+                            &identifier,
+                            &argument_identifiers,
+                            &expression,
+                            &mut context,
+                        ) {
+                            Ok(body) => record_definition(
+                                Definition::Function(identifier, argument_identifiers, body),
+                                &mut definitions,
+                            ),
+                            Err(error) => print_definition_error(error, line, mode, error_format),
                         }
                     }
                     Ok(ShowHelp(function_name)) => {
@@ -271,29 +604,118 @@ fn main() {
                             {
                                 show_help(function_help_text.clone()).expect("unable to show help");
                             } else {
-                                println!(
-                                    "Error: No help text available for the function {}.",
-                                    function_name,
+                                print_error(
+                                    "help",
+                                    &format!(
+                                        "No help text available for the function {}.",
+                                        function_name,
+                                    ),
+                                    error_format,
                                 );
                             }
                         } else {
                             show_help(HELP_TEXT.clone()).expect("unable to show help");
                         }
                     }
-                    Err(errors) => {
-                        for error in errors {
-                            format_parse_error(error)
-                                .print(Source::from(line))
-                                .expect("unable to print parse error");
+                    Ok(ShowCategoryHelp(category)) => {
+                        if let Some(category_help_text) = category_help_text(&category) {
+                            show_help(category_help_text).expect("unable to show help");
+                        } else {
+                            print_error(
+                                "help",
+                                &format!("No functions in the category {}.", category),
+                                error_format,
+                            );
                         }
                     }
+                    Ok(ListFunctions) => {
+                        for (category, functions) in functions_by_category() {
+                            println!(
+                                "{}: {}",
+                                Style::new().bold().paint(category),
+                                functions
+                                    .iter()
+                                    .map(|metadata| metadata.name)
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                            );
+                        }
+                    }
+                    Ok(SetSeed(seed)) => seed_context(&mut context, seed),
+                    Ok(SetRepresentation(new_representation)) => {
+                        representation = new_representation
+                    }
+                    Ok(SetPrecision(new_precision)) => {
+                        precision = new_precision.unwrap_or(DEFAULT_PRECISION)
+                    }
+                    Ok(SetMode(new_mode)) => mode = new_mode,
+                    Ok(SaveSession(path)) => {
+                        let session = Session {
+                            definitions: definitions.clone(),
+                            outputs: outputs.iter().map(|output| (**output).clone()).collect(),
+                            representation,
+                            precision,
+                            mode,
+                        };
+
+                        match fs::write(&path, session.to_string()) {
+                            Ok(()) => println!("Session saved to {}.", path),
+                            Err(error) => print_error(
+                                "session",
+                                &format!("Unable to save session: {}", error),
+                                error_format,
+                            ),
+                        }
+                    }
+                    Ok(LoadSession(path)) => {
+                        let mut new_context = default_context();
+                        let mut new_outputs = Vec::new();
+
+                        // The current session is only replaced if the whole session
+                        // could be restored.
+                        match fs::read_to_string(&path)
+                            .map_err(|error| format!("Unable to load session: {}", error))
+                            .and_then(|string| string.parse::<Session>())
+                            .and_then(|session| {
+                                restore_session(&session, &mut new_context, &mut new_outputs)
+                                    .map(|_| session)
+                            }) {
+                            Ok(session) => {
+                                context = new_context;
+                                outputs = new_outputs;
+                                definitions = session.definitions;
+                                representation = session.representation;
+                                precision = session.precision;
+                                mode = session.mode;
+
+                                println!("Session loaded from {}.", path);
+                            }
+                            Err(message) => print_error("session", &message, error_format),
+                        }
+                    }
+                    Ok(ShowFullOutput(index)) => {
+                        match index.or_else(|| outputs.len().checked_sub(1)) {
+                            Some(index) if index < outputs.len() => print_output(
+                                &editor,
+                                index,
+                                &outputs[index],
+                                representation.as_ref(),
+                                DisplayOptions {
+                                    significant_digits: Some(precision),
+                                    ..DisplayOptions::default()
+                                },
+                            ),
+                            _ => print_error("output", "There is no such output.", error_format),
+                        }
+                    }
+                    Err(errors) => print_parse_errors(errors, line, error_format),
                 }
             }
             Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
                 break;
             }
             Err(error) => {
-                println!("Error: {:#?}", error);
+                print_error("input", &format!("{:#?}", error), error_format);
                 break;
             }
         }
@@ -310,3 +732,310 @@ fn main() {
         .save_history(&history_path)
         .expect("unable to save input history");
 }
+
+#[cfg(test)]
+mod tests {
+    use savage_core::{
+        evaluate::default_context,
+        expression::{Expression, RationalRepresentation, Vector},
+        helpers::*,
+        parse::Mode,
+        print::DisplayOptions,
+    };
+
+    use std::{collections::HashMap, rc::Rc};
+
+    use crate::{
+        check_self_reference,
+        command::Command,
+        define_function, define_variable,
+        error::error_json,
+        intern_output, output_reference, record_definition, restore_session,
+        session::{Definition, Session},
+        store_output, DefinitionError, DEFAULT_PRECISION,
+    };
+
+    #[test]
+    fn output_references() {
+        let outputs = [int(1), var("a") + int(2), com(1, 2, 3, 4)].map(Rc::new);
+        let mut context = default_context();
+
+        let t = |string: &str, context: &HashMap<String, Expression>| {
+            output_reference(&string.parse().unwrap(), &outputs, context)
+        };
+
+        assert_eq!(t("out[0]", &context), Some(int(1)));
+        assert_eq!(t(" out[ 1 ] ", &context), Some(var("a") + int(2)));
+        assert_eq!(t("out[2]", &context), Some(com(1, 2, 3, 4)));
+        assert_eq!(
+            t("out", &context),
+            Some(Expression::Vector(
+                vec![int(1), var("a") + int(2), com(1, 2, 3, 4)].into()
+            )),
+        );
+
+        assert_eq!(t("out[3]", &context), None);
+        assert_eq!(t("out[-1]", &context), None);
+        assert_eq!(t("out[0] + 1", &context), None);
+        assert_eq!(t("out[1 + 1]", &context), None);
+        assert_eq!(t("a[0]", &context), None);
+
+        context.insert("a".to_owned(), int(1));
+
+        assert_eq!(t("out[0]", &context), Some(int(1)));
+        assert_eq!(t("out[1]", &context), None);
+        assert_eq!(t("out", &context), None);
+    }
+
+    #[test]
+    fn stored_outputs() {
+        let mut outputs = Vec::new();
+        let mut context = default_context();
+
+        let mut t = |input: &str, output: &str| {
+            let result = input
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(&context)
+                .unwrap();
+            assert_eq!(result.to_string(), output);
+            store_output(result, &mut outputs, &mut context);
+        };
+
+        t("2 + 2", "4");
+        t("ans * 3", "12");
+        t("ans - out[0]", "8");
+        t("a + ans", "a + 8");
+        t("ans", "a + 8");
+        t("out", "[4, 12, 8, a + 8, a + 8]");
+    }
+
+    #[test]
+    fn interned_outputs() {
+        let mut outputs = Vec::new();
+
+        for output in [int(1), var("a") + int(2), int(1), var("a") + int(2), int(2)] {
+            outputs.push(intern_output(output, &outputs));
+        }
+
+        assert!(Rc::ptr_eq(&outputs[0], &outputs[2]));
+        assert!(Rc::ptr_eq(&outputs[1], &outputs[3]));
+        assert!(!Rc::ptr_eq(&outputs[0], &outputs[4]));
+
+        assert_eq!(*outputs[2], int(1));
+        assert_eq!(*outputs[3], var("a") + int(2));
+        assert_eq!(*outputs[4], int(2));
+    }
+
+    #[test]
+    fn definition_error_json() {
+        let mut context = default_context();
+
+        let message = match define_variable("q", &(var("q") + int(1)), &mut context) {
+            Err(DefinitionError::Invalid(message)) => message,
+            result => panic!("unexpected result {:?}", result),
+        };
+
+        assert_eq!(
+            error_json("definition", &message),
+            "{\"kind\": \"definition\", \"message\": \"The variable q cannot be defined in terms of itself, because it does not have a value yet.\"}",
+        );
+    }
+
+    #[test]
+    fn self_references() {
+        let mut context = default_context();
+
+        let definition = |string: &str| match string.parse() {
+            Ok(Command::DefineVariable(identifier, expression)) => (identifier, expression),
+            _ => unreachable!(),
+        };
+
+        let (identifier, expression) = definition("a = a + 1");
+
+        assert!(check_self_reference(&identifier, &expression, &context).is_err());
+
+        let (identifier, expression) = definition("a = b + 1");
+
+        assert_eq!(
+            check_self_reference(&identifier, &expression, &context),
+            Ok(()),
+        );
+
+        context.insert("a".to_owned(), int(2));
+
+        let (identifier, expression) = definition("a = a + 1");
+
+        assert_eq!(
+            check_self_reference(&identifier, &expression, &context),
+            Ok(()),
+        );
+        assert_eq!(expression.evaluate(&context), Ok(int(3)));
+    }
+
+    #[test]
+    fn sessions() {
+        let mut context = default_context();
+        let mut definitions = Vec::new();
+        let mut outputs = Vec::new();
+
+        let mut run = |input: &str| match input.parse().unwrap() {
+            Command::DefineVariable(identifier, expression) => {
+                let value = define_variable(&identifier, &expression, &mut context).unwrap();
+                record_definition(Definition::Variable(identifier, value), &mut definitions);
+            }
+            Command::DefineFunction(identifier, argument_identifiers, expression) => {
+                let body = define_function(
+                    &identifier,
+                    &argument_identifiers,
+                    &expression,
+                    &mut context,
+                )
+                .unwrap();
+                record_definition(
+                    Definition::Function(identifier, argument_identifiers, body),
+                    &mut definitions,
+                );
+            }
+            Command::EvaluateExpression(expression) => {
+                let output = expression.evaluate(&context).unwrap();
+                store_output(output, &mut outputs, &mut context);
+            }
+            _ => unreachable!(),
+        };
+
+        run("a = 2");
+        run("f(x) = x ^ a + 1/2");
+        run("a = 0.5 + i");
+        run("f(3)");
+        run("[b, a]");
+
+        let session = Session {
+            definitions,
+            outputs: outputs.iter().map(|output| (**output).clone()).collect(),
+            representation: Some(RationalRepresentation::Decimal),
+            precision: 5,
+            mode: Mode::Programmer,
+        };
+
+        let string = session.to_string();
+
+        assert_eq!(
+            string,
+            "representation decimal\n\
+             precision 5\n\
+             mode programmer\n\
+             function f(x) = x ^ 2 + 1/2\n\
+             variable a = 0.5 + i\n\
+             output 19/2\n\
+             output [b, 0.5 + i]\n",
+        );
+
+        let restored_session = string.parse::<Session>().unwrap();
+
+        assert_eq!(restored_session.representation, session.representation);
+        assert_eq!(restored_session.precision, session.precision);
+        assert_eq!(restored_session.mode, session.mode);
+
+        let mut context = default_context();
+        let mut outputs = Vec::new();
+
+        restore_session(&restored_session, &mut context, &mut outputs).unwrap();
+
+        let t = |input: &str, output: &str| {
+            assert_eq!(
+                input
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&context)
+                    .unwrap()
+                    .to_string(),
+                output,
+            );
+        };
+
+        t("a", "0.5 + i");
+        t("f(2)", "9/2");
+        t("out[0] * 2", "19");
+        t("out[1]", "[b, 0.5 + i]");
+        t("ans", "[b, 0.5 + i]");
+        assert_eq!(outputs.len(), 2);
+
+        assert!("precision 0".parse::<Session>().is_err());
+        assert!("precision 4294967296".parse::<Session>().is_err());
+        assert!("variable a + 1".parse::<Session>().is_err());
+        assert!("function a = 1".parse::<Session>().is_err());
+        assert!("output".parse::<Session>().is_err());
+    }
+
+    #[test]
+    fn session_representations() {
+        let mut context = default_context();
+        let mut definitions = Vec::new();
+        let mut outputs = Vec::new();
+
+        for (identifier, value) in [("x", ratd(3, 14)), ("y", ratu(2, 4)), ("z", ratd(1, 4))] {
+            let value = define_variable(identifier, &value, &mut context).unwrap();
+            record_definition(
+                Definition::Variable(identifier.to_owned(), value),
+                &mut definitions,
+            );
+        }
+
+        store_output(
+            Expression::Vector(Vector::from_vec(vec![
+                ratd(3, 14),
+                ratu(2, 4),
+                comd(1, 3, 1, 1),
+            ])),
+            &mut outputs,
+            &mut context,
+        );
+
+        let session = Session {
+            definitions,
+            outputs: outputs.iter().map(|output| (**output).clone()).collect(),
+            representation: None,
+            precision: DEFAULT_PRECISION,
+            mode: Mode::Standard,
+        };
+
+        let string = session.to_string();
+
+        assert_eq!(
+            string,
+            "precision 15\n\
+             mode standard\n\
+             variable x = decimal(3, 14)\n\
+             variable y = unreduced(2, 4)\n\
+             variable z = 0.25\n\
+             output [decimal(3, 14), unreduced(2, 4), decimal(1, 3, 1, 1)]\n",
+        );
+
+        let mut context = default_context();
+        let mut outputs = Vec::new();
+
+        restore_session(&string.parse().unwrap(), &mut context, &mut outputs).unwrap();
+
+        let t = |input: &str, output: &str| {
+            assert_eq!(
+                input
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&context)
+                    .unwrap()
+                    .display(DisplayOptions {
+                        significant_digits: Some(DEFAULT_PRECISION),
+                        ..DisplayOptions::default()
+                    })
+                    .to_string(),
+                output,
+            );
+        };
+
+        t("x", "0.214285714285714");
+        t("y", "2/4");
+        t("z", "0.25");
+        t("out[0]", "[0.214285714285714, 2/4, 0.333333333333333 + i]");
+    }
+}
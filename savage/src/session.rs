@@ -0,0 +1,293 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use savage_core::{
+    evaluate::default_context,
+    expression::{Complex, Expression, Integer, Rational, RationalRepresentation},
+    helpers::ratu,
+    parse::Mode,
+};
+
+use crate::{
+    command::{self, Command},
+    DEFAULT_PRECISION, MAX_PRECISION,
+};
+
+/// Definition of a variable or function made in the REPL.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Definition {
+    /// Variable with the given identifier and (evaluated) value.
+    Variable(String, Expression),
+    /// Function with the given identifier, argument identifiers, and (evaluated) body.
+    Function(String, Vec<String>, Expression),
+}
+
+impl Definition {
+    /// Returns the identifier of the defined variable or function.
+    pub fn identifier(&self) -> &str {
+        match self {
+            Definition::Variable(identifier, _) | Definition::Function(identifier, _, _) => {
+                identifier
+            }
+        }
+    }
+}
+
+/// State of a REPL session, which can be saved to a file and restored from it
+/// using the `:session` command.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Session {
+    /// Definitions of variables and functions, in the order they were made.
+    pub definitions: Vec<Definition>,
+    /// Outputs, in the order they were printed.
+    pub outputs: Vec<Expression>,
+    /// Representation set with the `:repr` command.
+    pub representation: Option<RationalRepresentation>,
+    /// Number of significant digits set with the `:precision` command.
+    pub precision: usize,
+    /// Syntax set with the `:mode` command.
+    pub mode: Mode,
+}
+
+/// Returns the expression with each number whose representation would be lost
+/// by printing and parsing it again (e.g. `3/14` in decimal representation,
+/// or the unreduced fraction `2/4`) replaced by a call to a marker function
+/// named after the representation, whose arguments describe the number exactly
+/// (e.g. `decimal(3, 14)` or `unreduced(2, 4)`).
+fn mark_representations(expression: &Expression) -> Expression {
+    use savage_core::expression::Expression::{Complex, Rational, Variable};
+    use RationalRepresentation::*;
+
+    match expression {
+        Rational(_, _) | Complex(_, _) => {
+            let parsed = expression
+                .to_string()
+                .parse::<Expression>()
+                .ok()
+                .and_then(|parsed| parsed.evaluate(&default_context()).ok());
+
+            if parsed.as_ref() == Some(expression) {
+                return expression.clone();
+            }
+
+            let (parts, representation) = match expression {
+                Rational(x, representation) => (vec![x.clone()], *representation),
+                Complex(z, representation) => (vec![z.re.clone(), z.im.clone()], *representation),
+                _ => unreachable!(),
+            };
+
+            let mut arguments = Vec::new();
+
+            for part in parts {
+                let factor = match (representation, expression) {
+                    (Unreduced(factor), Rational(_, _)) => Integer::from(factor),
+                    _ => Integer::from(1),
+                };
+
+                arguments.push(Expression::Integer(part.numer() * &factor));
+                arguments.push(Expression::Integer(part.denom() * &factor));
+            }
+
+            if let (Unreduced(factor), Complex(_, _)) = (representation, expression) {
+                arguments.push(Expression::Integer(factor.into()));
+            }
+
+            Expression::FunctionValue(
+                Box::new(Variable(
+                    match representation {
+                        Fraction => "fraction",
+                        Decimal => "decimal",
+                        Unreduced(_) => "unreduced",
+                    }
+                    .to_owned(),
+                )),
+                arguments,
+            )
+        }
+        _ => expression.map_children(&mark_representations),
+    }
+}
+
+/// Returns the expression with calls to marker functions created by
+/// `mark_representations` replaced by the numbers they describe.
+fn unmark_representations(expression: &Expression) -> Expression {
+    use savage_core::expression::Expression::{FunctionValue, Integer, Variable};
+    use RationalRepresentation::*;
+
+    if let FunctionValue(function, arguments) = expression {
+        if let Variable(identifier) = &**function {
+            let integers = arguments
+                .iter()
+                .map(|argument| match argument.evaluate(&HashMap::new()) {
+                    Ok(Integer(n)) => Some(n),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>();
+
+            let rational = |numerator: &self::Integer, denominator: &self::Integer| {
+                if *denominator == self::Integer::from(0) {
+                    None
+                } else {
+                    Some(Rational::new(numerator.clone(), denominator.clone()))
+                }
+            };
+
+            let representation = if identifier == "decimal" {
+                Decimal
+            } else {
+                Fraction
+            };
+
+            let number = match (identifier.as_str(), integers.as_deref()) {
+                ("fraction" | "decimal", Some([n, d])) => {
+                    rational(n, d).map(|x| Expression::Rational(x, representation))
+                }
+                ("fraction" | "decimal", Some([rn, rd, im, id])) => rational(rn, rd)
+                    .zip(rational(im, id))
+                    .map(|(re, im)| Expression::Complex(Complex::new(re, im), representation)),
+                ("unreduced", Some([n, d])) => rational(n, d).map(|_| ratu(n.clone(), d.clone())),
+                ("unreduced", Some([rn, rd, im, id, factor])) => rational(rn, rd)
+                    .zip(rational(im, id))
+                    .zip(u32::try_from(factor).ok())
+                    .map(|((re, im), factor)| {
+                        Expression::Complex(Complex::new(re, im), Unreduced(factor))
+                    }),
+                _ => None,
+            };
+
+            if let Some(number) = number {
+                return number;
+            }
+        }
+    }
+
+    expression.map_children(&unmark_representations)
+}
+
+/// The session file format has one entry per line, consisting of a keyword followed by
+/// the entry's value. Definitions are written using the syntax of the REPL, and expressions
+/// are always written using the standard syntax, regardless of the session's mode.
+/// Numbers whose representation would otherwise be lost are written using
+/// marker functions (see `mark_representations`).
+impl Display for Session {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.representation {
+            Some(RationalRepresentation::Fraction) => writeln!(f, "representation fraction")?,
+            Some(RationalRepresentation::Decimal) => writeln!(f, "representation decimal")?,
+            _ => (),
+        }
+
+        writeln!(f, "precision {}", self.precision)?;
+
+        writeln!(
+            f,
+            "mode {}",
+            match self.mode {
+                Mode::Standard => "standard",
+                Mode::Programmer => "programmer",
+            },
+        )?;
+
+        for definition in &self.definitions {
+            match definition {
+                Definition::Variable(identifier, value) => writeln!(
+                    f,
+                    "variable {} = {}",
+                    identifier,
+                    mark_representations(value),
+                )?,
+                Definition::Function(identifier, argument_identifiers, body) => writeln!(
+                    f,
+                    "function {}({}) = {}",
+                    identifier,
+                    argument_identifiers.join(", "),
+                    mark_representations(body),
+                )?,
+            }
+        }
+
+        for output in &self.outputs {
+            writeln!(f, "output {}", mark_representations(output))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut session = Session {
+            definitions: Vec::new(),
+            outputs: Vec::new(),
+            representation: None,
+            precision: DEFAULT_PRECISION,
+            mode: Mode::default(),
+        };
+
+        for (index, line) in string.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid = || format!("Invalid entry in line {}: {}", index + 1, line);
+
+            let (keyword, value) = line.split_once(' ').ok_or_else(invalid)?;
+
+            match (keyword, value.trim()) {
+                ("representation", "fraction") => {
+                    session.representation = Some(RationalRepresentation::Fraction)
+                }
+                ("representation", "decimal") => {
+                    session.representation = Some(RationalRepresentation::Decimal)
+                }
+                ("precision", value) => {
+                    session.precision = value
+                        .parse()
+                        .ok()
+                        .filter(|&p| p > 0 && p <= MAX_PRECISION)
+                        .ok_or_else(invalid)?
+                }
+                ("mode", "standard") => session.mode = Mode::Standard,
+                ("mode", "programmer") => session.mode = Mode::Programmer,
+                ("variable" | "function", value) => {
+                    let definition =
+                        match (keyword, command::parse_with_spans(value, Mode::Standard)) {
+                            ("variable", Ok((Command::DefineVariable(identifier, value), _))) => {
+                                Definition::Variable(identifier, unmark_representations(&value))
+                            }
+                            (
+                                "function",
+                                Ok((
+                                    Command::DefineFunction(identifier, argument_identifiers, body),
+                                    _,
+                                )),
+                            ) => Definition::Function(
+                                identifier,
+                                argument_identifiers,
+                                unmark_representations(&body),
+                            ),
+                            _ => return Err(invalid()),
+                        };
+
+                    session.definitions.push(definition);
+                }
+                ("output", value) => session.outputs.push(unmark_representations(
+                    &value.parse().map_err(|_| invalid())?,
+                )),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(session)
+    }
+}
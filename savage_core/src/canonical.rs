@@ -0,0 +1,381 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use num::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+    expression::{Complex, Expression, Integer, RationalRepresentation, Type},
+    helpers::*,
+};
+
+/// Largest exponent for which powers of sums are expanded into sums of products.
+pub(crate) const MAX_EXPANSION_EXPONENT: u32 = 32;
+
+/// Product of non-arithmetic expressions ("atoms") raised to nonzero integer powers,
+/// with each atom occurring at most once, sorted by the printed form of the atoms.
+type Monomial = Vec<(Expression, Integer)>;
+
+/// Sum of distinct monomials multiplied by nonzero coefficients.
+type Polynomial = Vec<(Monomial, Complex)>;
+
+/// Returns the polynomial consisting of the atom raised to the power of `exponent`.
+fn atom(expression: Expression, exponent: Integer) -> Polynomial {
+    vec![(vec![(expression, exponent)], Complex::one())]
+}
+
+/// Returns the polynomial consisting of the constant `z`.
+fn constant(z: Complex) -> Polynomial {
+    if z.is_zero() {
+        vec![]
+    } else {
+        vec![(vec![], z)]
+    }
+}
+
+/// Returns the sum of the polynomials, with like terms collected.
+fn add(a: Polynomial, b: Polynomial) -> Polynomial {
+    let mut sum = a;
+
+    for (monomial, coefficient) in b {
+        if let Some(i) = sum.iter().position(|(m, _)| *m == monomial) {
+            sum[i].1 += coefficient;
+
+            if sum[i].1.is_zero() {
+                sum.remove(i);
+            }
+        } else {
+            sum.push((monomial, coefficient));
+        }
+    }
+
+    sum
+}
+
+/// Returns the negation of the polynomial.
+fn negate(a: Polynomial) -> Polynomial {
+    a.into_iter()
+        .map(|(monomial, coefficient)| (monomial, -coefficient))
+        .collect()
+}
+
+/// Returns the product of the monomials.
+fn multiply_monomials(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut product = a.clone();
+
+    for (atom, exponent) in b {
+        if let Some(i) = product.iter().position(|(a, _)| a == atom) {
+            product[i].1 += exponent;
+
+            if product[i].1.is_zero() {
+                product.remove(i);
+            }
+        } else {
+            product.push((atom.clone(), exponent.clone()));
+        }
+    }
+
+    product.sort_by_cached_key(|(atom, _)| atom.to_string());
+
+    product
+}
+
+/// Returns the product of the polynomials, with like terms collected.
+fn multiply(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    let mut product = vec![];
+
+    for (monomial_a, coefficient_a) in a {
+        for (monomial_b, coefficient_b) in b {
+            product = add(
+                product,
+                vec![(
+                    multiply_monomials(monomial_a, monomial_b),
+                    coefficient_a * coefficient_b,
+                )],
+            );
+        }
+    }
+
+    product
+}
+
+/// Returns the polynomial raised to the power of `exponent`, or `None` if the power
+/// is undefined or cannot be expanded into a polynomial.
+fn power(a: &Polynomial, exponent: &Integer) -> Option<Polynomial> {
+    match a.as_slice() {
+        [] => exponent.is_positive().then(Vec::new),
+        [(monomial, coefficient)] => Some(vec![(
+            monomial
+                .iter()
+                .map(|(atom, e)| (atom.clone(), e * exponent))
+                .filter(|(_, e)| !e.is_zero())
+                .collect(),
+            coefficient.powi(exponent.to_i32()?),
+        )]),
+        _ => {
+            let exponent = exponent.to_u32().filter(|e| *e <= MAX_EXPANSION_EXPONENT)?;
+
+            Some((0..exponent).fold(constant(Complex::one()), |p, _| multiply(&p, a)))
+        }
+    }
+}
+
+/// Returns the constant `z` such that `a = z * b`, or `None` if there is no such constant
+/// or `b` is zero. Quotients of sums are otherwise not cancelled, because that would
+/// require factoring the polynomials.
+fn ratio(a: &Polynomial, b: &Polynomial) -> Option<Complex> {
+    let (monomial, coefficient) = b.first()?;
+
+    let z = a.iter().find(|(m, _)| m == monomial)?.1.clone() / coefficient;
+
+    let proportional = a.len() == b.len()
+        && b.iter().all(|(monomial, coefficient)| {
+            a.iter()
+                .any(|(m, c)| m == monomial && *c == coefficient * &z)
+        });
+
+    if proportional {
+        Some(z)
+    } else {
+        None
+    }
+}
+
+/// Returns whether the polynomial contains a vector or matrix,
+/// in which case its factors cannot be reordered.
+fn contains_matrix(a: &Polynomial) -> bool {
+    a.iter().any(|(monomial, _)| {
+        monomial
+            .iter()
+            .any(|(atom, _)| matches!(atom, Expression::Vector(_) | Expression::Matrix(_)))
+    })
+}
+
+/// Returns the number `z` as an expression of the simplest type that can represent it.
+fn number(z: Complex) -> Expression {
+    if !z.im.is_zero() {
+        Expression::Complex(z, RationalRepresentation::Fraction)
+    } else if z.re.is_integer() {
+        Expression::Integer(z.re.to_integer())
+    } else {
+        Expression::Rational(z.re, RationalRepresentation::Fraction)
+    }
+}
+
+/// Returns the polynomial as an expression, with constant terms last.
+fn expression(a: &Polynomial) -> Expression {
+    let mut terms = a.clone();
+
+    // Monomials are ordered lexicographically by their atoms,
+    // with higher powers of the same atom first (e.g. `a^2 + a*b + b^2`).
+    terms.sort_by_cached_key(|(monomial, _)| {
+        (
+            monomial.is_empty(),
+            monomial
+                .iter()
+                .map(|(atom, exponent)| (atom.to_string(), -exponent))
+                .collect::<Vec<_>>(),
+        )
+    });
+
+    let mut sum: Option<Expression> = None;
+
+    for (monomial, coefficient) in terms {
+        let negative = coefficient.re.is_negative()
+            || (coefficient.re.is_zero() && coefficient.im.is_negative());
+
+        let coefficient = if negative && sum.is_some() {
+            -coefficient
+        } else {
+            coefficient
+        };
+
+        let factors = monomial
+            .into_iter()
+            .map(|(atom, exponent)| {
+                if exponent.is_one() {
+                    atom
+                } else {
+                    pow(atom, exponent)
+                }
+            })
+            .reduce(|a, b| a * b);
+
+        let term = match factors {
+            None => number(coefficient),
+            Some(factors) if coefficient.is_one() => factors,
+            Some(factors) if coefficient == -Complex::one() && sum.is_none() => -factors,
+            Some(factors) => number(coefficient) * factors,
+        };
+
+        sum = Some(match sum {
+            None => term,
+            Some(sum) if negative => sum - term,
+            Some(sum) => sum + term,
+        });
+    }
+
+    sum.unwrap_or_else(|| int(0))
+}
+
+impl Expression {
+    /// Returns the polynomial representing the expression,
+    /// with all atoms in canonical form.
+    fn canonical_polynomial(&self) -> Polynomial {
+        use Expression::*;
+
+        match self {
+            Integer(_) | Rational(_, _) | Complex(_, _) => match self.typ() {
+                Type::Number(z, _) => constant(z),
+                _ => unreachable!(),
+            },
+            Negation(a) => negate(a.canonical_polynomial()),
+            Sum(a, b) => add(a.canonical_polynomial(), b.canonical_polynomial()),
+            Difference(a, b) => add(a.canonical_polynomial(), negate(b.canonical_polynomial())),
+            Product(a, b) => {
+                let a = a.canonical_polynomial();
+                let b = b.canonical_polynomial();
+
+                if contains_matrix(&a) && contains_matrix(&b) {
+                    // Matrix multiplication is not commutative.
+                    atom(expression(&a) * expression(&b), 1.into())
+                } else {
+                    multiply(&a, &b)
+                }
+            }
+            Quotient(a, b) => {
+                let a = a.canonical_polynomial();
+                let b = b.canonical_polynomial();
+
+                if let Some(z) = ratio(&a, &b) {
+                    return constant(z);
+                }
+
+                let reciprocal =
+                    power(&b, &(-1).into()).unwrap_or_else(|| atom(expression(&b), (-1).into()));
+
+                multiply(&a, &reciprocal)
+            }
+            Power(a, b) => {
+                let a = a.canonical_polynomial();
+                let b = b.canonical_polynomial();
+
+                let integer_exponent = match b.as_slice() {
+                    [] => Some(crate::expression::Integer::zero()),
+                    [(monomial, z)]
+                        if monomial.is_empty() && z.im.is_zero() && z.re.is_integer() =>
+                    {
+                        Some(z.re.to_integer())
+                    }
+                    _ => None,
+                };
+
+                match integer_exponent {
+                    Some(n) => power(&a, &n).unwrap_or_else(|| {
+                        if n.is_zero() {
+                            // Zero to the power of zero is undefined.
+                            atom(pow(expression(&a), int(0)), 1.into())
+                        } else {
+                            atom(expression(&a), n)
+                        }
+                    }),
+                    None => atom(pow(expression(&a), expression(&b)), 1.into()),
+                }
+            }
+            Equal(a, b) | NotEqual(a, b) | And(a, b) | Or(a, b) => {
+                // The operands of commutative operations are sorted.
+                let mut operands = [a.canonical(), b.canonical()];
+                operands.sort_by_cached_key(|operand| operand.to_string());
+                let [a, b] = operands;
+
+                atom(
+                    match self {
+                        Equal(_, _) => eq(a, b),
+                        NotEqual(_, _) => ne(a, b),
+                        And(_, _) => and(a, b),
+                        Or(_, _) => or(a, b),
+                        _ => unreachable!(),
+                    },
+                    1.into(),
+                )
+            }
+            _ => atom(self.map_children(&Self::canonical), 1.into()),
+        }
+    }
+
+    /// Returns a canonical form of the expression, in which sums and products are
+    /// flattened and expanded, like terms and like factors are collected, operands
+    /// of commutative operations are sorted, and numbers are in fraction representation.
+    /// Arithmetic expressions that are mathematically equal under the assumption that
+    /// all variables denote nonzero numbers (e.g. `a + b` and `b + a`, or `(a + b)^2`
+    /// and `a^2 + 2*a*b + b^2`) have equal canonical forms, except for quotients
+    /// by sums, which are only cancelled if the dividend is a multiple of the divisor.
+    ///
+    /// Unlike evaluation, canonicalization neither substitutes variables nor calls functions.
+    pub fn canonical(&self) -> Self {
+        expression(&self.canonical_polynomial())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expression::Expression;
+
+    #[track_caller]
+    fn c(expressions: &[&str], result: &str) {
+        for expression in expressions {
+            let canonical_expression = expression.parse::<Expression>().unwrap().canonical();
+
+            assert_eq!(canonical_expression.to_string(), result);
+            assert_eq!(canonical_expression.canonical(), canonical_expression);
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        c(&["a + b", "b + a", "(b + 0) + a"], "a + b");
+        c(&["a * b * c", "c * (b * a)", "b * c * a * 1"], "a * b * c");
+        c(&["a - b", "-(b - a)", "a + (-1) * b", "-b + a"], "a - b");
+        c(&["-a - b", "-(a + b)", "-b - a"], "-a - b");
+        c(&["a + a + b", "2 * a + b", "b + 3 * a - a"], "2 * a + b");
+        c(&["a * a * b", "a^2 * b", "b * a * a^3 / a^2"], "a ^ 2 * b");
+        c(&["x / x * y", "y", "x * y / x"], "y");
+        c(&["a - a", "0 * a", "a * b - b * a"], "0");
+        c(&["2 * x / 4", "x / 2", "0.5 * x", "x * 1/2"], "1/2 * x");
+        c(&["1 + 2 + x", "x + 3", "3.0 + x"], "x + 3");
+        c(
+            &["(2 + 3 * i) * x", "x * 2 + 3 * i * x"],
+            "3 * i * x + 2 * x",
+        );
+    }
+
+    #[test]
+    fn expansion() {
+        c(
+            &["(a + b)^2", "a^2 + 2*a*b + b^2", "b^2 + a * (2 * b + a)"],
+            "a ^ 2 + 2 * a * b + b ^ 2",
+        );
+        c(&["(x + 1) * (x - 1)", "x^2 - 1"], "x ^ 2 - 1");
+        c(&["(a + b) / (a + b)", "1"], "1");
+        c(&["(2 * a + 2 * b) / (b + a)", "2"], "2");
+        c(&["(a + b)^(-1) * 2", "2 / (b + a)"], "2 * (a + b) ^ (-1)");
+        c(&["x^y * 2", "2 * x^(y + 0)"], "2 * x ^ y");
+    }
+
+    #[test]
+    fn other_operations() {
+        c(&["a == b", "b == a", "b + 0 == a * 1"], "a == b");
+        c(&["a && (b || c)", "(c || b) && a"], "a && (b || c)");
+        c(&["f(a + b)", "f(b + a)"], "f(a + b)");
+        c(&["[a + b, 2 * c]", "[b + a, c + c]"], "[a + b, 2 * c]");
+        c(&["a < b + 1", "a < 1 + b"], "a < b + 1");
+
+        assert_ne!(
+            "a < b".parse::<Expression>().unwrap().canonical(),
+            "b < a".parse::<Expression>().unwrap().canonical(),
+        );
+        assert_ne!(
+            "[[a]] * [[b]]".parse::<Expression>().unwrap().canonical(),
+            "[[b]] * [[a]]".parse::<Expression>().unwrap().canonical(),
+        );
+    }
+}
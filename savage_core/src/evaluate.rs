@@ -1,13 +1,17 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+};
 
-use num::{One, ToPrimitive, Zero};
+use num::{One, Signed, ToPrimitive, Zero};
 
 use crate::{
-    expression::{Complex, Expression, RationalRepresentation},
-    functions::functions,
+    expression::{Complex, Expression, Integer, Rational, RationalRepresentation},
+    functions::{functions, seeded_functions, Parameter},
 };
 
 /// Error that occurred while trying to evaluate an expression.
@@ -58,6 +62,131 @@ pub enum Error {
         expression: Expression,
         argument: Expression,
     },
+    /// Function expression evaluated with an argument that does not match
+    /// the type of the parameter in that position.
+    InvalidArgumentType {
+        expression: Expression,
+        argument: Expression,
+        /// Zero-based index of the argument.
+        index: usize,
+        parameter: Parameter,
+    },
+    /// Function expression evaluated with a system of equations
+    /// (given as an argument) that has no solution.
+    NoSolution {
+        expression: Expression,
+        equations: Expression,
+    },
+}
+
+impl Error {
+    /// Returns the sub-expression that caused the error,
+    /// which is the best candidate for pointing out to the user.
+    pub fn offending_expression(&self) -> &Expression {
+        use Error::*;
+
+        match self {
+            InvalidOperand { operand, .. } => operand,
+            IncompatibleOperands { expression, .. } => expression,
+            DivisionByZero { divisor, .. } => divisor,
+            ZeroToThePowerOfZero { expression, .. } => expression,
+            IndexOutOfBounds { index, .. } => index,
+            InvalidNumberOfArguments { expression, .. } => expression,
+            InvalidArgument { argument, .. } => argument,
+            InvalidArgumentType { argument, .. } => argument,
+            NoSolution { equations, .. } => equations,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use Error::*;
+
+        match self {
+            InvalidOperand {
+                expression,
+                operand,
+            } => write!(f, "Invalid operand {} in {}", operand, expression),
+            IncompatibleOperands {
+                expression,
+                operand_1,
+                operand_2,
+            } => write!(
+                f,
+                "Incompatible operands {} and {} in {}",
+                operand_1, operand_2, expression,
+            ),
+            DivisionByZero {
+                expression,
+                dividend,
+                divisor,
+            } => write!(
+                f,
+                "Division of {} by {}, which is zero, in {}",
+                dividend, divisor, expression,
+            ),
+            ZeroToThePowerOfZero {
+                expression,
+                base,
+                exponent,
+            } => write!(
+                f,
+                "Zero to the power of zero ({} ^ {}) in {}",
+                base, exponent, expression,
+            ),
+            IndexOutOfBounds {
+                expression,
+                vector_or_matrix,
+                index,
+            } => write!(
+                f,
+                "Index {} out of bounds for {} in {}",
+                index, vector_or_matrix, expression,
+            ),
+            InvalidNumberOfArguments {
+                expression,
+                min_number,
+                max_number,
+                given_number,
+            } => write!(
+                f,
+                "{} argument(s) given in {}, expected {}",
+                given_number,
+                expression,
+                if min_number == max_number {
+                    min_number.to_string()
+                } else {
+                    format!("{} to {}", min_number, max_number)
+                },
+            ),
+            InvalidArgument {
+                expression,
+                argument,
+            } => write!(f, "Invalid argument {} in {}", argument, expression),
+            InvalidArgumentType {
+                expression,
+                argument,
+                index,
+                parameter,
+            } => write!(
+                f,
+                "Invalid argument {} in {}, expected argument {} to be {}",
+                argument,
+                expression,
+                index + 1,
+                parameter,
+            ),
+            NoSolution {
+                expression,
+                equations,
+            } => write!(
+                f,
+                "The system {} in {} has no solution",
+                equations, expression
+            ),
+        }
+    }
 }
 
 /// Returns an evaluation context populated with standard variable and function definitions.
@@ -70,15 +199,83 @@ pub fn default_context() -> HashMap<String, Expression> {
     );
 
     for function in functions() {
-        default_context.insert(
-            function.metadata.name.to_owned(),
-            Expression::Function(function.metadata.name.to_owned(), function.implementation),
-        );
+        for identifier in function.metadata.identifiers() {
+            default_context.insert(
+                identifier.to_owned(),
+                Expression::Function(
+                    function.metadata.name.to_owned(),
+                    function.implementation.clone(),
+                ),
+            );
+        }
     }
 
     default_context
 }
 
+/// Replaces the functions that generate pseudo-random values in the context
+/// with ones whose generator is initialized with the given seed,
+/// making all subsequently generated values reproducible.
+pub fn seed_context(context: &mut HashMap<String, Expression>, seed: u64) {
+    for function in seeded_functions(seed) {
+        for identifier in function.metadata.identifiers() {
+            context.insert(
+                identifier.to_owned(),
+                Expression::Function(
+                    function.metadata.name.to_owned(),
+                    function.implementation.clone(),
+                ),
+            );
+        }
+    }
+}
+
+/// Returns the `n`-th root of the non-negative rational number if that root is rational,
+/// or `None` otherwise.
+fn exact_root(x: &Rational, n: u32) -> Option<Rational> {
+    let root = |x: &Integer| {
+        let root = x.nth_root(n);
+
+        if num::pow(root.clone(), n as usize) == *x {
+            Some(root)
+        } else {
+            None
+        }
+    };
+
+    Some(Rational::new(root(x.numer())?, root(x.denom())?))
+}
+
+/// Returns the principal value of `a ^ b` for a real base `a` and a real exponent `b`
+/// that is not an integer, or `None` if that value is not a complex number
+/// with rational parts. Such exact values exist for non-negative bases with
+/// rational roots (e.g. `4 ^ (1/2) = 2`), and for negative bases if the exponent
+/// is a multiple of `1/2` (e.g. `(-1) ^ (1/2) = i`).
+fn principal_power(a: &Complex, b: &Complex) -> Option<Complex> {
+    if !a.im.is_zero() || !b.im.is_zero() {
+        return None;
+    }
+
+    let p = b.re.numer().to_i32()?;
+    let q = b.re.denom().to_u32()?;
+
+    let root = if !a.re.is_negative() {
+        Complex::new(exact_root(&a.re, q)?, Rational::zero())
+    } else if q == 2 {
+        Complex::new(Rational::zero(), exact_root(&-a.re.clone(), 2)?)
+    } else {
+        // Other principal roots of negative numbers are irrational
+        // (e.g. `(-1) ^ (1/3) = 1/2 + sqrt(3)/2 * i`).
+        return None;
+    };
+
+    if root.is_zero() && p < 0 {
+        return None;
+    }
+
+    Some(root.powi(p))
+}
+
 impl Expression {
     /// Returns the result of performing a single evaluation step on
     /// the unary operator expression `self` with operand `a`, or an error
@@ -88,6 +285,7 @@ impl Expression {
         &self,
         a: &Self,
         context: &HashMap<String, Self>,
+        held: &HashSet<String>,
     ) -> Result<Self, Error> {
         use crate::expression::Expression::*;
         use crate::expression::Type::{Arithmetic, Boolean as Bool, Matrix as Mat, Number as Num};
@@ -95,7 +293,7 @@ impl Expression {
 
         let a_original = a;
 
-        let a = a.evaluate_step(context)?;
+        let a = a.evaluate_step(context, held)?;
 
         match (self, a.typ()) {
             (Negation(_), Bool(_)) | (Not(_), Num(_, _) | Mat(_) | Arithmetic) => {
@@ -153,17 +351,32 @@ impl Expression {
         a: &Self,
         b: &Self,
         context: &HashMap<String, Self>,
+        held: &HashSet<String>,
+    ) -> Result<Self, Error> {
+        // The operator is applied in a separate function, so that its locals
+        // don't take up stack space while recursing into the operands,
+        // which for long sums or products happens once per term.
+        let a_evaluated = a.evaluate_step(context, held)?;
+        let b_evaluated = b.evaluate_step(context, held)?;
+
+        self.apply_binary_operator(a, b, a_evaluated, b_evaluated)
+    }
+
+    /// Returns the result of applying the binary operator of the expression `self`
+    /// to the evaluated operands `a` and `b`, or an error if the operator cannot
+    /// be applied to them. `a_original` and `b_original` are the operands
+    /// before evaluation, which are reported in errors.
+    fn apply_binary_operator(
+        &self,
+        a_original: &Self,
+        b_original: &Self,
+        a: Self,
+        b: Self,
     ) -> Result<Self, Error> {
         use crate::expression::Expression::*;
         use crate::expression::Type::{Arithmetic, Boolean as Bool, Matrix as Mat, Number as Num};
         use Error::*;
 
-        let a_original = a;
-        let b_original = b;
-
-        let a = a.evaluate_step(context)?;
-        let b = b.evaluate_step(context)?;
-
         let a_evaluated = &a;
         let b_evaluated = &b;
 
@@ -266,14 +479,24 @@ impl Expression {
                         }
                     }
                     Power(_, _) => {
+                        // Converting the exponent directly would truncate
+                        // non-integer exponents.
+                        let integer_exponent = if b.im.is_zero() && b.re.is_integer() {
+                            b.re.to_integer().to_i32()
+                        } else {
+                            None
+                        };
+
                         if a.is_zero() && b.is_zero() {
                             Err(ZeroToThePowerOfZero {
                                 expression: self.clone(),
                                 base: a_original.clone(),
                                 exponent: b_original.clone(),
                             })
-                        } else if let Some(b) = b.to_i32() {
+                        } else if let Some(b) = integer_exponent {
                             Ok(Complex(a.powi(b), representation))
+                        } else if let Some(power) = principal_power(&a, &b) {
+                            Ok(Complex(power, representation))
                         } else {
                             // TODO
                             Ok(Power(
@@ -397,51 +620,293 @@ impl Expression {
         }
     }
 
-    /// Returns the result of performing a single evaluation step on the expression,
-    /// or an error if the expression cannot be evaluated. The `context` argument
-    /// can be used to set the values of variables by their identifiers.
-    fn evaluate_step(&self, context: &HashMap<String, Self>) -> Result<Self, Error> {
+    /// Returns the result of performing a single evaluation step on
+    /// the function value expression `self` with the given function
+    /// and arguments, or an error if the expression cannot be evaluated.
+    /// The `context` argument can be used to set the values of variables
+    /// by their identifiers.
+    fn evaluate_step_function_value(
+        &self,
+        function: &Self,
+        arguments: &[Self],
+        context: &HashMap<String, Self>,
+        held: &HashSet<String>,
+    ) -> Result<Self, Error> {
         use crate::expression::Expression::*;
         use crate::expression::Type::{
             Boolean as Bool, Function as Fun, Matrix as Mat, Number as Num,
         };
         use Error::*;
 
-        let expression = self.simplify();
+        let function_original = function;
+
+        let function = function.evaluate_step(context, held)?;
+
+        // Functions that hold their bindings (like `eval_with`) give the bound variables
+        // other values, so their values from the context must neither take effect
+        // nor cause errors while the arguments are evaluated (e.g. `eval_with(1 / a, [a == 2])`
+        // is `1/2` even if `a` is `0` in the context). Bindings that are not given as a literal vector (e.g. a variable whose value
+        // is a vector of bindings) have been evaluated already, so they can only bind
+        // variables without a value in the context, which need not be held.
+        let held = match (&function, arguments.last()) {
+            (Function(_, f), Some(Vector(bindings))) if f.holds_bindings => Cow::Owned(
+                bindings
+                    .iter()
+                    .filter_map(|binding| match binding {
+                        Equal(a, _) => match &**a {
+                            Variable(identifier) => Some(identifier.clone()),
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .chain(held.iter().cloned())
+                    .collect(),
+            ),
+            _ => Cow::Borrowed(held),
+        };
 
-        match &expression {
-            Variable(identifier) => context
-                .get(identifier)
-                .map_or_else(|| Ok(expression), |x| x.evaluate_step(context)),
-            Function(_, _) => Ok(expression),
-            FunctionValue(function, arguments) => {
-                let function_original = function;
+        let mut arguments_evaluated = Vec::new();
 
-                let function = function.evaluate_step(context)?;
+        for argument in arguments {
+            arguments_evaluated.push(argument.evaluate_step(context, &held)?);
+        }
 
-                let mut arguments_evaluated = Vec::new();
+        match function.typ() {
+            Num(_, _) | Mat(_) | Bool(_) => Err(InvalidOperand {
+                expression: self.clone(),
+                operand: function_original.clone(),
+            }),
 
-                for argument in arguments {
-                    arguments_evaluated.push(argument.evaluate_step(context)?);
+            Fun(_, f) => {
+                // Arguments are reported as they were given, rather than
+                // in evaluated form, so they can be located in the input.
+                // Functions may report arguments after converting them
+                // (e.g. a vector to a single-column matrix), so numbers
+                // and matrices are identified by their values.
+                let given_argument = |argument: Self| {
+                    arguments_evaluated
+                        .iter()
+                        .position(|a| {
+                            *a == argument
+                                || matches!(
+                                    (a.typ(), argument.typ()),
+                                    (Num(a, _), Num(b, _)) if a == b,
+                                )
+                                || matches!(
+                                    (a.typ(), argument.typ()),
+                                    (Mat(a), Mat(b)) if a == b,
+                                )
+                        })
+                        .map_or(argument, |i| arguments[i].clone())
+                };
+
+                (f.implementation)(self, &arguments_evaluated, context).map_err(|error| match error
+                {
+                    InvalidArgument {
+                        expression: error_expression,
+                        argument,
+                    } if error_expression == *self => InvalidArgument {
+                        expression: error_expression,
+                        argument: given_argument(argument),
+                    },
+                    NoSolution {
+                        expression: error_expression,
+                        equations,
+                    } if error_expression == *self => NoSolution {
+                        expression: error_expression,
+                        equations: given_argument(equations),
+                    },
+                    InvalidArgumentType {
+                        expression: error_expression,
+                        index,
+                        parameter,
+                        ..
+                    } if error_expression == *self && index < arguments.len() => {
+                        InvalidArgumentType {
+                            expression: error_expression,
+                            argument: arguments[index].clone(),
+                            index,
+                            parameter,
+                        }
+                    }
+                    error => error,
+                })
+            }
+
+            _ => Ok(FunctionValue(Box::new(function), arguments_evaluated)),
+        }
+    }
+
+    /// Returns the result of performing a single evaluation step on
+    /// the vector element expression `self` with the given vector and index,
+    /// or an error if the expression cannot be evaluated. The `context`
+    /// argument can be used to set the values of variables by their
+    /// identifiers.
+    fn evaluate_step_vector_element(
+        &self,
+        vector: &Self,
+        i: &Self,
+        context: &HashMap<String, Self>,
+        held: &HashSet<String>,
+    ) -> Result<Self, Error> {
+        use crate::expression::Expression::*;
+        use crate::expression::Type::{Boolean as Bool, Matrix as Mat, Number as Num};
+        use Error::*;
+
+        let vector_original = vector;
+        let i_original = i;
+
+        let vector = vector.evaluate_step(context, held)?;
+        let i = i.evaluate_step(context, held)?;
+
+        match (vector.typ(), i.typ()) {
+            (Num(_, _) | Bool(_), _) => Err(InvalidOperand {
+                expression: self.clone(),
+                operand: vector_original.clone(),
+            }),
+
+            (_, Mat(_) | Bool(_)) => Err(InvalidOperand {
+                expression: self.clone(),
+                operand: i_original.clone(),
+            }),
+
+            (Mat(vector), Num(i, _)) => {
+                if vector.ncols() != 1 {
+                    Err(InvalidOperand {
+                        expression: self.clone(),
+                        operand: vector_original.clone(),
+                    })
+                } else if let Some(i) = i.to_usize() {
+                    if i >= vector.nrows() {
+                        Err(IndexOutOfBounds {
+                            expression: self.clone(),
+                            vector_or_matrix: vector_original.clone(),
+                            index: i_original.clone(),
+                        })
+                    } else {
+                        Ok(vector[(i, 0)].clone())
+                    }
+                } else {
+                    Err(InvalidOperand {
+                        expression: self.clone(),
+                        operand: i_original.clone(),
+                    })
                 }
+            }
 
-                match function.typ() {
-                    Num(_, _) | Mat(_) | Bool(_) => Err(InvalidOperand {
-                        expression: expression.clone(),
-                        operand: *function_original.clone(),
-                    }),
+            _ => Ok(VectorElement(Box::new(vector), Box::new(i))),
+        }
+    }
 
-                    Fun(_, f) => f(&expression, &arguments_evaluated, context),
+    /// Returns the result of performing a single evaluation step on
+    /// the matrix element expression `self` with the given matrix and indices,
+    /// or an error if the expression cannot be evaluated. The `context`
+    /// argument can be used to set the values of variables by their
+    /// identifiers.
+    fn evaluate_step_matrix_element(
+        &self,
+        matrix: &Self,
+        i: &Self,
+        j: &Self,
+        context: &HashMap<String, Self>,
+        held: &HashSet<String>,
+    ) -> Result<Self, Error> {
+        use crate::expression::Expression::*;
+        use crate::expression::Type::{Boolean as Bool, Matrix as Mat, Number as Num};
+        use Error::*;
+
+        let matrix_original = matrix;
+        let i_original = i;
+        let j_original = j;
+
+        let matrix = matrix.evaluate_step(context, held)?;
+        let i = i.evaluate_step(context, held)?;
+        let j = j.evaluate_step(context, held)?;
+
+        match (matrix.typ(), i.typ(), j.typ()) {
+            (Num(_, _) | Bool(_), _, _) => Err(InvalidOperand {
+                expression: self.clone(),
+                operand: matrix_original.clone(),
+            }),
+
+            (_, Mat(_) | Bool(_), _) => Err(InvalidOperand {
+                expression: self.clone(),
+                operand: i_original.clone(),
+            }),
+
+            (_, _, Mat(_) | Bool(_)) => Err(InvalidOperand {
+                expression: self.clone(),
+                operand: j_original.clone(),
+            }),
 
-                    _ => Ok(FunctionValue(Box::new(function), arguments_evaluated)),
+            (Mat(matrix), Num(i, _), Num(j, _)) => {
+                if let Some(i) = i.to_usize() {
+                    if let Some(j) = j.to_usize() {
+                        if i >= matrix.nrows() {
+                            Err(IndexOutOfBounds {
+                                expression: self.clone(),
+                                vector_or_matrix: matrix_original.clone(),
+                                index: i_original.clone(),
+                            })
+                        } else if j >= matrix.ncols() {
+                            Err(IndexOutOfBounds {
+                                expression: self.clone(),
+                                vector_or_matrix: matrix_original.clone(),
+                                index: j_original.clone(),
+                            })
+                        } else {
+                            Ok(matrix[(i, j)].clone())
+                        }
+                    } else {
+                        Err(InvalidOperand {
+                            expression: self.clone(),
+                            operand: j_original.clone(),
+                        })
+                    }
+                } else {
+                    Err(InvalidOperand {
+                        expression: self.clone(),
+                        operand: i_original.clone(),
+                    })
                 }
             }
+
+            _ => Ok(MatrixElement(Box::new(matrix), Box::new(i), Box::new(j))),
+        }
+    }
+
+    /// Returns the result of performing a single evaluation step on the expression,
+    /// or an error if the expression cannot be evaluated. The `context` argument
+    /// can be used to set the values of variables by their identifiers, except for
+    /// those whose identifiers are contained in `held`, which remain symbolic.
+    fn evaluate_step(
+        &self,
+        context: &HashMap<String, Self>,
+        held: &HashSet<String>,
+    ) -> Result<Self, Error> {
+        use crate::expression::Expression::*;
+
+        let expression = self.simplify_step();
+
+        match &expression {
+            Variable(identifier) if held.contains(identifier) => Ok(expression),
+            Variable(identifier) => context
+                .get(identifier)
+                .map_or_else(|| Ok(expression), |x| x.evaluate_step(context, held)),
+            Function(_, _) => Ok(expression),
+            FunctionValue(function, arguments) => {
+                expression.evaluate_step_function_value(function, arguments, context, held)
+            }
             Integer(_) => Ok(expression),
-            Rational(x, _) => Ok(if x.denom().is_one() {
-                Integer(x.numer().clone())
-            } else {
-                expression
-            }),
+            Rational(x, representation) => Ok(
+                if x.denom().is_one()
+                    && !matches!(representation, RationalRepresentation::Unreduced(_))
+                {
+                    Integer(x.numer().clone())
+                } else {
+                    expression
+                },
+            ),
             Complex(z, representation) => Ok(if z.im.is_zero() {
                 Rational(z.re.clone(), *representation)
             } else {
@@ -451,55 +916,13 @@ impl Expression {
                 let mut elements = Vec::new();
 
                 for element in v.iter() {
-                    elements.push(element.evaluate_step(context)?);
+                    elements.push(element.evaluate_step(context, held)?);
                 }
 
                 Ok(Vector(crate::expression::Vector::from_vec(elements)))
             }
             VectorElement(vector, i) => {
-                let vector_original = vector;
-                let i_original = i;
-
-                let vector = vector.evaluate_step(context)?;
-                let i = i.evaluate_step(context)?;
-
-                match (vector.typ(), i.typ()) {
-                    (Num(_, _) | Bool(_), _) => Err(InvalidOperand {
-                        expression: expression.clone(),
-                        operand: *vector_original.clone(),
-                    }),
-
-                    (_, Mat(_) | Bool(_)) => Err(InvalidOperand {
-                        expression: expression.clone(),
-                        operand: *i_original.clone(),
-                    }),
-
-                    (Mat(vector), Num(i, _)) => {
-                        if vector.ncols() != 1 {
-                            Err(InvalidOperand {
-                                expression: expression.clone(),
-                                operand: *vector_original.clone(),
-                            })
-                        } else if let Some(i) = i.to_usize() {
-                            if i >= vector.nrows() {
-                                Err(IndexOutOfBounds {
-                                    expression: expression.clone(),
-                                    vector_or_matrix: *vector_original.clone(),
-                                    index: *i_original.clone(),
-                                })
-                            } else {
-                                Ok(vector[(i, 0)].clone())
-                            }
-                        } else {
-                            Err(InvalidOperand {
-                                expression: expression.clone(),
-                                operand: *i_original.clone(),
-                            })
-                        }
-                    }
-
-                    _ => Ok(VectorElement(Box::new(vector), Box::new(i))),
-                }
+                expression.evaluate_step_vector_element(vector, i, context, held)
             }
             Matrix(m) => {
                 let mut columns = Vec::new();
@@ -508,7 +931,7 @@ impl Expression {
                     let mut elements = Vec::new();
 
                     for element in column.iter() {
-                        elements.push(element.evaluate_step(context)?);
+                        elements.push(element.evaluate_step(context, held)?);
                     }
 
                     columns.push(crate::expression::Vector::from_vec(elements));
@@ -523,82 +946,25 @@ impl Expression {
                 })
             }
             MatrixElement(matrix, i, j) => {
-                let matrix_original = matrix;
-                let i_original = i;
-                let j_original = j;
-
-                let matrix = matrix.evaluate_step(context)?;
-                let i = i.evaluate_step(context)?;
-                let j = j.evaluate_step(context)?;
-
-                match (matrix.typ(), i.typ(), j.typ()) {
-                    (Num(_, _) | Bool(_), _, _) => Err(InvalidOperand {
-                        expression: expression.clone(),
-                        operand: *matrix_original.clone(),
-                    }),
-
-                    (_, Mat(_) | Bool(_), _) => Err(InvalidOperand {
-                        expression: expression.clone(),
-                        operand: *i_original.clone(),
-                    }),
-
-                    (_, _, Mat(_) | Bool(_)) => Err(InvalidOperand {
-                        expression: expression.clone(),
-                        operand: *j_original.clone(),
-                    }),
-
-                    (Mat(matrix), Num(i, _), Num(j, _)) => {
-                        if let Some(i) = i.to_usize() {
-                            if let Some(j) = j.to_usize() {
-                                if i >= matrix.nrows() {
-                                    Err(IndexOutOfBounds {
-                                        expression: expression.clone(),
-                                        vector_or_matrix: *matrix_original.clone(),
-                                        index: *i_original.clone(),
-                                    })
-                                } else if j >= matrix.ncols() {
-                                    Err(IndexOutOfBounds {
-                                        expression: expression.clone(),
-                                        vector_or_matrix: *matrix_original.clone(),
-                                        index: *j_original.clone(),
-                                    })
-                                } else {
-                                    Ok(matrix[(i, j)].clone())
-                                }
-                            } else {
-                                Err(InvalidOperand {
-                                    expression: expression.clone(),
-                                    operand: *j_original.clone(),
-                                })
-                            }
-                        } else {
-                            Err(InvalidOperand {
-                                expression: expression.clone(),
-                                operand: *i_original.clone(),
-                            })
-                        }
-                    }
-
-                    _ => Ok(MatrixElement(Box::new(matrix), Box::new(i), Box::new(j))),
-                }
+                expression.evaluate_step_matrix_element(matrix, i, j, context, held)
             }
             Boolean(_) => Ok(expression),
-            Negation(a) => expression.evaluate_step_unary(a, context),
-            Not(a) => expression.evaluate_step_unary(a, context),
-            Sum(a, b) => expression.evaluate_step_binary(a, b, context),
-            Difference(a, b) => expression.evaluate_step_binary(a, b, context),
-            Product(a, b) => expression.evaluate_step_binary(a, b, context),
-            Quotient(a, b) => expression.evaluate_step_binary(a, b, context),
-            Remainder(a, b) => expression.evaluate_step_binary(a, b, context),
-            Power(a, b) => expression.evaluate_step_binary(a, b, context),
-            Equal(a, b) => expression.evaluate_step_binary(a, b, context),
-            NotEqual(a, b) => expression.evaluate_step_binary(a, b, context),
-            LessThan(a, b) => expression.evaluate_step_binary(a, b, context),
-            LessThanOrEqual(a, b) => expression.evaluate_step_binary(a, b, context),
-            GreaterThan(a, b) => expression.evaluate_step_binary(a, b, context),
-            GreaterThanOrEqual(a, b) => expression.evaluate_step_binary(a, b, context),
-            And(a, b) => expression.evaluate_step_binary(a, b, context),
-            Or(a, b) => expression.evaluate_step_binary(a, b, context),
+            Negation(a) => expression.evaluate_step_unary(a, context, held),
+            Not(a) => expression.evaluate_step_unary(a, context, held),
+            Sum(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            Difference(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            Product(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            Quotient(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            Remainder(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            Power(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            Equal(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            NotEqual(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            LessThan(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            LessThanOrEqual(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            GreaterThan(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            GreaterThanOrEqual(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            And(a, b) => expression.evaluate_step_binary(a, b, context, held),
+            Or(a, b) => expression.evaluate_step_binary(a, b, context, held),
         }
     }
 
@@ -606,10 +972,22 @@ impl Expression {
     /// if the expression cannot be evaluated. The `context` argument
     /// can be used to set the values of variables by their identifiers.
     pub fn evaluate(&self, context: &HashMap<String, Self>) -> Result<Self, Error> {
+        self.evaluate_with_held(context, &HashSet::new())
+    }
+
+    /// Returns the result of evaluating the expression like [`Expression::evaluate`],
+    /// except that variables whose identifiers are contained in `held` are not
+    /// substituted, even if the context defines them. This makes it possible to show
+    /// intermediate symbolic forms of an expression.
+    pub fn evaluate_with_held(
+        &self,
+        context: &HashMap<String, Self>,
+        held: &HashSet<String>,
+    ) -> Result<Self, Error> {
         let mut old_expression: Self = self.clone();
 
         loop {
-            let new_expression = old_expression.evaluate_step(context)?;
+            let new_expression = old_expression.evaluate_step(context, held)?;
 
             if new_expression == old_expression {
                 return Ok(new_expression);
@@ -618,12 +996,24 @@ impl Expression {
             old_expression = new_expression;
         }
     }
+
+    /// Returns the boolean value of the expression if evaluating it
+    /// yields a boolean literal, or `None` if evaluation fails or the value
+    /// cannot be determined (e.g. because it depends on undefined variables).
+    pub fn try_evaluate_bool(&self, context: &HashMap<String, Self>) -> Option<bool> {
+        match self.evaluate(context) {
+            Ok(Self::Boolean(value)) => Some(value),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::evaluate::default_context;
     use crate::expression::Expression;
+    use crate::helpers::*;
+    use crate::parse::{parse_with_spans, Mode};
 
     #[track_caller]
     fn t(expression: &str, result: &str) {
@@ -642,6 +1032,15 @@ mod tests {
     fn arithmetic() {
         t("-(-1)", "1");
         t("-0", "0");
+        t("-5", "-5");
+        t("-(5)", "-5");
+        t("-(1/2)", "-1/2");
+        t("-(0.5)", "-0.5");
+        t("-(-x)", "x");
+        t("-(-(-x))", "-x");
+        t("-(a - b)", "b - a");
+        t("-(2 * x)", "-2 * x");
+        t("-(1/2 * x)", "-1/2 * x");
 
         t("1 + 2", "3");
         t("1 + -1", "0");
@@ -700,8 +1099,266 @@ mod tests {
         );
     }
 
+    #[test]
+    fn complex_numbers() {
+        t("(2 + 4*i) / 2", "1 + 2*i");
+        t("(2 + 4*i) / 4", "1/2 + i");
+        t("(3 + 6*i) / (3*i)", "2 - i");
+        t("(2*i) / (1 + i)", "1 + i");
+        t("(1 + i) / (1 - i)", "i");
+        t("(1 + i) / 2 * 2", "1 + i");
+        t("(1/2 + i/2) * 2", "1 + i");
+        t("(2/4 + 6/8*i) * 4", "2 + 3*i");
+        t("(1 + i) * (1 - i)", "2");
+        t("(0.5 + i) * 2", "1 + 2*i");
+        t("(0.5 + i) * (0.5 - i)", "1.25");
+        t("(1 + 3*i) * (1 + 3*i) / (1 + 3*i)", "1 + 3*i");
+
+        t("i * i", "-1");
+        t("i ^ 3", "-i");
+        t("(1 + i) ^ 2", "2*i");
+        t("(1 + i) ^ (-2)", "-1/2*i");
+        t("(-1) ^ (1/2)", "i");
+        t("(-4) ^ (1/2)", "2*i");
+        t("(-1/4) ^ 0.5", "0.5*i");
+        t("(-1) ^ (3/2)", "-i");
+        t("4 ^ (1/2)", "2");
+        t("(8/27) ^ (-2/3)", "9/4");
+        t("2 ^ (1/2)", "2 ^ (1/2)");
+        t("(-8) ^ (1/3)", "(-8) ^ (1/3)");
+        t("(2 + 3*i) + (1 - 5*i)", "3 - 2*i");
+        t("(2 + 3*i) - (2 + 3*i)", "0");
+        t("i * (2 + 3*i) + 3", "2*i");
+        t("-(1 - i)", "i - 1");
+
+        let mut context = default_context();
+        context.insert("i".to_owned(), int(2));
+
+        assert_eq!(
+            "(1 + i) * (1 - i)"
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(&context),
+            Ok(int(-3)),
+        );
+    }
+
+    #[test]
+    fn unreduced_fractions() {
+        let context = default_context();
+
+        assert_eq!(ratu(2, 4).evaluate(&context).unwrap().to_string(), "2/4");
+        assert_eq!(ratu(4, 2).evaluate(&context).unwrap().to_string(), "4/2");
+        assert_eq!((ratu(2, 4) + ratu(1, 4)).evaluate(&context), Ok(rat(3, 4)),);
+        assert_eq!(
+            (-ratu(2, 4)).evaluate(&context).unwrap().to_string(),
+            "-2/4"
+        );
+        assert_eq!((ratu(2, 4) * int(2)).evaluate(&context), Ok(int(1)));
+        assert_eq!(
+            eq(ratu(2, 4), rat(1, 2)).evaluate(&context),
+            Ok(Expression::Boolean(true)),
+        );
+    }
+
+    #[test]
+    fn quotients() {
+        use crate::evaluate::Error::DivisionByZero;
+
+        t("6/4", "3/2");
+        t("-6/4", "-3/2");
+        t("6/3", "2");
+        t("0/5", "0");
+        t("(2/3) / (4/9)", "3/2");
+        t("1.5 / 0.5", "3");
+        t("0.3 / 4", "0.075");
+        t("(1 + 2*i) / (3 - 4*i)", "2/5*i - 1/5");
+        t("(1 + i) / i", "1 - i");
+        t("x / x", "1");
+        t("(a + b) / (a + b)", "1");
+        t("x^3 / x", "x ^ 2");
+        t("x / x^3", "x ^ (-2)");
+        t("x / y", "x / y");
+        t("2^3 / 2", "4");
+
+        assert_eq!(
+            (int(1) / int(0)).evaluate(&default_context()),
+            Err(DivisionByZero {
+                expression: int(1) / int(0),
+                dividend: int(1),
+                divisor: int(0),
+            }),
+        );
+
+        assert!(matches!(
+            (com(1, 1, 1, 1) / (rat(1, 2) - rat(1, 2))).evaluate(&default_context()),
+            Err(DivisionByZero { .. }),
+        ));
+
+        for expression in [
+            "0 / 0",
+            "(1 - 1) / (1 - 1)",
+            "0 % 0",
+            "(1 / (1 - 1)) * (1 - 1)",
+        ] {
+            assert!(matches!(
+                expression
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&default_context()),
+                Err(DivisionByZero { .. }),
+            ));
+        }
+    }
+
+    #[test]
+    fn held_variables() {
+        use std::collections::HashSet;
+
+        let mut context = default_context();
+        context.insert("a".to_owned(), int(2));
+        context.insert("b".to_owned(), var("a") + int(1));
+
+        let held = HashSet::from(["a".to_owned()]);
+
+        assert_eq!(
+            (var("a") * int(3)).evaluate_with_held(&context, &held),
+            Ok(var("a") * int(3)),
+        );
+        assert_eq!(
+            var("b").evaluate_with_held(&context, &held),
+            Ok(var("a") + int(1)),
+        );
+        assert_eq!(
+            (var("a") * var("b")).evaluate_with_held(&context, &HashSet::new()),
+            Ok(int(6)),
+        );
+        assert_eq!(
+            fun(var("nth_prime"), [var("a")]).evaluate_with_held(&context, &held),
+            Ok(fun(var("nth_prime"), [var("a")])),
+        );
+    }
+
+    #[test]
+    fn collection_entries() {
+        use crate::evaluate::Error::DivisionByZero;
+
+        let mut context = default_context();
+        context.insert("a".to_owned(), int(1));
+        context.insert("b".to_owned(), int(2));
+        context.insert("v".to_owned(), "[a, b]".parse().unwrap());
+
+        let t = |expression: &str, result: &str| {
+            assert_eq!(
+                expression
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&context)
+                    .unwrap()
+                    .to_string(),
+                result,
+            );
+        };
+
+        t("[a, b]", "[1, 2]");
+        t("[a + b, c, a * c]", "[3, c, c]");
+        t("[[a, b], [b, a]]", "[[1, 2], [2, 1]]");
+        t("[[a, c], [b^2, [a, b]]]", "[[1, c], [4, [1, 2]]]");
+        t("[v, v[1]]", "[[1, 2], 2]");
+        t("[[a], [b]]", "[1, 2]");
+
+        assert_eq!(
+            "[a, b / (a - 1)]"
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(&context),
+            Err(DivisionByZero {
+                expression: var("b") / (var("a") - int(1)),
+                dividend: var("b"),
+                divisor: var("a") - int(1),
+            }),
+        );
+    }
+
+    #[test]
+    fn negative_factors() {
+        t("(-a) * b", "-(a * b)");
+        t("a * (-b)", "-(a * b)");
+        t("(-a) * (-b)", "a * b");
+        t("a * (-b) * c", "-(a * b * c)");
+        t("(-a) * b + a * (-b)", "-2 * a * b");
+        t("(-a) * b - (-(a * b))", "0");
+        t("(-2) * a", "-2 * a");
+
+        t("(-a) / b", "-(a / b)");
+        t("a / (-b)", "-(a / b)");
+        t("(-a) / (-b)", "a / b");
+    }
+
+    #[test]
+    fn power_laws() {
+        t("x^2 * x^3", "x ^ 5");
+        t("x * x^2", "x ^ 3");
+        t("x^a * x^b", "x ^ (a + b)");
+        t("2^a * 2", "2 ^ (a + 1)");
+
+        t("x^5 / x^2", "x ^ 3");
+        t("x^3 / x", "x ^ 2");
+        t("x^2 / x^3", "x ^ (-1)");
+        t("x^a / x^b", "x ^ (a - b)");
+
+        t("(x^2)^3", "x ^ 6");
+        t("(x^(1/2))^2", "x");
+        t("(x^a)^2", "x ^ (a * 2)");
+        t("(x^2)^(1/2)", "(x ^ 2) ^ (1/2)");
+        t("(x^a)^b", "(x ^ a) ^ b");
+    }
+
+    #[test]
+    fn boolean_values() {
+        let mut context = default_context();
+        context.insert("a".to_owned(), int(2));
+
+        assert_eq!(lt(int(1), var("a")).try_evaluate_bool(&context), Some(true));
+        assert_eq!(
+            eq(var("a") * int(2), int(5)).try_evaluate_bool(&context),
+            Some(false)
+        );
+        assert_eq!(
+            or(var("b"), !var("b")).try_evaluate_bool(&context),
+            Some(true)
+        );
+        assert_eq!(lt(var("b"), var("a")).try_evaluate_bool(&context), None);
+        assert_eq!(
+            and(var("b"), Expression::Boolean(true)).try_evaluate_bool(&context),
+            None
+        );
+        assert_eq!(var("a").try_evaluate_bool(&context), None);
+        assert_eq!((int(1) / int(0)).try_evaluate_bool(&context), None);
+    }
+
+    #[test]
+    fn spans() {
+        #[track_caller]
+        fn t(string: &str, offending_string: &str) {
+            let (expression, spans) = parse_with_spans(string, Mode::Standard).unwrap();
+            let error = expression.evaluate(&default_context()).unwrap_err();
+            let span = spans.get(error.offending_expression()).unwrap();
+            assert_eq!(&string[span], offending_string);
+        }
+
+        t("1 + det(2 * [1, 2])", "2 * [1, 2]");
+        t("nth_prime( 1 - 1 ) * 2", "1 - 1");
+        t("[a, b][2 + 1]", "2 + 1");
+        t("(1 + 2) / (a - a)", "a - a");
+        t("1 + [1] * 2 + 3", "1 + [1] * 2");
+        t("2 + !(1 + 1)", "1 + 1");
+    }
+
     #[test]
     fn linear_algebra() {
+        use crate::evaluate::Error::IncompatibleOperands;
+
         t("[1] + [2]", "[3]");
         t("[1] - [2]", "[-1]");
         t("[1] * [2]", "[2]");
@@ -719,6 +1376,21 @@ mod tests {
             "[[a, b], [c, d], [e, f]] * [[5, 6], [7, 8]]",
             "[[a * 5 + b * 7, a * 6 + b * 8], [c * 5 + d * 7, c * 6 + d * 8], [e * 5 + f * 7, e * 6 + f * 8]]",
         );
+
+        t("[[1, 2], [3, 4]] * [[1, 0], [0, 1]]", "[[1, 2], [3, 4]]");
+        t("[[1, 0], [0, 1]] * [[1, 2], [3, 4]]", "[[1, 2], [3, 4]]");
+        t(
+            "[[1, 2], [3, 4]] * [[5, 6], [7, 8]]",
+            "[[19, 22], [43, 50]]",
+        );
+
+        assert!(matches!(
+            "[[1, 2], [3, 4]] * [[1, 2, 3]]"
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(&default_context()),
+            Err(IncompatibleOperands { .. }),
+        ));
     }
 
     #[test]
@@ -795,5 +1467,15 @@ mod tests {
         t("true != false", "true");
         t("false != true", "true");
         t("false != false", "false");
+
+        t("a == a", "true");
+        t("a != a", "false");
+        t("f(a + 1) == f(a + 1)", "true");
+        t("[a, b] == [a, b]", "true");
+        t("(a && b) == (a && b)", "true");
+        t("a == b", "a == b");
+        t("a != b", "a != b");
+        t("a == true", "a == true");
+        t("a + 1 == 1 + a", "a + 1 == 1 + a");
     }
 }
@@ -7,14 +7,42 @@ use std::{
 };
 
 use derivative::*;
-use num::{Signed, Zero};
+use num::{Signed, ToPrimitive, Zero};
 
 use crate::evaluate::Error;
 
-/// Function implementation.
-pub type Function =
+/// Signature of function implementations, which are given the function value expression
+/// being evaluated, its (evaluated) arguments, and the context.
+pub type Implementation =
     dyn Fn(&Expression, &[Expression], &HashMap<String, Expression>) -> Result<Expression, Error>;
 
+/// Function implementation.
+pub struct Function {
+    /// Implementation proper.
+    pub implementation: Box<Implementation>,
+    /// Whether the variables bound by equations of the form `x == value` in the last argument
+    /// are held (i.e., not replaced with their values from the context) while the arguments
+    /// are evaluated, so that the function can give them other values.
+    pub holds_bindings: bool,
+}
+
+impl Function {
+    /// Returns a function with the given implementation that holds no variables.
+    pub fn new(
+        implementation: impl Fn(
+                &Expression,
+                &[Expression],
+                &HashMap<String, Expression>,
+            ) -> Result<Expression, Error>
+            + 'static,
+    ) -> Self {
+        Function {
+            implementation: Box::new(implementation),
+            holds_bindings: false,
+        }
+    }
+}
+
 /// Arbitrary-precision integer.
 pub type Integer = num::bigint::BigInt;
 
@@ -38,6 +66,12 @@ pub enum RationalRepresentation {
     /// Decimal, falling back to fraction representation
     /// if the number cannot be represented as a finite decimal.
     Decimal,
+    /// Fraction whose numerator and denominator are those of the number
+    /// in lowest terms, multiplied by the given factor (e.g. `2/4` for 1/2
+    /// with a factor of 2). Storing only the factor rather than the fraction
+    /// keeps `Expression` small, which matters because expressions
+    /// are parsed and evaluated recursively.
+    Unreduced(u32),
 }
 
 impl RationalRepresentation {
@@ -166,7 +200,7 @@ impl Expression {
             Integer(n) => Num(self::Rational::from_integer(n.clone()).into(), Fraction),
             Rational(x, representation) => Num(x.into(), *representation),
             Complex(z, representation) => Num(z.clone(), *representation),
-            Vector(v) => Mat(self::Matrix::from_columns(&[v.clone()])),
+            Vector(v) => Mat(self::Matrix::from_columns(std::slice::from_ref(v))),
             VectorElement(_, _) => Unknown,
             Matrix(m) => Mat(m.clone()),
             MatrixElement(_, _, _) => Unknown,
@@ -335,6 +369,158 @@ impl Expression {
         parts
     }
 
+    /// Returns the result of applying the function `f` to each direct sub-expression
+    /// of the expression (but not to the expression itself, nor to sub-expressions
+    /// of sub-expressions, unless `f` itself recurses into them).
+    pub fn map_children(&self, f: &impl Fn(&Self) -> Self) -> Self {
+        use Expression::*;
+
+        match self {
+            Variable(_) => self.clone(),
+            Function(_, _) => self.clone(),
+            FunctionValue(function, arguments) => {
+                FunctionValue(Box::new(f(function)), arguments.iter().map(f).collect())
+            }
+            Integer(_) => self.clone(),
+            Rational(_, _) => self.clone(),
+            Complex(_, _) => self.clone(),
+            Vector(v) => Vector(v.map(|element| f(&element))),
+            VectorElement(vector, i) => VectorElement(Box::new(f(vector)), Box::new(f(i))),
+            Matrix(m) => Matrix(m.map(|element| f(&element))),
+            MatrixElement(matrix, i, j) => {
+                MatrixElement(Box::new(f(matrix)), Box::new(f(i)), Box::new(f(j)))
+            }
+            Boolean(_) => self.clone(),
+            Negation(a) => Negation(Box::new(f(a))),
+            Not(a) => Not(Box::new(f(a))),
+            Sum(a, b) => Sum(Box::new(f(a)), Box::new(f(b))),
+            Difference(a, b) => Difference(Box::new(f(a)), Box::new(f(b))),
+            Product(a, b) => Product(Box::new(f(a)), Box::new(f(b))),
+            Quotient(a, b) => Quotient(Box::new(f(a)), Box::new(f(b))),
+            Remainder(a, b) => Remainder(Box::new(f(a)), Box::new(f(b))),
+            Power(a, b) => Power(Box::new(f(a)), Box::new(f(b))),
+            Equal(a, b) => Equal(Box::new(f(a)), Box::new(f(b))),
+            NotEqual(a, b) => NotEqual(Box::new(f(a)), Box::new(f(b))),
+            LessThan(a, b) => LessThan(Box::new(f(a)), Box::new(f(b))),
+            LessThanOrEqual(a, b) => LessThanOrEqual(Box::new(f(a)), Box::new(f(b))),
+            GreaterThan(a, b) => GreaterThan(Box::new(f(a)), Box::new(f(b))),
+            GreaterThanOrEqual(a, b) => GreaterThanOrEqual(Box::new(f(a)), Box::new(f(b))),
+            And(a, b) => And(Box::new(f(a)), Box::new(f(b))),
+            Or(a, b) => Or(Box::new(f(a)), Box::new(f(b))),
+        }
+    }
+
+    /// Returns the direct sub-expression of the expression with the given index,
+    /// or `None` if there is no such sub-expression. See [`Expression::get_at`]
+    /// for the indexing convention.
+    pub(crate) fn child(&self, index: usize) -> Option<&Self> {
+        use Expression::*;
+
+        match self {
+            Variable(_) | Function(_, _) | Integer(_) | Rational(_, _) | Complex(_, _) => None,
+            Boolean(_) => None,
+            FunctionValue(function, arguments) => match index {
+                0 => Some(function),
+                _ => arguments.get(index - 1),
+            },
+            Vector(v) => v.get(index),
+            Matrix(m) => (index < m.len()).then(|| &m[(index / m.ncols(), index % m.ncols())]),
+            VectorElement(a, b) => [a, b].get(index).map(|child| &***child),
+            MatrixElement(a, b, c) => [a, b, c].get(index).map(|child| &***child),
+            Negation(a) | Not(a) if index == 0 => Some(a),
+            Negation(_) | Not(_) => None,
+            Sum(a, b)
+            | Difference(a, b)
+            | Product(a, b)
+            | Quotient(a, b)
+            | Remainder(a, b)
+            | Power(a, b)
+            | Equal(a, b)
+            | NotEqual(a, b)
+            | LessThan(a, b)
+            | LessThanOrEqual(a, b)
+            | GreaterThan(a, b)
+            | GreaterThanOrEqual(a, b)
+            | And(a, b)
+            | Or(a, b) => [a, b].get(index).map(|child| &***child),
+        }
+    }
+
+    /// Mutable version of [`Expression::child`].
+    pub(crate) fn child_mut(&mut self, index: usize) -> Option<&mut Self> {
+        use Expression::*;
+
+        match self {
+            Variable(_) | Function(_, _) | Integer(_) | Rational(_, _) | Complex(_, _) => None,
+            Boolean(_) => None,
+            FunctionValue(function, arguments) => match index {
+                0 => Some(function),
+                _ => arguments.get_mut(index - 1),
+            },
+            Vector(v) => v.get_mut(index),
+            Matrix(m) => {
+                let columns = m.ncols();
+                (index < m.len()).then(|| &mut m[(index / columns, index % columns)])
+            }
+            VectorElement(a, b) => [a, b].into_iter().nth(index).map(|child| &mut **child),
+            MatrixElement(a, b, c) => [a, b, c].into_iter().nth(index).map(|child| &mut **child),
+            Negation(a) | Not(a) if index == 0 => Some(a),
+            Negation(_) | Not(_) => None,
+            Sum(a, b)
+            | Difference(a, b)
+            | Product(a, b)
+            | Quotient(a, b)
+            | Remainder(a, b)
+            | Power(a, b)
+            | Equal(a, b)
+            | NotEqual(a, b)
+            | LessThan(a, b)
+            | LessThanOrEqual(a, b)
+            | GreaterThan(a, b)
+            | GreaterThanOrEqual(a, b)
+            | And(a, b)
+            | Or(a, b) => [a, b].into_iter().nth(index).map(|child| &mut **child),
+        }
+    }
+
+    /// Returns the sub-expression at the given path, or `None` if the path
+    /// does not address a sub-expression. A path is a sequence of indices,
+    /// each of which selects a direct sub-expression of the expression
+    /// selected by the previous ones, and the empty path selects the expression itself.
+    ///
+    /// The direct sub-expressions are indexed as follows:
+    ///
+    /// * Operators: The operands, from left to right (i.e. `0` for the operand
+    ///   of a unary operator, and `0` and `1` for those of a binary operator).
+    /// * Function values: `0` for the function, followed by the arguments.
+    /// * Vectors: The elements, in order.
+    /// * Matrices: The elements, in row-major order (i.e. in the order
+    ///   in which they appear when the matrix is printed).
+    /// * Vector and matrix elements: `0` for the vector or matrix,
+    ///   followed by the index expressions.
+    /// * Variables, functions, numbers, and booleans have no sub-expressions.
+    pub fn get_at(&self, path: &[usize]) -> Option<&Self> {
+        path.iter()
+            .try_fold(self, |expression, &index| expression.child(index))
+    }
+
+    /// Returns the expression with the sub-expression at the given path
+    /// (see [`Expression::get_at`]) replaced by `new`. If the path does not
+    /// address a sub-expression, the expression is returned unchanged.
+    pub fn replace_at(&self, path: &[usize], new: Self) -> Self {
+        let mut expression = self.clone();
+
+        let target = path.iter().try_fold(&mut expression, |expression, &index| {
+            expression.child_mut(index)
+        });
+
+        if let Some(target) = target {
+            *target = new;
+        }
+
+        expression
+    }
+
     /// Returns the identifiers of all variables that the expression contains.
     pub fn variables(&self) -> HashSet<String> {
         let mut identifiers = HashSet::new();
@@ -347,4 +533,324 @@ impl Expression {
 
         identifiers
     }
+
+    /// Returns `true` if the expression does not contain the variable
+    /// with the given identifier anywhere, including as the name of
+    /// a function being called, and `false` otherwise.
+    ///
+    /// Savage has no constructs that bind variables, so every occurrence
+    /// of a variable is free. Function implementations are opaque,
+    /// and never contain any variables.
+    pub fn free_of(&self, identifier: &str) -> bool {
+        !self.variables().contains(identifier)
+    }
+
+    /// Returns the expression with all occurrences of the variable with the given identifier,
+    /// including as the name of a function being called, replaced with `value`.
+    ///
+    /// Function implementations are opaque, so the parameters of user-defined functions
+    /// are bound only inside them, and occurrences of a variable with the same identifier
+    /// in the body of such a function are never replaced.
+    pub fn substitute(&self, identifier: &str, value: &Self) -> Self {
+        match self {
+            Self::Variable(variable_identifier) if variable_identifier == identifier => {
+                value.clone()
+            }
+            _ => self.map_children(&|child| child.substitute(identifier, value)),
+        }
+    }
+
+    /// Returns the expression with all occurrences of variables whose identifiers
+    /// are keys in `values` replaced with the corresponding values, like
+    /// [`Expression::substitute`]. All variables are replaced simultaneously,
+    /// so variables contained in the values are never replaced themselves.
+    pub fn substitute_all(&self, values: &HashMap<String, Self>) -> Self {
+        match self {
+            Self::Variable(identifier) if values.contains_key(identifier) => {
+                values[identifier].clone()
+            }
+            _ => self.map_children(&|child| child.substitute_all(values)),
+        }
+    }
+
+    /// Returns the expression with the preferred representation of all rational numbers,
+    /// and of the rational parts of all complex numbers, replaced with `representation`.
+    pub fn with_representation(&self, representation: &RationalRepresentation) -> Self {
+        use Expression::*;
+
+        match self {
+            Rational(x, _) => Rational(x.clone(), *representation),
+            Complex(z, _) => Complex(z.clone(), *representation),
+            _ => self.map_children(&|child| child.with_representation(representation)),
+        }
+    }
+
+    /// Returns the vector or matrix expression as a matrix of floating-point numbers,
+    /// for use with the numerical linear algebra routines provided by `nalgebra`.
+    /// If the expression is not a vector or matrix, it is returned as the error.
+    /// If an element is not a real number, the first such element (in row-major order)
+    /// is returned as the error.
+    pub fn to_nalgebra_f64(&self) -> Result<nalgebra::DMatrix<f64>, Self> {
+        let matrix = match self.typ() {
+            Type::Matrix(matrix) => matrix,
+            _ => return Err(self.clone()),
+        };
+
+        let mut elements = Vec::with_capacity(matrix.len());
+
+        for row in matrix.row_iter() {
+            for element in row.iter() {
+                let value = match element.typ() {
+                    Type::Number(z, _) if z.im.is_zero() => z.re.to_f64(),
+                    _ => None,
+                };
+
+                elements.push(value.ok_or_else(|| element.clone())?);
+            }
+        }
+
+        Ok(nalgebra::DMatrix::from_row_slice(
+            matrix.nrows(),
+            matrix.ncols(),
+            &elements,
+        ))
+    }
+
+    /// Returns `true` if both expressions are numbers whose real parts and whose
+    /// imaginary parts differ by at most `tolerance`, or vectors or matrices
+    /// of the same shape whose corresponding elements are such numbers,
+    /// and `false` otherwise (in particular, if either expression is symbolic).
+    pub fn approx_eq(&self, other: &Self, tolerance: &Rational) -> bool {
+        match (self.typ(), other.typ()) {
+            (Type::Number(a, _), Type::Number(b, _)) => {
+                (a.re - b.re).abs() <= *tolerance && (a.im - b.im).abs() <= *tolerance
+            }
+            (Type::Matrix(a), Type::Matrix(b)) => {
+                a.shape() == b.shape()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(a, b)| a.approx_eq(b, tolerance))
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{dmatrix, dvector};
+
+    use crate::evaluate::default_context;
+    use crate::expression::Expression::*;
+    use crate::helpers::*;
+
+    #[test]
+    fn free_of() {
+        let expression = var("a") * fun(var("f"), [var("b") + int(1), Vector(dvector![var("c")])]);
+
+        assert!(!expression.free_of("a"));
+        assert!(!expression.free_of("b"));
+        assert!(!expression.free_of("c"));
+        assert!(!expression.free_of("f"));
+        assert!(expression.free_of("x"));
+        assert!(expression.free_of("ab"));
+
+        assert!(int(1).free_of("a"));
+        assert!(!var("a").free_of("a"));
+
+        let det = default_context()["det"].clone();
+
+        assert!(det.free_of("det"));
+        assert!(!fun(det, [var("a")]).free_of("a"));
+    }
+
+    #[test]
+    fn representations() {
+        use crate::expression::RationalRepresentation::*;
+
+        let expression = Vector(dvector![
+            rat(1, 2) * var("a"),
+            ratd(3, 2),
+            com(1, 4, 1, 1),
+            int(1)
+        ]);
+
+        assert_eq!(
+            expression.with_representation(&Decimal).to_string(),
+            "[0.5 * a, 1.5, 0.25 + i, 1]",
+        );
+        assert_eq!(
+            expression.with_representation(&Fraction).to_string(),
+            "[1/2 * a, 3/2, 1/4 + i, 1]",
+        );
+    }
+
+    #[test]
+    fn approximate_equality() {
+        use crate::expression::Rational;
+
+        let tolerance = Rational::new(1.into(), 1000.into());
+
+        assert!(int(1).approx_eq(&int(1), &tolerance));
+        assert!(ratd(31416, 10000).approx_eq(&ratd(314159, 100000), &tolerance));
+        assert!(rat(1, 3).approx_eq(&ratd(333, 1000), &tolerance));
+        assert!(rat(1, 1000).approx_eq(&int(0), &tolerance));
+        assert!(com(1, 1, 1, 2).approx_eq(&comd(10005, 10000, 4995, 10000), &tolerance));
+        assert!(Vector(dvector![int(1), rat(1, 3)])
+            .approx_eq(&Vector(dvector![int(1), ratd(3333, 10000)]), &tolerance));
+
+        assert!(!rat(1, 3).approx_eq(&ratd(33, 100), &tolerance));
+        assert!(!rat(1001, 1000).approx_eq(&int(0), &tolerance));
+        assert!(!int(1).approx_eq(&com(1, 1, 1, 100), &tolerance));
+        assert!(!Vector(dvector![int(1), int(2)]).approx_eq(&Vector(dvector![int(1)]), &tolerance));
+        assert!(!Vector(dvector![int(1), int(2)]).approx_eq(&int(1), &tolerance));
+
+        assert!(!var("a").approx_eq(&var("a"), &tolerance));
+        assert!(!var("a").approx_eq(&int(1), &tolerance));
+        assert!(!int(1).approx_eq(&(int(1) + var("a") - var("a")), &tolerance));
+        assert!(!Vector(dvector![var("a")]).approx_eq(&Vector(dvector![var("a")]), &tolerance));
+    }
+
+    #[test]
+    fn floating_point_matrices() {
+        let matrix = Matrix(dmatrix![
+            int(1), rat(-1, 4);
+            ratd(5, 2), com(3, 1, 0, 1)
+        ]);
+
+        assert_eq!(
+            matrix.to_nalgebra_f64(),
+            Ok(dmatrix![
+                1.0, -0.25;
+                2.5, 3.0
+            ]),
+        );
+
+        assert_eq!(
+            Vector(dvector![int(1), int(2)]).to_nalgebra_f64(),
+            Ok(dmatrix![1.0; 2.0]),
+        );
+
+        let matrix = Matrix(dmatrix![
+            int(1), var("a");
+            var("b"), com(0, 1, 1, 1)
+        ]);
+
+        assert_eq!(matrix.to_nalgebra_f64(), Err(var("a")));
+
+        let matrix = Matrix(dmatrix![
+            int(1), int(2);
+            com(0, 1, 1, 1), var("a")
+        ]);
+
+        assert_eq!(matrix.to_nalgebra_f64(), Err(com(0, 1, 1, 1)));
+
+        assert_eq!(int(1).to_nalgebra_f64(), Err(int(1)));
+    }
+
+    #[test]
+    fn substitution() {
+        use std::{collections::HashMap, rc::Rc};
+
+        // Equivalent to the REPL definition `f(x) = x + 1`.
+        let f = Function(
+            "f".to_owned(),
+            Rc::new(crate::expression::Function::new(|_, arguments, _| {
+                let mut context = HashMap::new();
+                context.insert("x".to_owned(), arguments[0].clone());
+                (var("x") + int(1)).evaluate(&context)
+            })),
+        );
+
+        let expression = fun(f.clone(), [var("x")]) * var("x");
+
+        assert_eq!(
+            expression.substitute("x", &int(3)),
+            fun(f.clone(), [int(3)]) * int(3),
+        );
+        assert_eq!(
+            expression
+                .substitute("x", &int(3))
+                .evaluate(&default_context()),
+            Ok(int(12)),
+        );
+        assert_eq!(
+            expression
+                .substitute("x", &var("y"))
+                .evaluate(&default_context()),
+            Ok((var("y") + int(1)) * var("y")),
+        );
+        assert_eq!(expression.substitute("y", &int(3)), expression);
+
+        let expression = pow(var("x"), int(2)) + var("x");
+
+        assert_eq!(
+            expression
+                .substitute("x", &int(3))
+                .evaluate(&default_context()),
+            Ok(int(12)),
+        );
+
+        assert_eq!(
+            fun(var("g"), [var("x"), var("g")]).substitute("g", &f),
+            fun(f.clone(), [var("x"), f]),
+        );
+
+        let values = HashMap::from([("x".to_owned(), var("y")), ("y".to_owned(), var("x"))]);
+
+        assert_eq!(
+            (var("x") - var("y")).substitute_all(&values),
+            var("y") - var("x"),
+        );
+    }
+
+    #[test]
+    fn paths() {
+        // a + 2 * f(b, c)
+        let expression = var("a") + int(2) * fun(var("f"), [var("b"), var("c")]);
+
+        assert_eq!(expression.get_at(&[]), Some(&expression));
+        assert_eq!(expression.get_at(&[0]), Some(&var("a")));
+        assert_eq!(expression.get_at(&[1, 0]), Some(&int(2)));
+        assert_eq!(expression.get_at(&[1, 1, 0]), Some(&var("f")));
+        assert_eq!(expression.get_at(&[1, 1, 2]), Some(&var("c")));
+        assert_eq!(expression.get_at(&[1, 1, 3]), None);
+        assert_eq!(expression.get_at(&[2]), None);
+        assert_eq!(expression.get_at(&[0, 0]), None);
+
+        assert_eq!(
+            expression.replace_at(&[1, 1, 2], int(3)),
+            var("a") + int(2) * fun(var("f"), [var("b"), int(3)]),
+        );
+        assert_eq!(
+            expression.replace_at(&[1, 0], -var("x")),
+            var("a") + -var("x") * fun(var("f"), [var("b"), var("c")]),
+        );
+        assert_eq!(expression.replace_at(&[], var("x")), var("x"));
+        assert_eq!(expression.replace_at(&[1, 2], var("x")), expression);
+
+        let matrix = Matrix(dmatrix![
+            int(1), int(2), int(3);
+            int(4), int(5), int(6)
+        ]);
+
+        assert_eq!(matrix.get_at(&[1]), Some(&int(2)));
+        assert_eq!(matrix.get_at(&[3]), Some(&int(4)));
+        assert_eq!(matrix.get_at(&[6]), None);
+        assert_eq!(
+            matrix.replace_at(&[3], var("x")),
+            Matrix(dmatrix![
+                int(1), int(2), int(3);
+                var("x"), int(5), int(6)
+            ]),
+        );
+
+        let vector = Vector(dvector![int(1), -var("a")]);
+
+        assert_eq!(vector.get_at(&[1, 0]), Some(&var("a")));
+        assert_eq!(
+            vector.replace_at(&[1, 0], var("b")),
+            Vector(dvector![int(1), -var("b")]),
+        );
+    }
 }
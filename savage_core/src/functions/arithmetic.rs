@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use num::{Signed, Zero};
+use savage_macros::function;
+
+use crate::{
+    expression::{Expression, Type},
+    functions::function_expression,
+    helpers::*,
+};
+
+#[function(
+    name = "abs",
+    aliases = r#"[
+        "absolute_value",
+    ]"#,
+    description = "absolute value of a rational number, which can also be written as `|x|`",
+    examples = r#"[
+        ("abs(-3)", "3"),
+        ("abs(2/3)", "2/3"),
+        ("abs(-1.5)", "1.5"),
+        ("|1 - 5|", "4"),
+        ("|-2| + |3|", "5"),
+        ("absolute_value(-7)", "7"),
+    ]"#,
+    categories = r#"[
+        "arithmetic",
+    ]"#
+)]
+fn abs(x: Expression) -> Result<Expression, Expression> {
+    // The argument is taken as an expression to preserve its representation.
+    // Symbolic arguments are left unevaluated, as for functions taking numbers.
+    match x.typ() {
+        Type::Number(z, representation) if z.im.is_zero() => {
+            Ok(Expression::Rational(z.re.abs(), representation))
+        }
+        Type::Arithmetic | Type::Unknown => Ok(fun(function_expression("abs").unwrap(), [x])),
+        _ => Err(x),
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+//! Bitwise operations on integers. Negative integers are treated
+//! as if they were represented in two's complement with an infinite
+//! number of leading ones, which is consistent with the identity
+//! `-n == bitxor(n, -1) + 1`.
+
+use num::ToPrimitive;
+use savage_macros::function;
+
+use crate::{
+    expression::{Expression, Integer},
+    functions::{function_expression, NonNegativeInteger},
+    helpers::*,
+};
+
+#[function(
+    name = "bitand",
+    description = "bitwise AND of two integers",
+    examples = r#"[
+        ("bitand(12, 10)", "8"),
+        ("bitand(12, 0)", "0"),
+        ("bitand(-1, 10)", "10"),
+        ("bitand(-4, 7)", "4"),
+    ]"#,
+    categories = r#"[
+        "bitwise operations",
+    ]"#
+)]
+fn bitand(a: Integer, b: Integer) -> Integer {
+    a & b
+}
+
+#[function(
+    name = "bitor",
+    description = "bitwise OR of two integers",
+    examples = r#"[
+        ("bitor(12, 10)", "14"),
+        ("bitor(12, 0)", "12"),
+        ("bitor(-4, 1)", "-3"),
+    ]"#,
+    categories = r#"[
+        "bitwise operations",
+    ]"#
+)]
+fn bitor(a: Integer, b: Integer) -> Integer {
+    a | b
+}
+
+#[function(
+    name = "bitxor",
+    description = "bitwise exclusive OR (XOR) of two integers",
+    examples = r#"[
+        ("bitxor(12, 10)", "6"),
+        ("bitxor(12, 12)", "0"),
+        ("bitxor(5, -1)", "-6"),
+    ]"#,
+    categories = r#"[
+        "bitwise operations",
+    ]"#
+)]
+fn bitxor(a: Integer, b: Integer) -> Integer {
+    a ^ b
+}
+
+#[function(
+    name = "shl",
+    description = "integer shifted left by the given non-negative number of bits (i.e., multiplied by `2^n`)",
+    examples = r#"[
+        ("shl(1, 4)", "16"),
+        ("shl(3, 0)", "3"),
+        ("shl(-3, 2)", "-12"),
+    ]"#,
+    categories = r#"[
+        "bitwise operations",
+    ]"#
+)]
+fn shl(a: Integer, n: NonNegativeInteger) -> Expression {
+    if let Some(n) = n.to_usize() {
+        int(a << n)
+    } else {
+        fun(function_expression("shl").unwrap(), [int(a), int(n)])
+    }
+}
+
+#[function(
+    name = "shr",
+    description = "integer shifted right by the given non-negative number of bits (i.e., floor-divided by `2^n`)",
+    examples = r#"[
+        ("shr(16, 4)", "1"),
+        ("shr(17, 1)", "8"),
+        ("shr(-17, 1)", "-9"),
+        ("shr(1, 10)", "0"),
+    ]"#,
+    categories = r#"[
+        "bitwise operations",
+    ]"#
+)]
+fn shr(a: Integer, n: NonNegativeInteger) -> Expression {
+    if let Some(n) = n.to_usize() {
+        int(a >> n)
+    } else {
+        fun(function_expression("shr").unwrap(), [int(a), int(n)])
+    }
+}
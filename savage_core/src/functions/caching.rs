@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::{cell::RefCell, rc::Rc};
+
+use savage_macros::function;
+
+use crate::{
+    expression::{Expression, Function as FunctionImplementation},
+    functions::function_expression,
+    helpers::*,
+};
+
+#[function(
+    name = "memoize",
+    description = "function that behaves like the given function, but remembers the value for each combination of arguments, so that later calls with the same arguments return that value without computing it again (e.g. after entering `f(x) = x^2` and `g = memoize(f)`, `g(3)` is `9`, and calling `g(3)` again reuses that value)",
+    examples = r#"[
+        ("filter(memoize(is_prime), [2, 3, 4, 5])", "[2, 3, 5]"),
+        ("zip_with(memoize(div), [7, 9], [2, 4])", "[3, 2]"),
+        ("memoize(is_prime)", "is_prime"),
+        ("memoize(f)", "memoize(f)"),
+    ]"#,
+    categories = r#"[
+        "functions",
+    ]"#
+)]
+fn memoize(function: Expression) -> Result<Expression, Expression> {
+    match function {
+        Expression::Function(identifier, implementation) => {
+            // Expressions cannot be hashed, so cached values are found by linear search.
+            let cache = RefCell::new(Vec::<(Vec<Expression>, Expression)>::new());
+
+            Ok(Expression::Function(
+                identifier,
+                Rc::new(FunctionImplementation {
+                    holds_bindings: implementation.holds_bindings,
+                    implementation: Box::new(move |expression, arguments, context| {
+                        if let Some((_, value)) = cache
+                            .borrow()
+                            .iter()
+                            .find(|(cached_arguments, _)| cached_arguments == arguments)
+                        {
+                            return Ok(value.clone());
+                        }
+
+                        let value =
+                            (implementation.implementation)(expression, arguments, context)?;
+
+                        cache.borrow_mut().push((arguments.to_vec(), value.clone()));
+
+                        Ok(value)
+                    }),
+                }),
+            ))
+        }
+        // Undefined functions cannot be memoized yet.
+        Expression::Variable(_) => Ok(fun(function_expression("memoize").unwrap(), [function])),
+        _ => Err(function),
+    }
+}
@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::collections::HashMap;
+
+use num::{pow, ToPrimitive, Zero};
+use savage_macros::function;
+
+use crate::{
+    expression::{Expression, Integer, Rational, RationalRepresentation, Type},
+    functions::function_expression,
+    helpers::*,
+};
+
+/// Error tolerance for adaptive Simpson's rule.
+const TOLERANCE: f64 = 1e-12;
+
+/// Maximum number of times an interval is split in half by adaptive Simpson's rule.
+const MAX_DEPTH: usize = 20;
+
+/// Number of decimal places to which numerical approximations are rounded.
+const DECIMAL_PLACES: usize = 10;
+
+/// Returns the value of the function at `x` as a floating-point number,
+/// or the function as the error if that value is not a real number.
+fn value(function: &Expression, x: &Rational) -> Result<f64, Expression> {
+    // Functions have already been resolved when the arguments were evaluated,
+    // so the value can be evaluated without a context.
+    let value = fun(function.clone(), [x.clone().into()])
+        .evaluate(&HashMap::new())
+        .map_err(|_| function.clone())?;
+
+    match value.typ() {
+        Type::Number(z, _) if z.im.is_zero() => {
+            z.re.to_f64()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| function.clone())
+        }
+        _ => Err(function.clone()),
+    }
+}
+
+/// Returns the integral of the function over the interval from `a` to `b`,
+/// given the values of the function at `a`, `b`, and the midpoint `m` of the interval,
+/// as well as the approximation `whole` of the integral obtained from Simpson's rule.
+/// The interval is recursively split in half until the approximations for both halves
+/// are within `tolerance` of the approximation for the whole interval.
+#[allow(clippy::too_many_arguments)]
+fn adaptive_simpson(
+    function: &Expression,
+    a: &Rational,
+    fa: f64,
+    b: &Rational,
+    fb: f64,
+    m: &Rational,
+    fm: f64,
+    whole: f64,
+    tolerance: f64,
+    depth: usize,
+) -> Result<f64, Expression> {
+    let two = Rational::from_integer(Integer::from(2));
+
+    let left_m = (a + m) / &two;
+    let right_m = (m + b) / &two;
+
+    let f_left_m = value(function, &left_m)?;
+    let f_right_m = value(function, &right_m)?;
+
+    let half_width = (m - a).to_f64().unwrap();
+
+    let left = half_width / 6.0 * (fa + 4.0 * f_left_m + fm);
+    let right = half_width / 6.0 * (fm + 4.0 * f_right_m + fb);
+
+    let delta = left + right - whole;
+
+    if depth == 0 || delta.abs() <= 15.0 * tolerance {
+        // Richardson extrapolation.
+        Ok(left + right + delta / 15.0)
+    } else {
+        Ok(adaptive_simpson(
+            function,
+            a,
+            fa,
+            m,
+            fm,
+            &left_m,
+            f_left_m,
+            left,
+            tolerance / 2.0,
+            depth - 1,
+        )? + adaptive_simpson(
+            function,
+            m,
+            fm,
+            b,
+            fb,
+            &right_m,
+            f_right_m,
+            right,
+            tolerance / 2.0,
+            depth - 1,
+        )?)
+    }
+}
+
+#[function(
+    name = "integrate_numeric",
+    description = "numerical approximation of the definite integral of a function with one argument from `a` to `b`, computed using adaptive Simpson's rule and rounded to 10 decimal places (user-defined functions work as well, e.g. after entering `f(x) = x^2`, `integrate_numeric(f, 0, 1)` is `0.3333333333`)",
+    examples = r#"[
+        ("integrate_numeric(abs, 0, 3)", "4.5"),
+        ("integrate_numeric(abs, -1, 1)", "1"),
+        ("integrate_numeric(abs, 2, 0)", "-2"),
+        ("integrate_numeric(abs, 1/2, 1/2)", "0"),
+        ("integrate_numeric(f, 0, 1)", "integrate_numeric(f, 0, 1)"),
+    ]"#,
+    categories = r#"[
+        "calculus",
+    ]"#
+)]
+fn integrate_numeric(
+    function: Expression,
+    a: Rational,
+    b: Rational,
+) -> Result<Expression, Expression> {
+    match function {
+        Expression::Function(_, _) => (),
+        // The integral of an undefined function remains symbolic.
+        Expression::Variable(_) => {
+            return Ok(fun(
+                function_expression("integrate_numeric").unwrap(),
+                [function, a.into(), b.into()],
+            ))
+        }
+        _ => return Err(function),
+    }
+
+    let two = Rational::from_integer(Integer::from(2));
+    let m = (&a + &b) / &two;
+
+    let fa = value(&function, &a)?;
+    let fb = value(&function, &b)?;
+    let fm = value(&function, &m)?;
+
+    let whole = (&b - &a).to_f64().unwrap() / 6.0 * (fa + 4.0 * fm + fb);
+
+    let integral = adaptive_simpson(
+        &function, &a, fa, &b, fb, &m, fm, whole, TOLERANCE, MAX_DEPTH,
+    )?;
+
+    let scale = Rational::from_integer(pow(Integer::from(10), DECIMAL_PLACES));
+
+    let integral = Rational::from_float(integral).ok_or_else(|| function.clone())?;
+
+    Ok(Expression::Rational(
+        (integral * &scale).round() / scale,
+        RationalRepresentation::Decimal,
+    ))
+}
+
+/// Returns the derivative of the expression with respect to the variable
+/// with the given identifier, or `None` if the derivative cannot be computed
+/// (e.g. because the expression contains an undefined function of the variable).
+fn derivative(expression: &Expression, identifier: &str) -> Option<Expression> {
+    use crate::expression::Expression::*;
+    use crate::helpers::pow;
+
+    if expression.free_of(identifier) {
+        return Some(int(0));
+    }
+
+    let d = |expression: &Expression| derivative(expression, identifier);
+
+    match expression {
+        Variable(_) => Some(int(1)),
+        Vector(v) => Some(Vector(v.iter().map(d).collect::<Option<Vec<_>>>()?.into())),
+        Matrix(m) => {
+            let elements = m.iter().map(d).collect::<Option<Vec<_>>>()?;
+
+            Some(Matrix(crate::expression::Matrix::from_vec(
+                m.nrows(),
+                m.ncols(),
+                elements,
+            )))
+        }
+        Negation(a) => Some(-d(a)?),
+        Sum(a, b) => Some(d(a)? + d(b)?),
+        Difference(a, b) => Some(d(a)? - d(b)?),
+        // Product rule
+        Product(a, b) => Some(d(a)? * *b.clone() + *a.clone() * d(b)?),
+        // Quotient rule
+        Quotient(a, b) => Some((d(a)? * *b.clone() - *a.clone() * d(b)?) / pow(*b.clone(), int(2))),
+        // Power rule, which only applies to exponents that do not depend on the variable.
+        Power(a, b) if b.free_of(identifier) => {
+            Some(*b.clone() * pow(*a.clone(), *b.clone() - int(1)) * d(a)?)
+        }
+        FunctionValue(function, arguments) if arguments.len() == 1 => {
+            let name = match &**function {
+                Variable(identifier) | Function(identifier, _) => identifier.as_str(),
+                _ => return None,
+            };
+
+            let x = arguments[0].clone();
+
+            // Chain rule
+            let outer_derivative = match name {
+                "sin" => fun(function_expression("cos").unwrap(), [x.clone()]),
+                "cos" => -fun(function_expression("sin").unwrap(), [x.clone()]),
+                "tan" => {
+                    int(1)
+                        + pow(
+                            fun(function_expression("tan").unwrap(), [x.clone()]),
+                            int(2),
+                        )
+                }
+                _ => return None,
+            };
+
+            Some(outer_derivative * d(&x)?)
+        }
+        _ => None,
+    }
+}
+
+#[function(
+    name = "diff",
+    description = "derivative of an expression with respect to a variable, computed symbolically using the sum, product, quotient, power, and chain rules (supports powers with exponents that do not depend on the variable, as well as `sin`, `cos`, and `tan`, and differentiates vectors and matrices elementwise)",
+    examples = r#"[
+        ("diff(x^2, x)", "2 * x"),
+        ("diff(x^3 + 2 * x, x)", "3 * x ^ 2 + 2"),
+        ("diff(a * x + b, x)", "a"),
+        ("diff(1 / x, x)", "-1 / x ^ 2"),
+        ("diff(sin(2 * x), x)", "cos(2 * x) * 2"),
+        ("diff([x, x^2], x)", "[1, 2 * x]"),
+        ("diff(y, x)", "0"),
+        ("diff(f(x), x)", "diff(f(x), x)"),
+        ("diff(2^x, x)", "diff(2 ^ x, x)"),
+    ]"#,
+    categories = r#"[
+        "calculus",
+    ]"#
+)]
+fn diff(expression: Expression, variable: Expression) -> Result<Expression, Expression> {
+    let identifier = match &variable {
+        Expression::Variable(identifier) => identifier.clone(),
+        _ => return Err(variable),
+    };
+
+    if let Type::Boolean(_) = expression.typ() {
+        return Err(expression);
+    }
+
+    Ok(derivative(&expression, &identifier)
+        .unwrap_or_else(|| fun(function_expression("diff").unwrap(), [expression, variable])))
+}
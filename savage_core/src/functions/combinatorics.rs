@@ -1,18 +1,23 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
-use num::range_inclusive;
+use num::{range_inclusive, One, Signed, ToPrimitive};
 use savage_macros::function;
 
-use crate::{expression::Integer, functions::NonNegativeInteger};
+use crate::{
+    expression::{Expression, Integer, Vector},
+    functions::{function_expression, NonNegativeInteger},
+    helpers::*,
+};
 
 #[function(
     name = "factorial",
-    description = "factorial of a non-negative integer",
+    description = "factorial of a non-negative integer (can also be written using the postfix operator `!`, e.g. `5!` is `120`)",
     examples = r#"[
         ("factorial(0)", "1"),
         ("factorial(1)", "1"),
         ("factorial(4)", "24"),
+        ("factorial(5)", "120"),
         ("factorial(10)", "3628800"),
     ]"#,
     categories = r#"[
@@ -22,3 +27,114 @@ use crate::{expression::Integer, functions::NonNegativeInteger};
 fn factorial(n: NonNegativeInteger) -> Integer {
     range_inclusive::<Integer>(1.into(), n).product()
 }
+
+#[function(
+    name = "gamma",
+    description = "gamma function, which is `factorial(n - 1)` for positive integers `n` (for all other arguments, the gamma function has no exact value that Savage can represent, so the function call is left unevaluated)",
+    examples = r#"[
+        ("gamma(1)", "1"),
+        ("gamma(5)", "24"),
+        ("gamma(11)", "3628800"),
+        ("gamma(1/2)", "gamma(1/2)"),
+        ("gamma(x)", "gamma(x)"),
+    ]"#,
+    categories = r#"[
+        "combinatorics",
+    ]"#
+)]
+fn gamma(x: Expression) -> Result<Expression, Expression> {
+    match x {
+        Expression::Integer(n) if n.is_positive() => Ok(int(factorial(n - Integer::one()))),
+        // The gamma function has poles at all non-positive integers.
+        Expression::Integer(_) => Err(x),
+        _ => Ok(fun(function_expression("gamma").unwrap(), [x])),
+    }
+}
+
+/// Appends all permutations of the elements of `rest` to `permutations`,
+/// each preceded by the elements of `prefix`, in lexicographic order of positions.
+fn push_permutations(
+    prefix: &mut Vec<Expression>,
+    rest: &[Expression],
+    permutations: &mut Vec<Expression>,
+) {
+    if rest.is_empty() {
+        permutations.push(Vector::from_vec(prefix.clone()).into());
+        return;
+    }
+
+    for i in 0..rest.len() {
+        let mut remaining = rest.to_vec();
+        prefix.push(remaining.remove(i));
+        push_permutations(prefix, &remaining, permutations);
+        prefix.pop();
+    }
+}
+
+#[function(
+    name = "permutations",
+    description = "vector of all permutations of the elements of a vector (note that a vector with n elements has n! permutations)",
+    examples = r#"[
+        ("permutations([1, 2, 3])", "[[1, 2, 3], [1, 3, 2], [2, 1, 3], [2, 3, 1], [3, 1, 2], [3, 2, 1]]"),
+        ("permutations([a, b])", "[[a, b], [b, a]]"),
+        ("permutations([x])", "[[x]]"),
+    ]"#,
+    categories = r#"[
+        "combinatorics",
+    ]"#
+)]
+fn permutations(vector: Vector) -> Vector {
+    let mut permutations = Vec::new();
+
+    push_permutations(&mut Vec::new(), vector.as_slice(), &mut permutations);
+
+    Vector::from_vec(permutations)
+}
+
+/// Appends all combinations of `k` elements of `rest` to `combinations`,
+/// each preceded by the elements of `prefix`, in lexicographic order of positions.
+fn push_combinations(
+    prefix: &mut Vec<Expression>,
+    rest: &[Expression],
+    k: usize,
+    combinations: &mut Vec<Expression>,
+) {
+    if k == 0 {
+        combinations.push(Vector::from_vec(prefix.clone()).into());
+        return;
+    }
+
+    for i in 0..rest.len() {
+        if rest.len() - i < k {
+            break;
+        }
+
+        prefix.push(rest[i].clone());
+        push_combinations(prefix, &rest[(i + 1)..], k - 1, combinations);
+        prefix.pop();
+    }
+}
+
+#[function(
+    name = "combinations",
+    description = "vector of all combinations of `k` elements of a vector, preserving the order of the elements (note that a vector with n elements has n! / (k! * (n - k)!) such combinations)",
+    examples = r#"[
+        ("combinations([1, 2, 3], 2)", "[[1, 2], [1, 3], [2, 3]]"),
+        ("combinations([a, b, c, d], 3)", "[[a, b, c], [a, b, d], [a, c, d], [b, c, d]]"),
+        ("combinations([1, 2], 3)", "[]"),
+    ]"#,
+    categories = r#"[
+        "combinatorics",
+    ]"#
+)]
+fn combinations(vector: Vector, k: NonNegativeInteger) -> Vector {
+    let mut combinations = Vec::new();
+
+    // If k does not fit into a usize, it is certainly larger than
+    // the number of elements, so there are no combinations.
+    if let Some(k) = k.to_usize() {
+        push_combinations(&mut Vec::new(), vector.as_slice(), k, &mut combinations);
+    }
+
+    Vector::from_vec(combinations)
+}
@@ -1,18 +1,48 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
+use std::collections::HashMap;
+
+use num::{integer::lcm, One, Signed, ToPrimitive, Zero};
 use permutohedron::heap_recursive;
 use savage_macros::function;
 
-use crate::{expression::Expression, functions::SquareMatrix, helpers::*};
+use crate::{
+    expression::{Complex, Expression, Integer, Matrix, Rational, Vector},
+    functions::{
+        function_expression, polynomials::polynomial, FunctionError, NonNegativeInteger,
+        SquareMatrix,
+    },
+    helpers::*,
+};
+
+/// Returns whether the permutation of `0..n` is even,
+/// i.e. whether it has an even number of inversions.
+fn is_even(permutation: &[usize]) -> bool {
+    let mut inversions = 0;
+
+    for i in 0..permutation.len() {
+        for j in (i + 1)..permutation.len() {
+            if permutation[i] > permutation[j] {
+                inversions += 1;
+            }
+        }
+    }
+
+    inversions % 2 == 0
+}
 
 #[function(
     name = "det",
+    aliases = r#"[
+        "determinant",
+    ]"#,
     description = "determinant of a square matrix",
     examples = r#"[
         ("det([[1, 2], [3, 4]])", "-2"),
         ("det([[a, b], [c, d]])", "a * d - b * c"),
         ("det([])", "1"),
+        ("determinant([[2, 0], [0, 3]])", "6"),
     ]"#,
     categories = r#"[
         "linear algebra",
@@ -25,33 +55,1122 @@ fn determinant(matrix: SquareMatrix) -> Expression {
 
     let mut indices = (0..matrix.nrows()).collect::<Vec<usize>>();
 
-    let mut products = Vec::new();
+    let mut terms = Vec::new();
 
     heap_recursive(indices.as_mut_slice(), |permutation| {
-        products.push(
-            (0..matrix.nrows())
-                .map(|i| matrix[(i, permutation[i])].clone())
-                .reduce(|a, b| a * b)
-                .unwrap(),
-        );
+        let product = (0..matrix.nrows())
+            .map(|i| matrix[(i, permutation[i])].clone())
+            .reduce(|a, b| a * b)
+            .unwrap();
+
+        terms.push((is_even(permutation), product));
     });
 
-    // The first permutation generated by Heap's algorithm is the identity,
-    // which has positive sign...
-    let mut positive = true;
+    let mut terms = terms.into_iter();
+
+    let (even, product) = terms.next().unwrap();
+    let mut determinant = if even { product } else { -product };
+
+    for (even, product) in terms {
+        determinant = if even {
+            determinant + product
+        } else {
+            determinant - product
+        };
+    }
+
+    determinant
+}
+
+#[function(
+    name = "is_invertible",
+    description = "whether the given square matrix is invertible, i.e. whether its determinant is nonzero",
+    examples = r#"[
+        ("is_invertible([[1, 2], [3, 4]])", "true"),
+        ("is_invertible([[1, 2], [2, 4]])", "false"),
+        ("is_invertible([[1, 0, 0], [0, i, 0], [0, 0, 1/2]])", "true"),
+        ("is_invertible([[a, 0], [0, 1]])", "is_invertible([[a, 0], [0, 1]])"),
+        ("is_invertible([[a, a], [b, b]])", "false"),
+        ("is_invertible([])", "true"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn is_invertible(matrix: SquareMatrix) -> Result<Expression, Expression> {
+    let determinant = determinant(matrix.clone())
+        .evaluate(&HashMap::new())
+        .map_err(|_| Expression::from(matrix.clone()))?;
+
+    Ok(match determinant {
+        Expression::Integer(n) => Expression::Boolean(!n.is_zero()),
+        Expression::Rational(x, _) => Expression::Boolean(!x.is_zero()),
+        Expression::Complex(z, _) => Expression::Boolean(!z.is_zero()),
+        // Whether a symbolic determinant is nonzero depends on the values of its variables.
+        _ => fun(
+            function_expression("is_invertible").unwrap(),
+            [matrix.into()],
+        ),
+    })
+}
+
+/// Largest integer whose divisors are searched for rational roots of polynomials.
+/// Searching the divisors of larger integers by trial division would be too slow.
+const MAX_DIVISOR_SEARCH: u64 = 1_000_000_000_000;
+
+/// Returns the positive divisors of the nonzero integer,
+/// or `None` if its absolute value exceeds [`MAX_DIVISOR_SEARCH`].
+fn divisors(n: &Integer) -> Option<Vec<Integer>> {
+    let n = n.abs();
+
+    if n > Integer::from(MAX_DIVISOR_SEARCH) {
+        return None;
+    }
+
+    let mut divisors = Vec::new();
+
+    let root = n.sqrt();
+
+    let mut i = Integer::one();
+
+    while i <= root {
+        if (&n % &i).is_zero() {
+            divisors.push(i.clone());
+            divisors.push(&n / &i);
+        }
+
+        i += 1;
+    }
+
+    Some(divisors)
+}
+
+/// Returns the roots of the polynomial with the given coefficients
+/// (in order of increasing degree), repeated according to their multiplicity,
+/// if all of them are rational numbers, or `None` otherwise.
+/// Roots are found using the rational root theorem.
+fn rational_roots(mut coefficients: Vec<Rational>) -> Option<Vec<Rational>> {
+    while coefficients.len() > 1 && coefficients.last().unwrap().is_zero() {
+        coefficients.pop();
+    }
+
+    let mut roots = Vec::new();
+
+    'deflate: while coefficients.len() > 1 {
+        if coefficients[0].is_zero() {
+            roots.push(Rational::zero());
+            coefficients.remove(0);
+            continue;
+        }
+
+        let denominator = coefficients
+            .iter()
+            .fold(Integer::one(), |a, c| lcm(a, c.denom().clone()));
+
+        let integers = coefficients
+            .iter()
+            .map(|c| (c * &denominator).to_integer())
+            .collect::<Vec<_>>();
+
+        for p in divisors(&integers[0])? {
+            for q in divisors(integers.last().unwrap())? {
+                for candidate in [Rational::new(p.clone(), q.clone()), Rational::new(-&p, q)] {
+                    // Synthetic division by (x - candidate), whose remainder
+                    // is the value of the polynomial at the candidate.
+                    let mut quotient = vec![Rational::zero(); coefficients.len() - 1];
+                    let mut value = Rational::zero();
+
+                    for (i, coefficient) in coefficients.iter().enumerate().rev() {
+                        value = value * &candidate + coefficient;
+
+                        if i > 0 {
+                            quotient[i - 1] = value.clone();
+                        }
+                    }
+
+                    if value.is_zero() {
+                        roots.push(candidate);
+                        coefficients = quotient;
+                        continue 'deflate;
+                    }
+                }
+            }
+        }
 
-    products
+        return None;
+    }
+
+    Some(roots)
+}
+
+#[function(
+    name = "eigenvals",
+    description = "eigenvalues of a square matrix with rational elements and at most 3 rows, in increasing order and repeated according to their algebraic multiplicity, if all of them are rational numbers (otherwise, the expression remains unevaluated)",
+    examples = r#"[
+        ("eigenvals([[2, 0], [0, 3]])", "[2, 3]"),
+        ("eigenvals([[2, 1], [1, 2]])", "[1, 3]"),
+        ("eigenvals([[1, 1], [0, 1]])", "[1, 1]"),
+        ("eigenvals([[2, 0, 0], [0, 3, 4], [0, 4, 9]])", "[1, 2, 11]"),
+        ("eigenvals([[1/2]])", "[1/2]"),
+        ("eigenvals([])", "[]"),
+        ("eigenvals([[1, 2], [3, 4]])", "eigenvals([[1, 2], [3, 4]])"),
+        ("eigenvals([[a, 0], [0, b]])", "eigenvals([[a, 0], [0, b]])"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn eigenvals(matrix: SquareMatrix) -> Result<Expression, Expression> {
+    let unevaluated = || {
+        Ok(fun(
+            function_expression("eigenvals").unwrap(),
+            [matrix.clone().into()],
+        ))
+    };
+
+    if matrix.nrows() > 3
+        || matrix
+            .iter()
+            .any(|element| Rational::try_from(element.clone()).is_err())
+    {
+        return unevaluated();
+    }
+
+    let lambda = var("lambda");
+
+    // Characteristic polynomial det(A - lambda * I).
+    let characteristic_polynomial =
+        determinant(Matrix::from_fn(matrix.nrows(), matrix.ncols(), |i, j| {
+            if i == j {
+                matrix[(i, j)].clone() - lambda.clone()
+            } else {
+                matrix[(i, j)].clone()
+            }
+        }));
+
+    let coefficients = polynomial(&characteristic_polynomial, "lambda")
+        .map_err(|_| Expression::from(matrix.clone()))?
         .into_iter()
-        .reduce(|a, b| {
-            // ... and every following permutation differs from its predecessor
-            // by exactly one transposition, which flips the sign.
-            positive = !positive;
+        .map(|coefficient| {
+            coefficient
+                .evaluate(&HashMap::new())
+                .ok()
+                .and_then(|coefficient| Rational::try_from(coefficient).ok())
+        })
+        .collect::<Option<Vec<_>>>();
+
+    match coefficients.and_then(rational_roots) {
+        Some(mut roots) => {
+            roots.sort();
+            Ok(Vector::from_vec(roots.into_iter().map(Expression::from).collect()).into())
+        }
+        None => unevaluated(),
+    }
+}
+
+/// Returns the product of the matrices, with its elements evaluated,
+/// or `None` if evaluation fails.
+fn evaluated_product(a: &Matrix, b: &Matrix) -> Option<Matrix> {
+    // The product is computed elementwise because simplification
+    // turns the product of a matrix with itself into a power.
+    let product = Matrix::from_fn(a.nrows(), b.ncols(), |i, j| {
+        (0..a.ncols())
+            .map(|k| a[(i, k)].clone() * b[(k, j)].clone())
+            .reduce(|a, b| a + b)
+            .unwrap()
+    });
+
+    Expression::Matrix(product)
+        .evaluate(&HashMap::new())
+        .ok()
+        .and_then(|product| Matrix::try_from(product).ok())
+}
+
+/// Returns the exponential of the matrix computed from its power series,
+/// or `None` if the matrix is not nilpotent, in which case the series does not terminate.
+fn nilpotent_exponential(matrix: &Matrix) -> Option<Expression> {
+    let identity = Matrix::from_fn(matrix.nrows(), matrix.ncols(), |i, j| int((i == j) as u8));
+
+    let mut exponential = Expression::Matrix(identity.clone());
+    let mut power = identity;
+    let mut factorial = Integer::one();
+
+    // The `n`th power of a nilpotent `n`x`n` matrix is zero.
+    for k in 1..=matrix.nrows() {
+        power = evaluated_product(&power, matrix)?;
+
+        if power.iter().all(|element| *element == int(0)) {
+            return exponential.evaluate(&HashMap::new()).ok();
+        }
+
+        factorial *= k;
+
+        exponential += Expression::Matrix(power.clone())
+            * Expression::from(Rational::new(Integer::one(), factorial.clone()));
+    }
+
+    None
+}
+
+/// Returns the exponential of the matrix computed as `P * D * P^-1`, where `D`
+/// is the diagonal matrix of the exponentials of the eigenvalues of the matrix
+/// and the columns of `P` are corresponding eigenvectors, or `None` if the matrix
+/// does not have rational eigenvalues or is not diagonalizable.
+fn diagonalizable_exponential(matrix: &Matrix) -> Option<Expression> {
+    let eigenvalues = match eigenvals(matrix.clone()).ok()? {
+        Expression::Vector(eigenvalues) => eigenvalues,
+        _ => return None,
+    };
+
+    let mut exponentials = Vec::new();
+    let mut eigenvectors = Vec::new();
+
+    for (i, eigenvalue) in eigenvalues.iter().enumerate() {
+        // Eigenvalues are sorted, so repeated eigenvalues are adjacent.
+        if i > 0 && eigenvalues[i - 1] == *eigenvalue {
+            continue;
+        }
+
+        // The eigenspace is the null space of A - lambda * I.
+        let shifted_matrix =
+            Expression::Matrix(Matrix::from_fn(matrix.nrows(), matrix.ncols(), |i, j| {
+                if i == j {
+                    matrix[(i, j)].clone() - eigenvalue.clone()
+                } else {
+                    matrix[(i, j)].clone()
+                }
+            }))
+            .evaluate(&HashMap::new())
+            .ok()?;
+
+        let eigenspace = nullspace(Matrix::try_from(shifted_matrix).ok()?).ok()?;
+
+        if let Expression::Vector(basis) = eigenspace {
+            for eigenvector in basis.iter() {
+                exponentials.push(pow(var("e"), eigenvalue.clone()));
+                eigenvectors.push(Vector::try_from(eigenvector.clone()).ok()?);
+            }
+        }
+    }
+
+    // The matrix is diagonalizable if and only if its eigenvectors span the whole space.
+    if eigenvectors.len() < matrix.nrows() {
+        return None;
+    }
+
+    let eigenvector_matrix = Matrix::from_columns(&eigenvectors);
+    let inverse_eigenvector_matrix =
+        Matrix::try_from(inverse(eigenvector_matrix.clone()).ok()?).ok()?;
+    let n = eigenvectors.len();
+
+    let exponential = evaluated_product(
+        &evaluated_product(
+            &eigenvector_matrix,
+            &Matrix::from_fn(n, n, |i, j| {
+                if i == j {
+                    exponentials[i].clone()
+                } else {
+                    int(0)
+                }
+            }),
+        )?,
+        &inverse_eigenvector_matrix,
+    )?;
+
+    // Evaluation leaves the sums of powers of `e` in a rather verbose form.
+    Some(exponential.map(|element| element.canonical()).into())
+}
+
+#[function(
+    name = "expm",
+    description = "exponential of a square matrix, computed exactly (in terms of the symbol `e`) from the terminating power series if the matrix is nilpotent, or from an eigendecomposition if the matrix has rational elements and eigenvalues and is diagonalizable (otherwise, the expression remains unevaluated)",
+    examples = r#"[
+        ("expm([[0, 1], [0, 0]])", "[[1, 1], [0, 1]]"),
+        ("expm([[0, 1, 0], [0, 0, 1], [0, 0, 0]])", "[[1, 1, 1/2], [0, 1, 1], [0, 0, 1]]"),
+        ("expm([[0, a], [0, 0]])", "[[1, a], [0, 1]]"),
+        ("expm([[0, 0], [0, 0]])", "[[1, 0], [0, 1]]"),
+        ("expm([[1, 0], [0, 2]])", "[[e, 0], [0, e ^ 2]]"),
+        ("expm([[2, 1], [1, 2]])", "[[1/2 * e ^ 3 + 1/2 * e, 1/2 * e ^ 3 - 1/2 * e], [1/2 * e ^ 3 - 1/2 * e, 1/2 * e ^ 3 + 1/2 * e]]"),
+        ("expm([])", "[]"),
+        ("expm([[1, 1], [0, 1]])", "expm([[1, 1], [0, 1]])"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn expm(matrix: SquareMatrix) -> Expression {
+    if matrix.is_empty() {
+        return matrix.into();
+    }
+
+    nilpotent_exponential(&matrix)
+        .or_else(|| diagonalizable_exponential(&matrix))
+        .unwrap_or_else(|| fun(function_expression("expm").unwrap(), [matrix.into()]))
+}
+
+#[function(
+    name = "is_square",
+    description = "whether the given matrix is square, which the empty matrix is considered to be",
+    examples = r#"[
+        ("is_square([[1, 2], [3, 4]])", "true"),
+        ("is_square([[1, 2, 3]])", "false"),
+        ("is_square([[a]])", "true"),
+        ("is_square([1, 2])", "false"),
+        ("is_square([])", "true"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn is_square(matrix: Matrix) -> Expression {
+    Expression::Boolean(matrix.is_square() || matrix.is_empty())
+}
+
+#[function(
+    name = "transpose",
+    description = "transpose of a matrix, with vectors being treated as column vectors (so transposing a vector gives a single-row matrix, and transposing that gives the vector again)",
+    examples = r#"[
+        ("transpose([[1, 2], [3, 4]])", "[[1, 3], [2, 4]]"),
+        ("transpose([[1, 2, 3], [4, 5, 6]])", "[[1, 4], [2, 5], [3, 6]]"),
+        ("transpose([1, 2, 3])", "[[1, 2, 3]]"),
+        ("transpose([[1, 2, 3]])", "[1, 2, 3]"),
+        ("transpose(transpose([a, b]))", "[a, b]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn transpose(matrix: Matrix) -> Matrix {
+    matrix.transpose()
+}
+
+#[function(
+    name = "outer",
+    description = "outer product of two vectors, i.e. the matrix whose element at row `i` and column `j` is the product of the `i`-th element of the first vector and the `j`-th element of the second vector",
+    examples = r#"[
+        ("outer([1, 2], [3, 4])", "[[3, 4], [6, 8]]"),
+        ("outer([1, 2, 3], [a, b])", "[[a, b], [2 * a, 2 * b], [3 * a, 3 * b]]"),
+        ("outer([2, 1/2], [4])", "[8, 2]"),
+        ("outer([x], [y, z])", "[[x * y, x * z]]"),
+        ("outer([], [1, 2])", "[]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn outer(u: Vector, v: Vector) -> Matrix {
+    Matrix::from_fn(u.len(), v.len(), |i, j| u[i].clone() * v[j].clone())
+}
+
+#[function(
+    name = "shape",
+    description = "vector of the number of rows and columns of a matrix, or of the length of a vector",
+    examples = r#"[
+        ("shape([[1, 2, 3], [4, 5, 6]])", "[2, 3]"),
+        ("shape([[a, b]])", "[1, 2]"),
+        ("shape([1, 2, 3])", "[3]"),
+        ("shape([])", "[0]"),
+        ("shape(a)", "shape(a)"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn shape(collection: Expression) -> Result<Expression, Expression> {
+    use crate::expression::Type::{Arithmetic, Boolean as Bool, Unknown};
+
+    match &collection {
+        Expression::Vector(vector) => Ok(Vector::from_vec(vec![int(vector.len())]).into()),
+        Expression::Matrix(matrix) => {
+            Ok(Vector::from_vec(vec![int(matrix.nrows()), int(matrix.ncols())]).into())
+        }
+        // An expression whose type is not known yet might still evaluate
+        // to a collection, so the function call is left unevaluated.
+        _ if matches!(collection.typ(), Bool(None) | Arithmetic | Unknown) => {
+            Ok(fun(function_expression("shape").unwrap(), [collection]))
+        }
+        _ => Err(collection),
+    }
+}
+
+/// Returns the index as a `usize` if it is a valid row or column index for a matrix
+/// with the given number of rows or columns, or the index as an expression otherwise.
+fn matrix_index(index: Integer, size: usize) -> Result<usize, Expression> {
+    index
+        .to_usize()
+        .filter(|&i| i < size)
+        .ok_or_else(|| int(index))
+}
+
+#[function(
+    name = "minor",
+    description = "determinant of the submatrix obtained by deleting the 0-based row `i` and column `j` of a square matrix",
+    examples = r#"[
+        ("minor([[1, 2], [3, 4]], 0, 0)", "4"),
+        ("minor([[1, 2, 3], [4, 5, 6], [7, 8, 10]], 1, 2)", "-6"),
+        ("minor([[a, b], [c, d]], 1, 0)", "b"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn minor(matrix: SquareMatrix, i: Integer, j: Integer) -> Result<Expression, Expression> {
+    let i = matrix_index(i, matrix.nrows())?;
+    let j = matrix_index(j, matrix.ncols())?;
+
+    // Matrix::remove_row and Matrix::remove_column are unsound for element types
+    // that implement Drop, so the submatrix is assembled from its components instead.
+    let size = matrix.nrows() - 1;
+    let skip = |index, deleted| if index < deleted { index } else { index + 1 };
+
+    Ok(determinant(Matrix::from_fn(size, size, |row, column| {
+        matrix[(skip(row, i), skip(column, j))].clone()
+    })))
+}
+
+#[function(
+    name = "cofactor",
+    description = "minor of a square matrix at the 0-based row `i` and column `j`, multiplied by `(-1)^(i+j)`",
+    examples = r#"[
+        ("cofactor([[1, 2], [3, 4]], 0, 0)", "4"),
+        ("cofactor([[1, 2], [3, 4]], 0, 1)", "-3"),
+        ("cofactor([[a, b], [c, d]], 1, 0)", "-b"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn cofactor(matrix: SquareMatrix, i: Integer, j: Integer) -> Result<Expression, Expression> {
+    let positive = (i.clone() + j.clone()) % 2 == Integer::from(0);
+    let minor = minor(matrix, i, j)?;
+
+    Ok(if positive { minor } else { -minor })
+}
+
+#[function(
+    name = "inverse",
+    description = "inverse of an invertible square matrix, computed as the adjugate matrix divided by the determinant",
+    examples = r#"[
+        ("inverse([[1, 2], [3, 4]])", "[[-2, 1], [3/2, -1/2]]"),
+        ("inverse([[2, 0, 0], [0, 1/2, 0], [0, 0, 1]])", "[[1/2, 0, 0], [0, 2, 0], [0, 0, 1]]"),
+        ("inverse([[2]])", "[1/2]"),
+        ("inverse([[a, b], [c, d]])", "[[d / (a * d - b * c), -(b / (a * d - b * c))], [-(c / (a * d - b * c)), a / (a * d - b * c)]]"),
+        ("inverse([])", "[]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn inverse(matrix: SquareMatrix) -> Result<Expression, Expression> {
+    let determinant = determinant(matrix.clone())
+        .evaluate(&HashMap::new())
+        .map_err(|_| Expression::from(matrix.clone()))?;
+
+    if matches!(Complex::try_from(determinant.clone()), Ok(z) if z.is_zero()) {
+        return Err(matrix.into());
+    }
+
+    let mut adjugate = Vec::new();
+
+    for i in 0..matrix.nrows() {
+        for j in 0..matrix.ncols() {
+            adjugate.push(cofactor(matrix.clone(), j.into(), i.into())?);
+        }
+    }
+
+    Ok(Matrix::from_row_iterator(
+        matrix.nrows(),
+        matrix.ncols(),
+        adjugate
+            .into_iter()
+            .map(|cofactor| cofactor / determinant.clone()),
+    )
+    .into())
+}
+
+#[function(
+    name = "hstack",
+    description = "horizontal concatenation of two matrices with the same number of rows",
+    examples = r#"[
+        ("hstack([[1], [2]], [[3], [4]])", "[[1, 3], [2, 4]]"),
+        ("hstack([[1, 2], [3, 4]], [5, 6])", "[[1, 2, 5], [3, 4, 6]]"),
+        ("hstack([a], [b])", "[[a, b]]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn hstack(a: Matrix, b: Matrix) -> Result<Matrix, Expression> {
+    if a.nrows() != b.nrows() {
+        return Err(b.into());
+    }
+
+    Ok(Matrix::from_fn(a.nrows(), a.ncols() + b.ncols(), |i, j| {
+        if j < a.ncols() {
+            a[(i, j)].clone()
+        } else {
+            b[(i, j - a.ncols())].clone()
+        }
+    }))
+}
+
+#[function(
+    name = "vstack",
+    description = "vertical concatenation of two matrices with the same number of columns",
+    examples = r#"[
+        ("vstack([[1, 2]], [[3, 4]])", "[[1, 2], [3, 4]]"),
+        ("vstack([[1, 2], [3, 4]], [[5, 6]])", "[[1, 2], [3, 4], [5, 6]]"),
+        ("vstack([a, b], [c])", "[a, b, c]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn vstack(a: Matrix, b: Matrix) -> Result<Matrix, Expression> {
+    if a.ncols() != b.ncols() {
+        return Err(b.into());
+    }
+
+    Ok(Matrix::from_fn(a.nrows() + b.nrows(), a.ncols(), |i, j| {
+        if i < a.nrows() {
+            a[(i, j)].clone()
+        } else {
+            b[(i - a.nrows(), j)].clone()
+        }
+    }))
+}
 
-            if positive {
-                a + b
+#[function(
+    name = "submatrix",
+    description = "block of a matrix given by the half-open, 0-based row range from `row_start` to `row_end` and column range from `column_start` to `column_end`",
+    examples = r#"[
+        ("submatrix([[1, 2, 3], [4, 5, 6], [7, 8, 9]], 1, 3, 0, 2)", "[[4, 5], [7, 8]]"),
+        ("submatrix([[1, 2, 3], [4, 5, 6], [7, 8, 9]], 0, 1, 0, 3)", "[[1, 2, 3]]"),
+        ("submatrix([[a, b], [c, d]], 0, 2, 1, 2)", "[b, d]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn submatrix(
+    matrix: Matrix,
+    row_start: NonNegativeInteger,
+    row_end: NonNegativeInteger,
+    column_start: NonNegativeInteger,
+    column_end: NonNegativeInteger,
+) -> Result<Matrix, Expression> {
+    let range = |start: Integer, end: Integer, size: usize| {
+        let start_index = start.to_usize().ok_or_else(|| int(start.clone()))?;
+        let end_index = end
+            .to_usize()
+            .filter(|&i| i <= size)
+            .ok_or_else(|| int(end))?;
+
+        if start_index > end_index {
+            Err(int(start))
+        } else {
+            Ok(start_index..end_index)
+        }
+    };
+
+    let rows = range(row_start, row_end, matrix.nrows())?;
+    let columns = range(column_start, column_end, matrix.ncols())?;
+
+    Ok(matrix
+        .slice((rows.start, columns.start), (rows.len(), columns.len()))
+        .clone_owned())
+}
+
+#[function(
+    name = "row_swap",
+    description = "matrix with the 0-based rows `i` and `j` exchanged (an elementary row operation)",
+    examples = r#"[
+        ("row_swap([[1, 2], [3, 4]], 0, 1)", "[[3, 4], [1, 2]]"),
+        ("row_swap([[1, 2], [3, 4], [5, 6]], 2, 0)", "[[5, 6], [3, 4], [1, 2]]"),
+        ("row_swap([[a, b], [c, d]], 1, 1)", "[[a, b], [c, d]]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn row_swap(matrix: Matrix, i: Integer, j: Integer) -> Result<Matrix, Expression> {
+    let i = matrix_index(i, matrix.nrows())?;
+    let j = matrix_index(j, matrix.nrows())?;
+
+    let mut matrix = matrix;
+    matrix.swap_rows(i, j);
+
+    Ok(matrix)
+}
+
+#[function(
+    name = "row_scale",
+    description = "matrix with the 0-based row `i` multiplied by a non-zero factor (an elementary row operation)",
+    examples = r#"[
+        ("row_scale([[1, 2], [3, 4]], 1, 1/3)", "[[1, 2], [1, 4/3]]"),
+        ("row_scale([[1, 2], [3, 4]], 0, -2)", "[[-2, -4], [3, 4]]"),
+        ("row_scale([[a, b], [c, d]], 0, k)", "[[a * k, b * k], [c, d]]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn row_scale(matrix: Matrix, i: Integer, factor: Expression) -> Result<Matrix, Expression> {
+    let i = matrix_index(i, matrix.nrows())?;
+
+    // Multiplying a row by zero is not an elementary row operation,
+    // as it cannot be undone.
+    if factor == int(0) {
+        return Err(factor);
+    }
+
+    let mut matrix = matrix;
+
+    for element in matrix.row_mut(i).iter_mut() {
+        *element = element.clone() * factor.clone();
+    }
+
+    Ok(matrix)
+}
+
+#[function(
+    name = "row_add",
+    description = "matrix with a multiple of the 0-based row `j` added to the 0-based row `i`, where `i` and `j` are different (an elementary row operation)",
+    examples = r#"[
+        ("row_add([[1, 2], [3, 4]], 1, 0, -3)", "[[1, 2], [0, -2]]"),
+        ("row_add([[1, 2], [3, 4]], 0, 1, 1/2)", "[[5/2, 4], [3, 4]]"),
+        ("row_add([[a, b], [c, d]], 1, 0, k)", "[[a, b], [c + k * a, d + k * b]]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn row_add(
+    matrix: Matrix,
+    i: Integer,
+    j: Integer,
+    factor: Expression,
+) -> Result<Matrix, Expression> {
+    let i = matrix_index(i, matrix.nrows())?;
+    let j_index = matrix_index(j.clone(), matrix.nrows())?;
+
+    // Adding a multiple of a row to itself is a scaling of that row,
+    // which is not an elementary row operation of this type.
+    if i == j_index {
+        return Err(int(j));
+    }
+
+    let mut matrix = matrix;
+
+    for column in 0..matrix.ncols() {
+        matrix[(i, column)] =
+            matrix[(i, column)].clone() + factor.clone() * matrix[(j_index, column)].clone();
+    }
+
+    Ok(matrix)
+}
+
+/// Linear form in a list of variables, represented by the coefficients of the variables
+/// (`None` meaning that the form does not depend on that variable) and the constant term.
+type LinearForm = (Vec<Option<Expression>>, Expression);
+
+/// Returns the expression as a linear form in the variables,
+/// or `None` if the expression is not linear in the variables.
+fn linear_form(expression: &Expression, variables: &[String]) -> Option<LinearForm> {
+    use crate::expression::Expression::*;
+
+    let combine = |a: LinearForm, b: LinearForm, f: fn(Expression, Expression) -> Expression| {
+        let coefficients =
+            a.0.into_iter()
+                .zip(b.0)
+                .map(|coefficients| match coefficients {
+                    (Some(a), Some(b)) => Some(f(a, b)),
+                    (Some(a), None) => Some(f(a, int(0))),
+                    (None, Some(b)) => Some(f(int(0), b)),
+                    (None, None) => None,
+                })
+                .collect();
+
+        (coefficients, f(a.1, b.1))
+    };
+
+    let scale = |a: LinearForm, f: &dyn Fn(Expression) -> Expression| {
+        (a.0.into_iter().map(|c| c.map(f)).collect(), f(a.1))
+    };
+
+    let is_constant = |a: &LinearForm| a.0.iter().all(Option::is_none);
+
+    match expression {
+        Variable(identifier) if variables.contains(identifier) => Some((
+            variables
+                .iter()
+                .map(|variable| (variable == identifier).then(|| int(1)))
+                .collect(),
+            int(0),
+        )),
+        Negation(a) => Some(scale(linear_form(a, variables)?, &|x| -x)),
+        Sum(a, b) => Some(combine(
+            linear_form(a, variables)?,
+            linear_form(b, variables)?,
+            |a, b| a + b,
+        )),
+        Difference(a, b) => Some(combine(
+            linear_form(a, variables)?,
+            linear_form(b, variables)?,
+            |a, b| a - b,
+        )),
+        Product(a, b) => {
+            let a = linear_form(a, variables)?;
+            let b = linear_form(b, variables)?;
+
+            if is_constant(&a) {
+                Some(scale(b, &|x| a.1.clone() * x))
+            } else if is_constant(&b) {
+                Some(scale(a, &|x| x * b.1.clone()))
             } else {
-                a - b
+                None
             }
+        }
+        Quotient(a, b) => {
+            let a = linear_form(a, variables)?;
+            let b = linear_form(b, variables)?;
+
+            if is_constant(&b) {
+                Some(scale(a, &|x| x / b.1.clone()))
+            } else {
+                None
+            }
+        }
+        _ if expression
+            .variables()
+            .iter()
+            .any(|identifier| variables.contains(identifier)) =>
+        {
+            None
+        }
+        _ => Some((vec![None; variables.len()], expression.clone())),
+    }
+}
+
+/// Brings a system of linear equations with the given number of variables into
+/// reduced row echelon form using Gauss-Jordan elimination, where each row holds
+/// the coefficients of one equation, applying the same row operations to
+/// the right-hand sides. Returns the columns containing pivots, in order.
+fn row_reduce(
+    rows: &mut [Vec<Complex>],
+    right_hand_sides: &mut [Expression],
+    columns: usize,
+) -> Vec<usize> {
+    let mut pivot_columns = Vec::new();
+
+    for column in 0..columns {
+        let pivot_row = pivot_columns.len();
+
+        let row = match (pivot_row..rows.len()).find(|&row| !rows[row][column].is_zero()) {
+            Some(row) => row,
+            None => continue,
+        };
+
+        rows.swap(pivot_row, row);
+        right_hand_sides.swap(pivot_row, row);
+
+        let pivot = rows[pivot_row][column].clone();
+
+        for element in &mut rows[pivot_row] {
+            *element /= pivot.clone();
+        }
+
+        right_hand_sides[pivot_row] /= pivot.into();
+
+        let pivot_elements = rows[pivot_row].clone();
+        let pivot_right_hand_side = right_hand_sides[pivot_row].clone();
+
+        for (row, (elements, right_hand_side)) in
+            rows.iter_mut().zip(right_hand_sides.iter_mut()).enumerate()
+        {
+            let factor = elements[column].clone();
+
+            if row == pivot_row || factor.is_zero() {
+                continue;
+            }
+
+            for (element, pivot_element) in elements.iter_mut().zip(&pivot_elements) {
+                *element -= factor.clone() * pivot_element.clone();
+            }
+
+            *right_hand_side -= Expression::from(factor) * pivot_right_hand_side.clone();
+        }
+
+        pivot_columns.push(column);
+    }
+
+    pivot_columns
+}
+
+#[function(
+    name = "solve",
+    description = "solution of a system of linear equations with numeric coefficients for a vector of variables (variables that are not determined by the system are left as free parameters)",
+    examples = r#"[
+        ("solve([x + y == 3, x - y == 1], [x, y])", "[2, 1]"),
+        ("solve([2 * x == 4 - y, (x + 1) / 2 == y], [x, y])", "[7/5, 6/5]"),
+        ("solve([x + 2 * i * y == 3, y == i], [x, y])", "[5, i]"),
+        ("solve([x + y == 3], [x, y])", "[3 - y, y]"),
+        ("solve([x + y == a], [x, y])", "[a - y, y]"),
+        ("solve([x == 1, x == 1], [x])", "[1]"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn solve(equations: Vector, variables: Vector) -> Result<Expression, FunctionError> {
+    let unevaluated = || {
+        fun(
+            function_expression("solve").unwrap(),
+            [equations.clone().into(), variables.clone().into()],
+        )
+    };
+
+    let mut identifiers = Vec::new();
+
+    for variable in variables.iter() {
+        if let Expression::Variable(identifier) = variable {
+            identifiers.push(identifier.clone());
+        } else {
+            return Err(variable.clone().into());
+        }
+    }
+
+    let context = HashMap::new();
+
+    // Each row holds the coefficients of the variables in one equation,
+    // with all terms involving the variables moved to the left.
+    let mut rows = Vec::new();
+    let mut right_hand_sides = Vec::new();
+
+    for equation in equations.iter() {
+        // Equations that evaluated to a boolean value contain none of the variables,
+        // and are equivalent to `0 == 0` if true and to `0 == 1` if false.
+        let difference = match equation {
+            Expression::Boolean(true) => continue,
+            Expression::Boolean(false) => int(-1),
+            Expression::Equal(a, b) => (**a).clone() - (**b).clone(),
+            _ => return Err(equation.clone().into()),
+        };
+
+        let (coefficients, constant) =
+            linear_form(&difference, &identifiers).ok_or_else(|| equation.clone())?;
+
+        let mut row = Vec::new();
+
+        for coefficient in coefficients {
+            let coefficient = coefficient
+                .unwrap_or_else(|| int(0))
+                .evaluate(&context)
+                .map_err(|_| equation.clone())?;
+
+            match Complex::try_from(coefficient) {
+                Ok(coefficient) => row.push(coefficient),
+                Err(_) => return Ok(unevaluated()),
+            }
+        }
+
+        rows.push(row);
+        right_hand_sides.push(-constant);
+    }
+
+    let pivot_columns = row_reduce(&mut rows, &mut right_hand_sides, identifiers.len());
+
+    // The rank of the coefficient matrix is the number of pivots. Rows without a pivot
+    // have only zero coefficients left, so the augmented matrix has a higher rank
+    // if and only if one of those rows has a nonzero right-hand side.
+    let rank = pivot_columns.len();
+    let mut augmented_rank = rank;
+
+    for right_hand_side in &right_hand_sides[rank..] {
+        let right_hand_side = right_hand_side
+            .evaluate(&context)
+            .map_err(|_| Expression::from(equations.clone()))?;
+
+        match Complex::try_from(right_hand_side) {
+            Ok(value) if value.is_zero() => (),
+            Ok(_) => augmented_rank = rank + 1,
+            Err(_) => return Ok(unevaluated()),
+        }
+    }
+
+    // Rouché-Capelli theorem: The system has no solution if the ranks differ,
+    // a unique solution if they equal the number of variables, and infinitely many
+    // solutions otherwise, parameterized by the variables without a pivot.
+    if augmented_rank > rank {
+        return Err(FunctionError::NoSolution(equations.into()));
+    }
+
+    let mut solution = variables.clone();
+
+    for (row, &column) in pivot_columns.iter().enumerate() {
+        let mut value = right_hand_sides[row].clone();
+
+        for (i, variable) in variables.iter().enumerate() {
+            if !pivot_columns.contains(&i) && !rows[row][i].is_zero() {
+                value -= Expression::from(rows[row][i].clone()) * variable.clone();
+            }
+        }
+
+        solution[column] = value
+            .evaluate(&context)
+            .map_err(|_| Expression::from(equations.clone()))?;
+    }
+
+    Ok(solution.into())
+}
+
+#[function(
+    name = "solve_steps",
+    description = "steps for solving a linear equation with a numeric coefficient for a variable, i.e. the equation with all terms moved to the left, collected into a multiple of the variable and a constant term, with the constant term moved to the right, and finally solved for the variable",
+    examples = r#"[
+        ("solve_steps(2 * x + 3 == 7, x)", "[2 * x + 3 == 7, 2 * x + 3 - 7 == 0, 2 * x - 4 == 0, 2 * x == 4, x == 2]"),
+        ("solve_steps(x == 5, x)", "[x == 5, x - 5 == 0, x == 5]"),
+        ("solve_steps(3 * x == x + a, x)", "[3 * x == x + a, 3 * x - (x + a) == 0, 2 * x - a == 0, 2 * x == a, x == a / 2]"),
+        ("solve_steps(x ^ 2 == 4, x)", "solve_steps(x ^ 2 == 4, x)"),
+        ("solve_steps(a * x == 1, x)", "solve_steps(a * x == 1, x)"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn solve_steps(equation: Expression, variable: Expression) -> Result<Expression, Expression> {
+    use crate::expression::Expression::*;
+
+    let unevaluated = || {
+        fun(
+            function_expression("solve_steps").unwrap(),
+            [equation.clone(), variable.clone()],
+        )
+    };
+
+    let identifier = match &variable {
+        Variable(identifier) => identifier.clone(),
+        _ => return Err(variable),
+    };
+
+    let difference = match &equation {
+        Equal(a, b) => (**a).clone() - (**b).clone(),
+        _ => return Err(equation),
+    };
+
+    let (coefficients, constant) = match linear_form(&difference, &[identifier]) {
+        Some(form) => form,
+        None => return Ok(unevaluated()),
+    };
+
+    let context = HashMap::new();
+
+    let evaluate =
+        |expression: Expression| expression.evaluate(&context).map_err(|_| equation.clone());
+
+    let coefficient = evaluate(coefficients[0].clone().unwrap_or_else(|| int(0)))?;
+    let constant = evaluate(constant)?;
+
+    match crate::expression::Complex::try_from(coefficient.clone()) {
+        Ok(value) if !value.is_zero() => (),
+        _ => return Ok(unevaluated()),
+    }
+
+    let mut steps = vec![
+        equation.clone(),
+        Equal(Box::new(difference), Box::new(int(0))),
+        Equal(
+            Box::new(match &constant {
+                Negation(a) => coefficient.clone() * variable.clone() - (**a).clone(),
+                _ => coefficient.clone() * variable.clone() + constant.clone(),
+            }),
+            Box::new(int(0)),
+        ),
+        Equal(
+            Box::new(coefficient.clone() * variable.clone()),
+            Box::new(-constant.clone()),
+        ),
+        Equal(Box::new(variable), Box::new(-constant / coefficient)),
+    ]
+    .into_iter()
+    .map(evaluate)
+    .collect::<Result<Vec<_>, _>>()?;
+
+    // Some steps do not change anything for simple equations (e.g. `x == 5`).
+    // Steps are compared by their string representation because differently
+    // structured expressions (e.g. `x - 5` and `x + -5`) can look the same.
+    steps.dedup_by(|a, b| a.to_string() == b.to_string());
+
+    Ok(Vector(steps.into()))
+}
+
+#[function(
+    name = "nullspace",
+    description = "basis of the null space (kernel) of a matrix with numeric elements, i.e. of the space of vectors `v` with `A * v == 0`, computed using Gauss-Jordan elimination (the basis is empty if the null space only contains the zero vector)",
+    examples = r#"[
+        ("nullspace([[1, 1], [1, 1]])", "[[-1, 1]]"),
+        ("nullspace([[1, 2, 3], [4, 5, 6]])", "[[1, -2, 1]]"),
+        ("nullspace([[1, 2, 3]])", "[[-2, 1, 0], [-3, 0, 1]]"),
+        ("nullspace([[0, 0]])", "[[1, 0], [0, 1]]"),
+        ("nullspace([[1, 0], [0, 1]])", "[]"),
+        ("nullspace([])", "[]"),
+        ("nullspace([[1, a]])", "nullspace([[1, a]])"),
+    ]"#,
+    categories = r#"[
+        "linear algebra",
+    ]"#
+)]
+fn nullspace(matrix: Matrix) -> Result<Expression, Expression> {
+    // The empty matrix is considered to be square (see `is_square`),
+    // so its null space is trivial.
+    if matrix.is_empty() {
+        return Ok(Expression::Vector(Vector::from_vec(Vec::new())));
+    }
+
+    let mut rows = Vec::new();
+
+    for row in matrix.row_iter() {
+        let mut elements = Vec::new();
+
+        for element in row.iter() {
+            match Complex::try_from(element.clone()) {
+                Ok(element) => elements.push(element),
+                Err(_) => {
+                    return Ok(fun(
+                        function_expression("nullspace").unwrap(),
+                        [matrix.into()],
+                    ))
+                }
+            }
+        }
+
+        rows.push(elements);
+    }
+
+    // The null space is the set of solutions of the homogeneous system,
+    // whose right-hand sides are all zero.
+    let mut right_hand_sides = vec![int(0); rows.len()];
+
+    let pivot_columns = row_reduce(&mut rows, &mut right_hand_sides, matrix.ncols());
+
+    // Each column without a pivot corresponds to a free variable. Setting that variable
+    // to 1 and all other free variables to 0 determines one vector of the basis.
+    let basis = (0..matrix.ncols())
+        .filter(|column| !pivot_columns.contains(column))
+        .map(|free_column| {
+            let mut vector = vec![Complex::zero(); matrix.ncols()];
+
+            vector[free_column] = Complex::one();
+
+            for (row, &column) in pivot_columns.iter().enumerate() {
+                vector[column] = -rows[row][free_column].clone();
+            }
+
+            Expression::Vector(Vector::from_vec(
+                vector.into_iter().map(Expression::from).collect(),
+            ))
         })
-        .unwrap()
+        .collect();
+
+    Ok(Expression::Vector(Vector::from_vec(basis)))
 }
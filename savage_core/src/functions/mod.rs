@@ -1,12 +1,27 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
+mod arithmetic;
+mod bitwise;
+mod caching;
+mod calculus;
 mod combinatorics;
 mod linear_algebra;
 mod logic;
 mod number_theory;
+mod polynomials;
+mod random;
+mod rounding;
+mod simplification;
+mod substitution;
+mod trigonometry;
+mod vectors;
 
-use std::rc::Rc;
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    rc::Rc,
+};
 
 use num::Signed;
 use savage_macros::functions;
@@ -53,11 +68,38 @@ pub enum Parameter {
     Boolean,
 }
 
+/// Formats the parameter as a noun phrase with an indefinite article
+/// (e.g. "a square matrix"), for use in error messages.
+impl Display for Parameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use Parameter::*;
+
+        write!(
+            f,
+            "{}",
+            match self {
+                Expression => "an expression",
+                Integer => "an integer",
+                NonNegativeInteger => "a non-negative integer",
+                PositiveInteger => "a positive integer",
+                Rational => "a rational number",
+                Complex => "a complex number",
+                Vector => "a vector",
+                Matrix => "a matrix",
+                SquareMatrix => "a square matrix",
+                Boolean => "a boolean value",
+            },
+        )
+    }
+}
+
 /// Metadata associated with a function.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Metadata {
     /// Name used to represent the function (also, default identifier for invoking the function).
     pub name: &'static str,
+    /// Alternative identifiers for invoking the function.
+    pub aliases: &'static [&'static str],
     /// Human-readable description of the function.
     pub description: &'static str,
     /// Parameters expected by the function, in the expected order.
@@ -66,6 +108,32 @@ pub struct Metadata {
     pub examples: &'static [(&'static str, &'static str)],
     /// Categories associated with the function.
     pub categories: &'static [&'static str],
+    /// Whether the function holds the variables bound in its last argument
+    /// (see [`FunctionImplementation::holds_bindings`]).
+    pub holds_bindings: bool,
+}
+
+impl Metadata {
+    /// Returns all identifiers that can be used to invoke the function,
+    /// starting with its name and followed by its aliases.
+    pub fn identifiers(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+}
+
+/// Reason why a function implementation failed to compute a result.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub(crate) enum FunctionError {
+    /// Argument that is invalid for the function.
+    InvalidArgument(Expression),
+    /// System of equations (given as an argument) that has no solution.
+    NoSolution(Expression),
+}
+
+impl From<Expression> for FunctionError {
+    fn from(argument: Expression) -> Self {
+        FunctionError::InvalidArgument(argument)
+    }
 }
 
 /// Function definition.
@@ -77,85 +145,185 @@ pub struct Function {
 }
 
 /// Returns a regular function implementation that type-checks its arguments
-/// based on the given `parameters` and then invokes the given function `proxy`.
+/// based on the parameters given in `metadata` and then invokes the given function `proxy`.
 fn wrap_proxy(
-    parameters: &'static [Parameter],
-    proxy: impl Fn(&[Expression]) -> Result<Expression, Expression> + 'static,
+    metadata: &Metadata,
+    proxy: impl Fn(&[Expression]) -> Result<Expression, FunctionError> + 'static,
 ) -> Rc<FunctionImplementation> {
     use crate::evaluate::Error::*;
     use crate::expression::Type::{Arithmetic, Boolean as Bool, Unknown};
     use Parameter::*;
 
-    Rc::new(move |expression, arguments, _| {
-        if arguments.len() != parameters.len() {
-            return Err(InvalidNumberOfArguments {
-                expression: expression.clone(),
-                min_number: parameters.len(),
-                max_number: parameters.len(),
-                given_number: arguments.len(),
-            });
-        }
+    let parameters = metadata.parameters;
 
-        for (argument, parameter) in arguments.iter().zip(parameters) {
-            if let Bool(None) | Arithmetic | Unknown = argument.typ() {
-                if *parameter != Expression {
-                    return Ok(expression.clone());
-                }
+    Rc::new(FunctionImplementation {
+        implementation: Box::new(move |expression, arguments, _| {
+            if arguments.len() != parameters.len() {
+                return Err(InvalidNumberOfArguments {
+                    expression: expression.clone(),
+                    min_number: parameters.len(),
+                    max_number: parameters.len(),
+                    given_number: arguments.len(),
+                });
             }
 
-            let mut argument_valid = true;
-
-            match parameter {
-                NonNegativeInteger => {
-                    if let Ok(integer) = crate::expression::Integer::try_from(argument.clone()) {
-                        argument_valid = !integer.is_negative();
+            for (index, (argument, parameter)) in arguments.iter().zip(parameters).enumerate() {
+                if let Bool(None) | Arithmetic | Unknown = argument.typ() {
+                    if *parameter != Expression {
+                        return Ok(expression.clone());
                     }
                 }
-                PositiveInteger => {
-                    if let Ok(integer) = crate::expression::Integer::try_from(argument.clone()) {
-                        argument_valid = integer.is_positive();
-                    }
-                }
-                SquareMatrix => {
-                    if let Ok(matrix) = crate::expression::Matrix::try_from(argument.clone()) {
-                        argument_valid = matrix.is_square() || matrix.is_empty();
-                    }
+
+                // The conversions performed by the proxy are checked here, so that
+                // arguments of the wrong type are reported along with the expected type.
+                let argument_valid = match parameter {
+                    Expression => true,
+                    Integer => crate::expression::Integer::try_from(argument.clone()).is_ok(),
+                    NonNegativeInteger => matches!(
+                        crate::expression::Integer::try_from(argument.clone()),
+                        Ok(integer) if !integer.is_negative(),
+                    ),
+                    PositiveInteger => matches!(
+                        crate::expression::Integer::try_from(argument.clone()),
+                        Ok(integer) if integer.is_positive(),
+                    ),
+                    Rational => crate::expression::Rational::try_from(argument.clone()).is_ok(),
+                    Complex => crate::expression::Complex::try_from(argument.clone()).is_ok(),
+                    // Matrices are accepted only if they consist of a single column,
+                    // which is how vectors are represented as matrices.
+                    Vector => crate::expression::Vector::try_from(argument.clone()).is_ok(),
+                    Matrix => crate::expression::Matrix::try_from(argument.clone()).is_ok(),
+                    SquareMatrix => matches!(
+                        crate::expression::Matrix::try_from(argument.clone()),
+                        Ok(matrix) if matrix.is_square() || matrix.is_empty(),
+                    ),
+                    Boolean => bool::try_from(argument.clone()).is_ok(),
+                };
+
+                if !argument_valid {
+                    return Err(InvalidArgumentType {
+                        expression: expression.clone(),
+                        argument: argument.clone(),
+                        index,
+                        parameter: *parameter,
+                    });
                 }
-                _ => (),
             }
 
-            if !argument_valid {
-                return Err(InvalidArgument {
+            proxy(arguments).map_err(|error| match error {
+                FunctionError::InvalidArgument(argument) => InvalidArgument {
                     expression: expression.clone(),
-                    argument: argument.clone(),
-                });
-            }
-        }
-
-        proxy(arguments).map_err(|argument| InvalidArgument {
-            expression: expression.clone(),
-            argument,
-        })
+                    argument,
+                },
+                FunctionError::NoSolution(equations) => NoSolution {
+                    expression: expression.clone(),
+                    equations,
+                },
+            })
+        }),
+        holds_bindings: metadata.holds_bindings,
     })
 }
 
 /// Returns all available functions.
 pub fn functions() -> Vec<Function> {
-    functions!(
+    let mut functions = functions!(
+        arithmetic::abs,
         logic::and,
+        bitwise::bitand,
+        bitwise::bitor,
+        bitwise::bitxor,
+        bitwise::shl,
+        bitwise::shr,
         combinatorics::factorial,
+        combinatorics::gamma,
+        combinatorics::permutations,
+        combinatorics::combinations,
+        calculus::integrate_numeric,
+        calculus::diff,
+        caching::memoize,
         linear_algebra::determinant,
+        linear_algebra::is_invertible,
+        linear_algebra::eigenvals,
+        linear_algebra::expm,
+        linear_algebra::is_square,
+        linear_algebra::shape,
+        linear_algebra::transpose,
+        linear_algebra::outer,
+        linear_algebra::hstack,
+        linear_algebra::vstack,
+        linear_algebra::submatrix,
+        linear_algebra::row_swap,
+        linear_algebra::row_scale,
+        linear_algebra::row_add,
+        linear_algebra::minor,
+        linear_algebra::cofactor,
+        linear_algebra::inverse,
+        linear_algebra::solve,
+        linear_algebra::solve_steps,
+        linear_algebra::nullspace,
         number_theory::is_prime,
         number_theory::nth_prime,
         number_theory::prime_pi,
-    )
+        number_theory::floor_div,
+        number_theory::floor_mod,
+        number_theory::gcd,
+        number_theory::lcm,
+        number_theory::rationalize,
+        polynomials::is_polynomial,
+        polynomials::coeffs,
+        polynomials::poly_eval,
+        polynomials::expand,
+        polynomials::factor_out,
+        polynomials::from_roots,
+        polynomials::cancel,
+        rounding::round_to,
+        rounding::chop,
+        rounding::approx,
+        simplification::simplify,
+        substitution::partial,
+        substitution::eval_with,
+        substitution::subs,
+        trigonometry::sin,
+        trigonometry::cos,
+        trigonometry::tan,
+        trigonometry::simplify_trig,
+        trigonometry::deg2rad,
+        trigonometry::rad2deg,
+        vectors::zip,
+        vectors::zip_with,
+        vectors::reduce,
+        vectors::filter,
+        vectors::total,
+        vectors::mean,
+        vectors::tabulate,
+        vectors::counts,
+        vectors::unique,
+        vectors::union,
+        vectors::intersection,
+        vectors::difference,
+    );
+
+    functions.append(&mut random::functions(random::DEFAULT_SEED));
+
+    functions
 }
 
-/// Returns an expression representing the function with the given name,
-/// or `None` if the function library contains no function with that name.
+/// Returns the functions that generate pseudo-random values,
+/// sharing a new generator initialized with the given seed.
+pub fn seeded_functions(seed: u64) -> Vec<Function> {
+    random::functions(seed)
+}
+
+/// Returns an expression representing the function with the given name or alias,
+/// or `None` if the function library contains no function with that identifier.
 pub fn function_expression(name: &str) -> Option<Expression> {
     for function in functions() {
-        if function.metadata.name == name {
+        if function
+            .metadata
+            .identifiers()
+            .any(|identifier| identifier == name)
+        {
             return Some(Expression::Function(
                 function.metadata.name.to_owned(),
                 function.implementation,
@@ -166,11 +334,46 @@ pub fn function_expression(name: &str) -> Option<Expression> {
     None
 }
 
+/// Returns the metadata of all available functions,
+/// in the order in which they are listed in the documentation.
+pub fn all_metadata() -> Vec<Metadata> {
+    functions()
+        .into_iter()
+        .map(|function| function.metadata)
+        .collect()
+}
+
+/// Returns the metadata of the function with the given name or alias,
+/// or `None` if the function library contains no function with that identifier.
+pub fn metadata(name: &str) -> Option<Metadata> {
+    all_metadata()
+        .into_iter()
+        .find(|metadata| metadata.identifiers().any(|identifier| identifier == name))
+}
+
+/// Returns the metadata of all available functions, grouped by category.
+/// Categories are sorted alphabetically, and functions associated with
+/// multiple categories appear in each of them.
+pub fn functions_by_category() -> BTreeMap<&'static str, Vec<Metadata>> {
+    let mut functions_by_category = BTreeMap::new();
+
+    for metadata in all_metadata() {
+        for category in metadata.categories {
+            functions_by_category
+                .entry(*category)
+                .or_insert_with(Vec::new)
+                .push(metadata.clone());
+        }
+    }
+
+    functions_by_category
+}
+
 #[cfg(test)]
 mod tests {
     use crate::evaluate::default_context;
-    use crate::expression::Expression;
-    use crate::functions::functions;
+    use crate::expression::{Expression, Function as FunctionImplementation};
+    use crate::functions::{functions, functions_by_category};
 
     #[track_caller]
     fn t(expression: &str, result: &str) {
@@ -193,4 +396,554 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn invalid_arguments() {
+        use crate::evaluate::Error::InvalidArgument;
+        use crate::helpers::*;
+
+        assert_eq!(
+            fun(var("div"), [int(1), int(0)]).evaluate(&default_context()),
+            Err(InvalidArgument {
+                expression: fun(var("div"), [int(1), int(0)]),
+                argument: int(0),
+            }),
+        );
+    }
+
+    #[test]
+    fn negative_shift() {
+        use crate::evaluate::Error::InvalidArgumentType;
+        use crate::functions::Parameter;
+        use crate::helpers::*;
+
+        assert_eq!(
+            fun(var("shl"), [int(1), int(-1)]).evaluate(&default_context()),
+            Err(InvalidArgumentType {
+                expression: fun(var("shl"), [int(1), int(-1)]),
+                argument: int(-1),
+                index: 1,
+                parameter: Parameter::NonNegativeInteger,
+            }),
+        );
+    }
+
+    #[test]
+    fn argument_types() {
+        use crate::evaluate::Error::InvalidArgumentType;
+        use crate::functions::Parameter;
+        use crate::helpers::*;
+
+        let error = "det([[1, 2]])"
+            .parse::<Expression>()
+            .unwrap()
+            .evaluate(&default_context())
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            InvalidArgumentType {
+                expression: fun(var("det"), ["[[1, 2]]".parse().unwrap()]),
+                argument: "[[1, 2]]".parse().unwrap(),
+                index: 0,
+                parameter: Parameter::SquareMatrix,
+            },
+        );
+        assert_eq!(
+            error.to_string(),
+            "Invalid argument [[1, 2]] in det([[1, 2]]), expected argument 1 to be a square matrix",
+        );
+
+        for (expression, index, parameter) in [
+            ("det(1)", 0, Parameter::SquareMatrix),
+            ("round_to(1, 1/2)", 1, Parameter::NonNegativeInteger),
+            ("div(1, [1])", 1, Parameter::Integer),
+            ("is_prime(true)", 0, Parameter::Integer),
+        ] {
+            assert!(matches!(
+                expression.parse::<Expression>().unwrap().evaluate(&default_context()),
+                Err(InvalidArgumentType { index: i, parameter: p, .. }) if i == index && p == parameter,
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_structured_arguments() {
+        use crate::evaluate::Error::{InvalidArgument, InvalidArgumentType};
+
+        for (expression, argument) in [
+            ("hstack([[1, 2]], [1, 2])", "[1, 2]"),
+            ("vstack([1, 2], [[1, 2]])", "[[1, 2]]"),
+            ("submatrix([[1, 2], [3, 4]], 0, 3, 0, 1)", "3"),
+            ("submatrix([[1, 2], [3, 4]], 0, 1, 2, 1)", "2"),
+            ("minor([[1, 2], [3, 4]], 2, 0)", "2"),
+            ("cofactor([[1, 2], [3, 4]], 0, -1)", "-1"),
+            ("row_swap([[1, 2], [3, 4]], 0, 2)", "2"),
+            ("row_swap([[1, 2], [3, 4]], -1, 0)", "-1"),
+            ("row_scale([[1, 2], [3, 4]], 2, 5)", "2"),
+            ("row_scale([[1, 2], [3, 4]], 0, 0)", "0"),
+            ("row_add([[1, 2], [3, 4]], 0, 0, 1)", "0"),
+            ("row_add([[1, 2], [3, 4]], 0, 2, 1)", "2"),
+            ("solve([x ^ 2 == 1], [x])", "x ^ 2 == 1"),
+            ("solve([x == 1], [1])", "1"),
+            ("coeffs(x + sin(x), x)", "sin(x)"),
+            ("coeffs(1 / x, x)", "1 / x"),
+            ("coeffs(x, 2)", "2"),
+            ("is_polynomial(x, 2)", "2"),
+            ("cancel(x / 0, x)", "0"),
+            ("cancel((x^2 - a) / (x - 1), x)", "x ^ 2 - a"),
+            ("cancel(1 / x, 1)", "1"),
+            ("from_roots([1, 2], 2)", "2"),
+            ("from_roots([1, x], x)", "x"),
+            ("shape(1)", "1"),
+            ("shape(true)", "true"),
+            ("partial(x + y, [x == 1, x == 2])", "x == 2"),
+            ("partial(x + y, [1 == x])", "1 == x"),
+            ("partial(x + y, [x])", "x"),
+            ("eval_with(x + y, [x == 1, x == 2])", "x == 2"),
+            ("eval_with(x + y, [2 * x == 1])", "2 * x == 1"),
+            ("subs(x + y, 2, 1)", "2"),
+            ("factorial(-1)", "-1"),
+            ("(-2)!", "-2"),
+            ("gamma(0)", "0"),
+            ("gamma(-2)", "-2"),
+            ("zip([1, 2], [3])", "[3]"),
+            ("zip_with(div, [1, 2], [3])", "[3]"),
+            ("zip_with(2, [1, 2], [3, 4])", "2"),
+            ("zip_with([f], [1, 2], [3, 4])", "[f]"),
+            ("reduce(1, [1, 2], 0)", "1"),
+            ("filter(factorial, [1, 2])", "factorial"),
+            ("filter(is_prime, [1/2])", "1/2"),
+            ("mean([])", "[]"),
+            ("chop(1, -1)", "-1"),
+            ("inverse([[1, 2], [2, 4]])", "[[1, 2], [2, 4]]"),
+            ("inverse([[a, a], [b, b]])", "[[a, a], [b, b]]"),
+            ("eigenvals([[1, 2]])", "[[1, 2]]"),
+            ("factor_out(2 * a, 0)", "0"),
+            ("tan(pi / 2)", "pi / 2"),
+            ("tan(-3/2 * pi)", "-3 / 2 * pi"),
+            ("sin(true)", "true"),
+            ("cos([1, 2])", "[1, 2]"),
+            ("tabulate(1, 0, 1, 1)", "1"),
+            ("tabulate(abs, 0, 1, 0)", "0"),
+            ("tabulate(abs, 0, 1, i)", "i"),
+            ("memoize(1)", "1"),
+            ("memoize([f])", "[f]"),
+            ("integrate_numeric(1, 0, 1)", "1"),
+            ("integrate_numeric(deg2rad, 0, 1)", "deg2rad"),
+            ("integrate_numeric(abs, 0, i)", "i"),
+            ("diff(x ^ 2, 2)", "2"),
+            ("diff(x == 1, x)", "x == 1"),
+            ("total([[1, 2], [3, 4]])", "[[1, 2], [3, 4]]"),
+            ("total([[1, 2]])", "[[1, 2]]"),
+            ("permutations([[1, 2], [3, 4]])", "[[1, 2], [3, 4]]"),
+            ("zip([1, 2], [[1, 2], [3, 4]])", "[[1, 2], [3, 4]]"),
+            ("poly_eval([[1, 2], [3, 4]], x)", "[[1, 2], [3, 4]]"),
+        ] {
+            assert!(matches!(
+                expression.parse::<Expression>().unwrap().evaluate(&default_context()),
+                Err(InvalidArgument { argument: a, .. } | InvalidArgumentType { argument: a, .. })
+                    if a.to_string() == argument,
+            ));
+        }
+    }
+
+    #[test]
+    fn context_bindings() {
+        use std::rc::Rc;
+
+        use crate::helpers::*;
+
+        let mut context = default_context();
+        context.insert("a".to_owned(), int(5));
+        context.insert("b".to_owned(), int(1));
+
+        let t = |expression: &str, result: &str| {
+            assert_eq!(
+                expression
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&context)
+                    .unwrap()
+                    .to_string(),
+                result,
+            );
+        };
+
+        t("eval_with(a + b, [a == 1])", "2");
+        t("eval_with(a + b, [a == b + 1])", "3");
+        t("eval_with(a + b, [a == b, b == a])", "6");
+        t("eval_with(a * x, [x == a])", "25");
+        t("eval_with(diff(a^2, a), [a == 3])", "6");
+        t("eval_with(1 / (a - 5), [a == 7])", "1/2");
+        t("eval_with(eval_with(a + b, [b == 2]), [a == 1])", "3");
+        t("a", "5");
+
+        // Holding is a property of the built-in function, not of its name.
+        context.insert(
+            "eval_with".to_owned(),
+            Expression::Function(
+                "eval_with".to_owned(),
+                Rc::new(FunctionImplementation::new(|_, arguments, _| {
+                    Ok(arguments[0].clone())
+                })),
+            ),
+        );
+
+        assert_eq!(
+            "eval_with(a + b, [a == 1])"
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(&context)
+                .unwrap(),
+            int(6),
+        );
+    }
+
+    #[test]
+    fn vector_parameters() {
+        t("total([[1], [2], [3]])", "6");
+        t("zip([[1], [2]], [3, 4])", "[[1, 3], [2, 4]]");
+    }
+
+    #[test]
+    fn numerical_integration() {
+        use std::rc::Rc;
+
+        use crate::helpers::{int, pow};
+
+        let mut context = default_context();
+
+        context.insert(
+            "f".to_owned(),
+            Expression::Function(
+                "f".to_owned(),
+                Rc::new(FunctionImplementation::new(|_, arguments, context| {
+                    pow(arguments[0].clone(), int(2)).evaluate(context)
+                })),
+            ),
+        );
+
+        context.insert(
+            "g".to_owned(),
+            Expression::Function(
+                "g".to_owned(),
+                Rc::new(FunctionImplementation::new(|_, arguments, context| {
+                    (int(1) / (int(1) + pow(arguments[0].clone(), int(2)))).evaluate(context)
+                })),
+            ),
+        );
+
+        for (expression, result) in [
+            ("integrate_numeric(f, 0, 1)", "0.3333333333"),
+            ("integrate_numeric(f, -3, 3)", "18"),
+            // The integral of `1 / (1 + x^2)` from 0 to 1 is `pi / 4`.
+            ("integrate_numeric(g, 0, 1)", "0.7853981634"),
+        ] {
+            assert_eq!(
+                expression
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&context)
+                    .unwrap()
+                    .to_string(),
+                result,
+            );
+        }
+    }
+
+    #[test]
+    fn memoization() {
+        use std::{cell::Cell, rc::Rc};
+
+        use crate::helpers::{int, pow};
+
+        let calls = Rc::new(Cell::new(0));
+
+        let mut context = default_context();
+
+        context.insert("f".to_owned(), {
+            let calls = calls.clone();
+
+            Expression::Function(
+                "f".to_owned(),
+                Rc::new(FunctionImplementation::new(move |_, arguments, context| {
+                    calls.set(calls.get() + 1);
+                    (pow(arguments[0].clone(), int(2)) + arguments[1].clone()).evaluate(context)
+                })),
+            )
+        });
+
+        let evaluate = |expression: &str, context: &_| {
+            expression
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(context)
+                .unwrap()
+                .to_string()
+        };
+
+        let g = "memoize(f)"
+            .parse::<Expression>()
+            .unwrap()
+            .evaluate(&context)
+            .unwrap();
+
+        assert!(matches!(&g, Expression::Function(identifier, _) if identifier == "f"));
+
+        context.insert("g".to_owned(), g);
+
+        for (arguments, value) in [("3, 1", "10"), ("a, 2", "a ^ 2 + 2"), ("3, 2", "11")] {
+            let f_value = evaluate(&format!("f({})", arguments), &context);
+
+            assert_eq!(f_value, value);
+            assert_eq!(evaluate(&format!("g({})", arguments), &context), f_value);
+            assert_eq!(evaluate(&format!("g({})", arguments), &context), f_value);
+        }
+
+        // Each distinct combination of arguments is computed only once by `g`,
+        // in addition to the unmemoized calls to `f`.
+        assert_eq!(calls.get(), 6);
+
+        assert_eq!(evaluate("g(3, 1) + g(3, 1) * g(3, 2)", &context), "120");
+        assert_eq!(calls.get(), 6);
+    }
+
+    #[test]
+    fn determinants() {
+        t("det([[2, 0, 1], [1, 3, 2], [1, 1, 2]])", "6");
+        t("det([[6, 1, 1], [4, -2, 5], [2, 8, 7]])", "-306");
+        t("det([[2, 0, 1], [1, 3, 2], [1, 1, 1]])", "0");
+        t("det([[0, 1, 0], [0, 0, 1], [1, 0, 0]])", "1");
+        t("det([[0, 1, 0], [1, 0, 0], [0, 0, 1]])", "-1");
+        t(
+            "det([[1, 0, 2, -1], [3, 0, 0, 5], [2, 1, 4, -3], [1, 0, 5, 0]])",
+            "30",
+        );
+        t(
+            "det([[3, 2, 0, 1], [4, 0, 1, 2], [3, 0, 2, 1], [9, 2, 3, 1]])",
+            "24",
+        );
+        t(
+            "det([[0, 0, 0, 1], [0, 0, 1, 0], [0, 1, 0, 0], [1, 0, 0, 0]])",
+            "1",
+        );
+        t(
+            "det([[1, 2, 3, 4, 5], [2, 3, 1, 5, 4], [3, 1, 2, 2, 1], [4, 5, 1, 2, 3], [5, 4, 3, 1, 2]])",
+            "-54",
+        );
+    }
+
+    #[test]
+    fn seeds() {
+        use crate::evaluate::seed_context;
+
+        let expression = "[random(0, 1000), random(-10^30, 10^30)] + random_matrix(2, 1, 0, 1000)"
+            .parse::<Expression>()
+            .unwrap();
+
+        let outputs = [42, 42, 43].map(|seed| {
+            let mut context = default_context();
+            seed_context(&mut context, seed);
+
+            // The second evaluation continues from the generator state left by the first.
+            [
+                expression.evaluate(&context).unwrap(),
+                expression.evaluate(&context).unwrap(),
+            ]
+        });
+
+        assert_eq!(outputs[0], outputs[1]);
+        assert_ne!(outputs[0][0], outputs[0][1]);
+        assert_ne!(outputs[0], outputs[2]);
+    }
+
+    #[test]
+    fn linear_systems() {
+        use crate::evaluate::Error::NoSolution;
+
+        // Unique solution (rank 3, 3 variables).
+        t(
+            "solve([x + y + z == 6, 2 * y + 5 * z == -4, 2 * x + 5 * y - z == 27], [x, y, z])",
+            "[5, 3, -2]",
+        );
+
+        // No solution (coefficient matrix has rank 1, augmented matrix has rank 2).
+        // Equations that are false regardless of the variables are inconsistent as well.
+        for (expression, equations) in [
+            (
+                "solve([x + y == 1, 2 * x + 2 * y == 3], [x, y])",
+                "[x + y == 1, 2 * x + 2 * y == 3]",
+            ),
+            ("solve([x == 1, x == 2], [x])", "[x == 1, x == 2]"),
+            ("solve([x == 1, 1 == 2], [x])", "[x == 1, 1 == 2]"),
+        ] {
+            let error = expression
+                .parse::<Expression>()
+                .unwrap()
+                .evaluate(&default_context())
+                .unwrap_err();
+
+            assert!(matches!(
+                &error,
+                NoSolution { equations: e, .. } if e.to_string() == equations,
+            ));
+
+            assert_eq!(
+                error.to_string(),
+                format!("The system {} in {} has no solution", equations, expression),
+            );
+        }
+
+        // Infinitely many solutions (rank 2, 3 variables).
+        t(
+            "solve([x + y + z == 1, x - y == 0, 2 * x + z == 1], [x, y, z])",
+            "[1/2 - 1/2 * z, 1/2 - 1/2 * z, z]",
+        );
+
+        // Infinitely many solutions (rank 0, 2 variables).
+        t("solve([0 * x == 0], [x, y])", "[x, y]");
+    }
+
+    #[test]
+    fn expansions() {
+        use crate::helpers::*;
+
+        let p = "3 * x^5 - x^4 + 2 * x^3 + 7 * x - 4";
+        let q = "-x^5 + 5 * x^4 - 3 * x^2 + x + 9";
+
+        let expanded = format!("expand(({}) * ({}), x)", p, q)
+            .parse::<Expression>()
+            .unwrap()
+            .evaluate(&default_context())
+            .unwrap();
+
+        assert!(!expanded.to_string().contains('('));
+
+        // Two polynomials of degree 10 that agree at 11 points are identical.
+        for x in -5..=5 {
+            let mut context = default_context();
+            context.insert("x".to_owned(), int(x));
+
+            assert_eq!(
+                expanded.evaluate(&context),
+                format!("({}) * ({})", p, q)
+                    .parse::<Expression>()
+                    .unwrap()
+                    .evaluate(&context),
+            );
+        }
+    }
+
+    // TODO: Replace with a real benchmark once `#[bench]` is stable.
+    #[test]
+    fn expansion_benchmark() {
+        t(
+            "coeffs(expand((x + 1)^30 * (x - 1)^30, x), x) == coeffs(expand((x^2 - 1)^30, x), x)",
+            "true",
+        );
+    }
+
+    #[test]
+    fn expansion_limit() {
+        t("is_polynomial(x^3000 + x^2999 * (x - 1)^3000, x)", "true");
+        t("is_polynomial((x + 1)^3000 / x, x)", "false");
+
+        t(
+            "expand((x + 1)^32, x) == expand((x + 1)^31 * (x + 1), x)",
+            "true",
+        );
+        t("expand((x + 1)^33, x)", "expand((x + 1) ^ 33, x)");
+        t(
+            "coeffs(x + (x - 1)^1000, x)",
+            "coeffs(x + (x - 1) ^ 1000, x)",
+        );
+        t("expand(x^100, x)", "expand(x ^ 100, x)");
+
+        // Exponents are identified by their values, whatever their representation.
+        t(
+            "expand((x + 1)^2 * (x + 1), x)",
+            "x ^ 3 + 3 * x ^ 2 + 3 * x + 1",
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        use std::collections::HashSet;
+
+        use crate::functions::{all_metadata, metadata, Parameter};
+
+        let det = metadata("det").unwrap();
+
+        assert_eq!(det.name, "det");
+        assert_eq!(det.parameters, [Parameter::SquareMatrix]);
+
+        let hstack = metadata("hstack").unwrap();
+
+        assert_eq!(hstack.parameters, [Parameter::Matrix, Parameter::Matrix]);
+
+        assert_eq!(metadata("undefined_function"), None);
+
+        let all_metadata = all_metadata();
+
+        assert_eq!(all_metadata.len(), functions().len());
+        assert_eq!(
+            all_metadata
+                .iter()
+                .map(|metadata| metadata.name)
+                .collect::<HashSet<_>>()
+                .len(),
+            all_metadata.len(),
+        );
+    }
+
+    #[test]
+    fn aliases() {
+        use std::collections::HashSet;
+
+        use crate::{evaluate::default_context, functions::function_expression};
+
+        let context = default_context();
+        let mut identifiers = HashSet::new();
+
+        for function in functions() {
+            for identifier in function.metadata.identifiers() {
+                assert!(identifiers.insert(identifier));
+
+                match &context[identifier] {
+                    Expression::Function(name, _) => assert_eq!(name, function.metadata.name),
+                    _ => panic!("{} is not a function", identifier),
+                }
+
+                match function_expression(identifier) {
+                    Some(Expression::Function(name, _)) => {
+                        assert_eq!(name, function.metadata.name)
+                    }
+                    _ => panic!("{} is not a function", identifier),
+                }
+            }
+        }
+
+        assert_eq!(
+            functions()
+                .into_iter()
+                .find(|function| function.metadata.name == "det")
+                .unwrap()
+                .metadata
+                .identifiers()
+                .collect::<Vec<_>>(),
+            ["det", "determinant"],
+        );
+    }
+
+    #[test]
+    fn categories() {
+        let functions_by_category = functions_by_category();
+
+        for function in functions() {
+            for category in function.metadata.categories {
+                assert!(functions_by_category[category].contains(&function.metadata));
+            }
+        }
+    }
 }
@@ -1,35 +1,85 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
-use num::ToPrimitive;
+use num::{One, Signed, ToPrimitive, Zero};
 use primal::StreamingSieve;
 use savage_macros::function;
 
 use crate::{
-    expression::Expression,
+    expression::{Expression, Integer, Rational},
     functions::{function_expression, NonNegativeInteger, PositiveInteger},
     helpers::*,
 };
 
+/// Bases for which the Miller-Rabin test correctly identifies all composite numbers
+/// less than `MILLER_RABIN_LIMIT` (see https://oeis.org/A014233).
+const MILLER_RABIN_BASES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// Smallest composite number that passes the Miller-Rabin test for all `MILLER_RABIN_BASES`.
+const MILLER_RABIN_LIMIT: &str = "3317044064679887385961981";
+
+/// Returns whether the odd integer `n`, which must be greater than the largest
+/// of the `MILLER_RABIN_BASES`, passes the Miller-Rabin test for all of those bases.
+fn miller_rabin(n: &Integer) -> bool {
+    let n_minus_1 = n - Integer::one();
+
+    // n - 1 = 2^s * d with d odd
+    let s = n_minus_1.trailing_zeros().unwrap();
+    let d = &n_minus_1 >> s;
+
+    'bases: for base in MILLER_RABIN_BASES {
+        let mut x = Integer::from(base).modpow(&d, n);
+
+        if x.is_one() || x == n_minus_1 {
+            continue;
+        }
+
+        for _ in 1..s {
+            x = x.modpow(&Integer::from(2), n);
+
+            if x == n_minus_1 {
+                continue 'bases;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
 #[function(
     name = "is_prime",
-    description = "whether the given non-negative integer is a prime number",
+    description = "whether the given integer is a prime number (negative numbers, 0, and 1 are not prime; integers greater than 2^64 are tested using the deterministic Miller-Rabin test, which is only guaranteed to be correct for integers less than 3317044064679887385961981, so the expression remains unevaluated for larger integers)",
     examples = r#"[
+        ("is_prime(-7)", "false"),
         ("is_prime(0)", "false"),
         ("is_prime(1)", "false"),
         ("is_prime(2)", "true"),
+        ("is_prime(7)", "true"),
+        ("is_prime(9)", "false"),
         ("is_prime(29)", "true"),
         ("is_prime(2^31)", "false"),
         ("is_prime(2^31 - 1)", "true"),
+        ("is_prime(2^67 - 1)", "false"),
+        ("is_prime(2^61 - 1)", "true"),
+        ("is_prime(2^79 - 67)", "true"),
+        ("is_prime(2^89 - 1)", "is_prime(618970019642690137449562111)"),
     ]"#,
     categories = r#"[
         "number theory",
         "prime numbers",
     ]"#
 )]
-fn is_prime(n: NonNegativeInteger) -> Expression {
-    if let Some(n) = n.to_u64() {
+fn is_prime(n: Integer) -> Expression {
+    if !n.is_positive() {
+        Expression::Boolean(false)
+    } else if let Some(n) = n.to_u64() {
         Expression::Boolean(primal::is_prime(n))
+    } else if n < MILLER_RABIN_LIMIT.parse().unwrap() {
+        // Integers that don't fit into 64 bits are greater than all bases,
+        // and the test is only reached for odd integers.
+        Expression::Boolean(num::Integer::is_odd(&n) && miller_rabin(&n))
     } else {
         fun(function_expression("is_prime").unwrap(), [int(n)])
     }
@@ -79,3 +129,154 @@ fn prime_pi(n: NonNegativeInteger) -> Expression {
         fun(function_expression("prime_pi").unwrap(), [int(n)])
     }
 }
+
+#[function(
+    name = "div",
+    description = "largest integer less than or equal to the quotient of two integers (floor division), which satisfies `div(a, b) * b + mod(a, b) == a`",
+    examples = r#"[
+        ("div(7, 2)", "3"),
+        ("div(-7, 2)", "-4"),
+        ("div(7, -2)", "-4"),
+        ("div(-7, -2)", "3"),
+        ("div(6, 3)", "2"),
+        ("div(a, 2)", "div(a, 2)"),
+    ]"#,
+    categories = r#"[
+        "number theory",
+        "integer arithmetic",
+    ]"#
+)]
+fn floor_div(a: Integer, b: Integer) -> Result<Integer, Expression> {
+    if b.is_zero() {
+        Err(int(b))
+    } else {
+        Ok(num::Integer::div_floor(&a, &b))
+    }
+}
+
+#[function(
+    name = "mod",
+    description = "remainder of the floor division of two integers, which has the same sign as the divisor (unlike `a % b`, which has the same sign as the dividend), so that `div(a, b) * b + mod(a, b) == a`",
+    examples = r#"[
+        ("mod(7, 2)", "1"),
+        ("mod(-7, 2)", "1"),
+        ("mod(7, -2)", "-1"),
+        ("mod(-7, -2)", "-1"),
+        ("mod(6, 3)", "0"),
+        ("mod(a, 2)", "mod(a, 2)"),
+    ]"#,
+    categories = r#"[
+        "number theory",
+        "integer arithmetic",
+    ]"#
+)]
+fn floor_mod(a: Integer, b: Integer) -> Result<Integer, Expression> {
+    if b.is_zero() {
+        Err(int(b))
+    } else {
+        Ok(num::Integer::mod_floor(&a, &b))
+    }
+}
+
+#[function(
+    name = "gcd",
+    description = "greatest common divisor of two integers, which is always non-negative (the greatest common divisor of 0 and 0 is 0)",
+    examples = r#"[
+        ("gcd(12, 18)", "6"),
+        ("gcd(-12, 18)", "6"),
+        ("gcd(7, 0)", "7"),
+        ("gcd(0, 0)", "0"),
+        ("gcd(2^40, 6^20)", "1048576"),
+        ("gcd(a, 6)", "gcd(a, 6)"),
+    ]"#,
+    categories = r#"[
+        "number theory",
+        "integer arithmetic",
+    ]"#
+)]
+fn gcd(a: Integer, b: Integer) -> Integer {
+    num::Integer::gcd(&a, &b)
+}
+
+#[function(
+    name = "lcm",
+    description = "least common multiple of two integers, which is always non-negative (the least common multiple of an integer and 0 is 0)",
+    examples = r#"[
+        ("lcm(4, 6)", "12"),
+        ("lcm(-4, 6)", "12"),
+        ("lcm(7, 1)", "7"),
+        ("lcm(7, 0)", "0"),
+        ("lcm(a, 6)", "lcm(a, 6)"),
+    ]"#,
+    categories = r#"[
+        "number theory",
+        "integer arithmetic",
+    ]"#
+)]
+fn lcm(a: Integer, b: Integer) -> Integer {
+    num::Integer::lcm(&a, &b)
+}
+
+#[function(
+    name = "rationalize",
+    description = "rational number closest to a rational number among those whose denominator is at most the given positive integer",
+    examples = r#"[
+        ("rationalize(3.14159, 100)", "311/99"),
+        ("rationalize(3.14159, 10)", "22/7"),
+        ("rationalize(0.333, 10)", "1/3"),
+        ("rationalize(-1.7, 5)", "-5/3"),
+        ("rationalize(2/3, 3)", "2/3"),
+        ("rationalize(0.9, 1)", "1"),
+    ]"#,
+    categories = r#"[
+        "number theory",
+    ]"#
+)]
+fn rationalize(x: Rational, max_denominator: PositiveInteger) -> Rational {
+    if *x.denom() <= max_denominator {
+        return x;
+    }
+
+    // The best approximation is either the last convergent of the continued fraction
+    // expansion of x whose denominator does not exceed the maximum, or the largest
+    // semiconvergent between that convergent and the one preceding it.
+    // See https://en.wikipedia.org/wiki/Continued_fraction#Best_rational_approximations
+    let (mut p_0, mut q_0, mut p_1, mut q_1) = (
+        Integer::zero(),
+        Integer::one(),
+        Integer::one(),
+        Integer::zero(),
+    );
+
+    let (mut n, mut d) = (x.numer().clone(), x.denom().clone());
+
+    loop {
+        let a = num::Integer::div_floor(&n, &d);
+        let q_2 = &q_0 + &a * &q_1;
+
+        if q_2 > max_denominator {
+            break;
+        }
+
+        let p_2 = &p_0 + &a * &p_1;
+        p_0 = p_1;
+        q_0 = q_1;
+        p_1 = p_2;
+        q_1 = q_2;
+
+        let r = &n - &a * &d;
+        n = d;
+        d = r;
+    }
+
+    let k = (max_denominator - &q_0) / &q_1;
+
+    let semiconvergent = Rational::new(&p_0 + &k * &p_1, q_0 + k * &q_1);
+    let convergent = Rational::new(p_1, q_1);
+
+    if (&convergent - &x).abs() <= (&semiconvergent - &x).abs() {
+        convergent
+    } else {
+        semiconvergent
+    }
+}
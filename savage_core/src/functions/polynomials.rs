@@ -0,0 +1,613 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::{cmp::max, collections::HashMap};
+
+use num::{Signed, ToPrimitive, Zero};
+use savage_macros::function;
+
+use crate::{
+    canonical::MAX_EXPANSION_EXPONENT,
+    expression::{Expression, Integer, Type, Vector},
+    functions::function_expression,
+    helpers::*,
+};
+
+/// Polynomial in a single variable, represented by its coefficients
+/// in order of increasing degree. The list of coefficients is never empty.
+type Polynomial = Vec<Expression>;
+
+/// Returns the polynomials combined coefficient-wise using the function `f`.
+fn combine(
+    a: Polynomial,
+    b: Polynomial,
+    f: fn(Expression, Expression) -> Expression,
+) -> Polynomial {
+    (0..max(a.len(), b.len()))
+        .map(|i| {
+            f(
+                a.get(i).cloned().unwrap_or_else(|| int(0)),
+                b.get(i).cloned().unwrap_or_else(|| int(0)),
+            )
+        })
+        .collect()
+}
+
+/// Returns the product of the polynomials.
+fn multiply(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    let mut product = vec![int(0); a.len() + b.len() - 1];
+
+    for (i, a) in a.iter().enumerate() {
+        for (j, b) in b.iter().enumerate() {
+            product[i + j] = product[i + j].clone() + a.clone() * b.clone();
+        }
+    }
+
+    product
+}
+
+/// Returns the polynomial without zero coefficients for powers above its degree.
+fn trim(mut polynomial: Polynomial) -> Polynomial {
+    while polynomial.len() > 1 && polynomial.last() == Some(&int(0)) {
+        polynomial.pop();
+    }
+
+    polynomial
+}
+
+/// Returns the polynomial with all coefficients evaluated,
+/// or the first coefficient that cannot be evaluated.
+fn evaluate(polynomial: Polynomial) -> Result<Polynomial, Expression> {
+    polynomial
+        .into_iter()
+        .map(|coefficient| {
+            coefficient
+                .evaluate(&HashMap::new())
+                .map_err(|_| coefficient.clone())
+        })
+        .collect()
+}
+
+/// Returns the quotient and the remainder of the polynomial division of `a` by `b`,
+/// which must be non-zero. Both polynomials must have numeric coefficients,
+/// as otherwise it cannot be determined whether the remainder has reached zero.
+fn divide(a: &Polynomial, b: &Polynomial) -> Result<(Polynomial, Polynomial), Expression> {
+    let b = trim(b.clone());
+    let mut remainder = trim(a.clone());
+    let mut quotient = vec![int(0); (remainder.len() + 1).saturating_sub(b.len()).max(1)];
+
+    while remainder.len() >= b.len() && remainder != [int(0)] {
+        let shift = remainder.len() - b.len();
+
+        let factor = (remainder.last().unwrap().clone() / b.last().unwrap().clone())
+            .evaluate(&HashMap::new())
+            .map_err(|_| remainder.last().unwrap().clone())?;
+
+        for (i, coefficient) in b.iter().enumerate() {
+            remainder[shift + i] =
+                remainder[shift + i].clone() - factor.clone() * coefficient.clone();
+        }
+
+        quotient[shift] = factor;
+
+        // The leading coefficient is now zero by construction.
+        remainder.pop();
+
+        if remainder.is_empty() {
+            remainder.push(int(0));
+        }
+
+        remainder = trim(evaluate(remainder)?);
+    }
+
+    Ok((quotient, remainder))
+}
+
+/// Returns the monic greatest common divisor of the polynomials, at least one of which
+/// must be non-zero, computed using the Euclidean algorithm. Both polynomials must have
+/// numeric coefficients.
+fn gcd(a: &Polynomial, b: &Polynomial) -> Result<Polynomial, Expression> {
+    let mut a = trim(a.clone());
+    let mut b = trim(b.clone());
+
+    while b != [int(0)] {
+        let (_, remainder) = divide(&a, &b)?;
+        a = b;
+        b = remainder;
+    }
+
+    let leading_coefficient = a.last().unwrap().clone();
+
+    evaluate(
+        a.into_iter()
+            .map(|coefficient| coefficient / leading_coefficient.clone())
+            .collect(),
+    )
+}
+
+/// Returns the value of the exponent if it is a number with an integer value.
+/// Exponents are not necessarily normalized (e.g. `3` may be represented
+/// as a complex number), so they are identified by their values.
+fn integer_exponent(exponent: &Expression) -> Option<Integer> {
+    Integer::try_from(exponent.clone()).ok()
+}
+
+/// Returns the expression as a polynomial in the variable with the given identifier,
+/// or the first sub-expression that prevents this from being possible.
+/// Powers with exponents greater than `MAX_EXPANSION_EXPONENT` are not expanded.
+pub(super) fn polynomial(
+    expression: &Expression,
+    identifier: &str,
+) -> Result<Polynomial, Expression> {
+    use crate::expression::Expression::*;
+
+    // Coefficients are evaluated after every operation that creates them
+    // to prevent them from growing into huge expressions.
+    let evaluate = |polynomial: Polynomial| {
+        polynomial
+            .into_iter()
+            .map(|coefficient| {
+                coefficient
+                    .evaluate(&HashMap::new())
+                    .map_err(|_| expression.clone())
+            })
+            .collect::<Result<Polynomial, Expression>>()
+    };
+
+    match expression {
+        Variable(i) if i == identifier => Ok(vec![int(0), int(1)]),
+        Negation(a) => evaluate(polynomial(a, identifier)?.into_iter().map(|c| -c).collect()),
+        Sum(a, b) => evaluate(combine(
+            polynomial(a, identifier)?,
+            polynomial(b, identifier)?,
+            |a, b| a + b,
+        )),
+        Difference(a, b) => evaluate(combine(
+            polynomial(a, identifier)?,
+            polynomial(b, identifier)?,
+            |a, b| a - b,
+        )),
+        Product(a, b) => evaluate(multiply(
+            &polynomial(a, identifier)?,
+            &polynomial(b, identifier)?,
+        )),
+        Quotient(a, b) if b.free_of(identifier) => evaluate(
+            polynomial(a, identifier)?
+                .into_iter()
+                .map(|c| c / (**b).clone())
+                .collect(),
+        ),
+        Power(a, b) if !a.free_of(identifier) => {
+            let exponent = integer_exponent(b)
+                .and_then(|n| n.to_u32())
+                .filter(|n| *n <= MAX_EXPANSION_EXPONENT)
+                .ok_or_else(|| expression.clone())?;
+
+            let base = polynomial(a, identifier)?;
+
+            let mut power = vec![int(1)];
+
+            for _ in 0..exponent {
+                power = evaluate(multiply(&power, &base))?;
+            }
+
+            Ok(power)
+        }
+        _ if !expression.free_of(identifier) => Err(expression.clone()),
+        _ => Ok(vec![expression.clone()]),
+    }
+}
+
+/// Returns whether the expression is a polynomial in the variable with the given identifier,
+/// determined from its structure alone, without expanding any powers.
+fn is_polynomial_in(expression: &Expression, identifier: &str) -> bool {
+    use crate::expression::Expression::*;
+
+    match expression {
+        Variable(i) if i == identifier => true,
+        Negation(a) => is_polynomial_in(a, identifier),
+        Sum(a, b) | Difference(a, b) | Product(a, b) => {
+            is_polynomial_in(a, identifier) && is_polynomial_in(b, identifier)
+        }
+        Quotient(a, b) if b.free_of(identifier) => is_polynomial_in(a, identifier),
+        Power(a, b) if !a.free_of(identifier) => {
+            matches!(integer_exponent(b), Some(n) if !n.is_negative())
+                && is_polynomial_in(a, identifier)
+        }
+        _ => expression.free_of(identifier),
+    }
+}
+
+/// Returns whether the expression contains a power of a polynomial in the variable
+/// with the given identifier that is too large to be expanded.
+fn exceeds_expansion_limit(expression: &Expression, identifier: &str) -> bool {
+    expression.parts().iter().any(|part| match part {
+        Expression::Power(a, b) if !a.free_of(identifier) => {
+            matches!(integer_exponent(b), Some(n) if n > MAX_EXPANSION_EXPONENT.into())
+        }
+        _ => false,
+    })
+}
+
+/// Returns the polynomial as a sum of powers of the variable,
+/// from the highest power down to the constant term.
+fn expression(polynomial: Polynomial, variable: &Expression) -> Expression {
+    use crate::expression::Expression::*;
+
+    let mut sum: Option<Expression> = None;
+
+    for (exponent, coefficient) in polynomial.into_iter().enumerate().rev() {
+        // Numeric coefficients are made non-negative, and negated coefficients
+        // are replaced with their operands, with the sign being absorbed into the sum.
+        let (negative, coefficient) = match coefficient {
+            Integer(n) if n.is_zero() => continue,
+            Integer(n) if n.is_negative() => (true, Integer(-n)),
+            Rational(x, representation) if x.is_negative() => (true, Rational(-x, representation)),
+            Negation(a) => (true, *a),
+            coefficient => (false, coefficient),
+        };
+
+        let term = match exponent {
+            0 => coefficient,
+            _ => {
+                let power = match exponent {
+                    1 => variable.clone(),
+                    _ => pow(variable.clone(), int(exponent)),
+                };
+
+                if coefficient == int(1) {
+                    power
+                } else {
+                    coefficient * power
+                }
+            }
+        };
+
+        sum = Some(match (sum, negative) {
+            (None, false) => term,
+            (None, true) => -term,
+            (Some(sum), false) => sum + term,
+            (Some(sum), true) => sum - term,
+        });
+    }
+
+    sum.unwrap_or_else(|| int(0))
+}
+
+#[function(
+    name = "expand",
+    description = "polynomial in a variable expanded into a sum of powers of the variable (left unevaluated if that requires expanding a power of a polynomial with an exponent greater than 32)",
+    examples = r#"[
+        ("expand((x + 1)^2, x)", "x ^ 2 + 2 * x + 1"),
+        ("expand((x - 1) * (x + 1), x)", "x ^ 2 - 1"),
+        ("expand((x + a)^2, x)", "x ^ 2 + 2 * a * x + a ^ 2"),
+        ("expand((2 * x - 1)^3, x)", "8 * x ^ 3 - 12 * x ^ 2 + 6 * x - 1"),
+        ("expand(x * (x^2 - x) / 3 + 1, x)", "1/3 * x ^ 3 - 1/3 * x ^ 2 + 1"),
+        ("expand((x + 1)^2 - x^2 - 2 * x, x)", "1"),
+        ("expand((x + 1)^100, x)", "expand((x + 1) ^ 100, x)"),
+    ]"#,
+    categories = r#"[
+        "polynomials",
+    ]"#
+)]
+fn expand(expression: Expression, variable: Expression) -> Result<Expression, Expression> {
+    let identifier = match &variable {
+        Expression::Variable(identifier) => identifier,
+        _ => return Err(variable),
+    };
+
+    if exceeds_expansion_limit(&expression, identifier) {
+        return Ok(fun(
+            function_expression("expand").unwrap(),
+            [expression, variable],
+        ));
+    }
+
+    Ok(self::expression(
+        polynomial(&expression, identifier)?,
+        &variable,
+    ))
+}
+
+/// Returns the terms of the expression if it is a (possibly nested) sum or difference,
+/// each paired with whether it is subtracted, or the expression itself otherwise.
+fn terms(expression: &Expression, negative: bool) -> Vec<(Expression, bool)> {
+    use crate::expression::Expression::*;
+
+    match expression {
+        Sum(a, b) => {
+            let mut terms = terms(a, negative);
+            terms.append(&mut self::terms(b, negative));
+            terms
+        }
+        Difference(a, b) => {
+            let mut terms = terms(a, negative);
+            terms.append(&mut self::terms(b, !negative));
+            terms
+        }
+        _ => vec![(expression.clone(), negative)],
+    }
+}
+
+/// Returns the term divided by the factor if the factor divides it,
+/// that is, if the factor is one of the term's factors, the base of a power
+/// with an integer exponent of at least 2, or an integer dividing an integer factor.
+fn quotient(term: &Expression, factor: &Expression) -> Option<Expression> {
+    use crate::expression::Expression::*;
+
+    if term == factor {
+        return Some(int(1));
+    }
+
+    match (term, factor) {
+        (Integer(n), Integer(d)) if (n % d).is_zero() => Some(Integer(n / d)),
+        (Negation(a), _) => quotient(a, factor).map(|a| -a),
+        (Power(base, exponent), _) if **base == *factor => match &**exponent {
+            Integer(n) if *n > 2.into() => Some(pow((**base).clone(), Integer(n - 1))),
+            Integer(n) if *n == 2.into() => Some((**base).clone()),
+            _ => None,
+        },
+        (Product(a, b), _) => {
+            if let Some(a) = quotient(a, factor) {
+                Some(if a == int(1) {
+                    (**b).clone()
+                } else {
+                    a * (**b).clone()
+                })
+            } else {
+                quotient(b, factor).map(|b| {
+                    if b == int(1) {
+                        (**a).clone()
+                    } else {
+                        (**a).clone() * b
+                    }
+                })
+            }
+        }
+        _ => None,
+    }
+}
+
+#[function(
+    name = "factor_out",
+    description = "sum with a factor that divides each of its terms factored out, or the sum itself if the factor does not divide all terms",
+    examples = r#"[
+        ("factor_out(2 * a + 2 * b, 2)", "2 * (a + b)"),
+        ("factor_out(a * x - a * y, a)", "a * (x - y)"),
+        ("factor_out(x^3 + x^2 + x, x)", "x * (x ^ 2 + x + 1)"),
+        ("factor_out(6 * a + 4, 2)", "2 * (3 * a + 2)"),
+        ("factor_out(2 * a + b, 2)", "2 * a + b"),
+    ]"#,
+    categories = r#"[
+        "polynomials",
+        "simplification",
+    ]"#
+)]
+fn factor_out(expression: Expression, factor: Expression) -> Result<Expression, Expression> {
+    if matches!(factor.typ(), Type::Number(z, _) if z.is_zero()) {
+        return Err(factor);
+    }
+
+    let mut sum: Option<Expression> = None;
+
+    for (term, negative) in terms(&expression, false) {
+        let term = match quotient(&term, &factor) {
+            Some(term) => term,
+            None => return Ok(expression),
+        };
+
+        sum = Some(match (sum, negative) {
+            (None, false) => term,
+            (None, true) => -term,
+            (Some(sum), false) => sum + term,
+            (Some(sum), true) => sum - term,
+        });
+    }
+
+    Ok(factor * sum.unwrap())
+}
+
+#[function(
+    name = "from_roots",
+    description = "monic polynomial in a variable with the given roots (repeated according to their multiplicity), expanded into a sum of powers of the variable",
+    examples = r#"[
+        ("from_roots([1, 2], x)", "x ^ 2 - 3 * x + 2"),
+        ("from_roots([1, 1, 1], x)", "x ^ 3 - 3 * x ^ 2 + 3 * x - 1"),
+        ("from_roots([i, -i], x)", "x ^ 2 + 1"),
+        ("from_roots([1 + i, 1 - i], x)", "x ^ 2 - 2 * x + 2"),
+        ("from_roots([a, 0], x)", "x ^ 2 - a * x"),
+        ("from_roots([], x)", "1"),
+    ]"#,
+    categories = r#"[
+        "polynomials",
+    ]"#
+)]
+fn from_roots(roots: Vector, variable: Expression) -> Result<Expression, Expression> {
+    let identifier = match &variable {
+        Expression::Variable(identifier) => identifier,
+        _ => return Err(variable),
+    };
+
+    let mut polynomial = vec![int(1)];
+
+    for root in roots.iter() {
+        if !root.free_of(identifier) {
+            return Err(root.clone());
+        }
+
+        // Coefficients are evaluated after each multiplication
+        // to prevent them from growing into huge expressions.
+        polynomial = multiply(&polynomial, &vec![-root.clone(), int(1)])
+            .into_iter()
+            .map(|coefficient| {
+                coefficient
+                    .evaluate(&HashMap::new())
+                    .map_err(|_| root.clone())
+            })
+            .collect::<Result<Polynomial, Expression>>()?;
+    }
+
+    Ok(self::expression(polynomial, &variable))
+}
+
+#[function(
+    name = "is_polynomial",
+    description = "whether an expression is a polynomial in a variable, i.e. a sum of non-negative integer powers of the variable with coefficients that do not contain the variable",
+    examples = r#"[
+        ("is_polynomial(x^2 + 1, x)", "true"),
+        ("is_polynomial((x + 1)^3 / 2, x)", "true"),
+        ("is_polynomial(a * x + b, x)", "true"),
+        ("is_polynomial(5, x)", "true"),
+        ("is_polynomial((x + 1)^3000, x)", "true"),
+        ("is_polynomial(1/x, x)", "false"),
+        ("is_polynomial(x^(1/2), x)", "false"),
+        ("is_polynomial(x^n, x)", "false"),
+        ("is_polynomial(f(x), x)", "false"),
+    ]"#,
+    categories = r#"[
+        "polynomials",
+    ]"#
+)]
+fn is_polynomial(expression: Expression, variable: Expression) -> Result<Expression, Expression> {
+    let identifier = match &variable {
+        Expression::Variable(identifier) => identifier,
+        _ => return Err(variable),
+    };
+
+    Ok(Expression::Boolean(is_polynomial_in(
+        &expression,
+        identifier,
+    )))
+}
+
+#[function(
+    name = "cancel",
+    description = "quotient of polynomials in a variable with numeric coefficients, with their greatest common divisor cancelled and the denominator made monic (note that the result may be defined at points where the original quotient is not, e.g. at `x = 1` for `(x^2 - 1) / (x - 1)`)",
+    examples = r#"[
+        ("cancel((x^2 - 1) / (x - 1), x)", "x + 1"),
+        ("cancel((x^2 + 3 * x + 2) / (x^2 - 1), x)", "(x + 2) / (x - 1)"),
+        ("cancel((2 * x + 2) / (4 * x + 4), x)", "1/2"),
+        ("cancel((x^2 + 1) / (2 * x + 2), x)", "(1/2 * x ^ 2 + 1/2) / (x + 1)"),
+        ("cancel(x^2 / (x^3 - x), x)", "x / (x ^ 2 - 1)"),
+        ("cancel((x^4 - 1) / (x^2 + 1), x)", "x ^ 2 - 1"),
+        ("cancel((x + 1)^2, x)", "x ^ 2 + 2 * x + 1"),
+    ]"#,
+    categories = r#"[
+        "polynomials",
+    ]"#
+)]
+fn cancel(expression: Expression, variable: Expression) -> Result<Expression, Expression> {
+    let identifier = match &variable {
+        Expression::Variable(identifier) => identifier,
+        _ => return Err(variable),
+    };
+
+    let (numerator, denominator) = match &expression {
+        Expression::Quotient(a, b) => ((**a).clone(), (**b).clone()),
+        _ => (expression.clone(), int(1)),
+    };
+
+    let numeric_polynomial = |expression: &Expression| {
+        let polynomial = trim(polynomial(expression, identifier)?);
+
+        if polynomial
+            .iter()
+            .all(|coefficient| matches!(coefficient.typ(), Type::Number(_, _)))
+        {
+            Ok(polynomial)
+        } else {
+            Err(expression.clone())
+        }
+    };
+
+    let numerator_polynomial = numeric_polynomial(&numerator)?;
+    let denominator_polynomial = numeric_polynomial(&denominator)?;
+
+    if denominator_polynomial == [int(0)] {
+        return Err(denominator);
+    }
+
+    let divisor = gcd(&numerator_polynomial, &denominator_polynomial)?;
+
+    let (numerator_polynomial, _) = divide(&numerator_polynomial, &divisor)?;
+    let (denominator_polynomial, _) = divide(&denominator_polynomial, &divisor)?;
+
+    let leading_coefficient = denominator_polynomial.last().unwrap().clone();
+
+    let normalize = |polynomial: Polynomial| {
+        evaluate(
+            polynomial
+                .into_iter()
+                .map(|coefficient| coefficient / leading_coefficient.clone())
+                .collect(),
+        )
+    };
+
+    let numerator = self::expression(normalize(numerator_polynomial)?, &variable);
+    let denominator_polynomial = normalize(denominator_polynomial)?;
+
+    if denominator_polynomial.len() == 1 {
+        Ok(numerator)
+    } else {
+        Ok(numerator / self::expression(denominator_polynomial, &variable))
+    }
+}
+
+#[function(
+    name = "coeffs",
+    description = "vector of the coefficients of a polynomial in a variable, from the constant term up to the highest power of the variable (left unevaluated if that requires expanding a power of a polynomial with an exponent greater than 32)",
+    examples = r#"[
+        ("coeffs(x^2 + 3, x)", "[3, 0, 1]"),
+        ("coeffs((x + 1)^3, x)", "[1, 3, 3, 1]"),
+        ("coeffs(a * x^2 - b * x + x, x)", "[0, -b + 1, a]"),
+        ("coeffs(x * y + y^2, y)", "[0, x, 1]"),
+        ("coeffs(x / 2 - x^2 + x^2, x)", "[0, 1/2]"),
+        ("coeffs(5, x)", "[5]"),
+        ("coeffs((x + 1)^100, x)", "coeffs((x + 1) ^ 100, x)"),
+    ]"#,
+    categories = r#"[
+        "polynomials",
+    ]"#
+)]
+fn coeffs(expression: Expression, variable: Expression) -> Result<Expression, Expression> {
+    let identifier = match &variable {
+        Expression::Variable(identifier) => identifier,
+        _ => return Err(variable),
+    };
+
+    if exceeds_expansion_limit(&expression, identifier) {
+        return Ok(fun(
+            function_expression("coeffs").unwrap(),
+            [expression, variable],
+        ));
+    }
+
+    Ok(Expression::Vector(Vector::from_vec(trim(polynomial(
+        &expression,
+        identifier,
+    )?))))
+}
+
+#[function(
+    name = "poly_eval",
+    description = "value of the polynomial with the given coefficients (from the constant term up to the highest power) at a point, computed using Horner's method",
+    examples = r#"[
+        ("poly_eval([1, 0, 1], 2)", "5"),
+        ("poly_eval([3, -2, 0, 1], 1/2)", "17/8"),
+        ("poly_eval(coeffs((x + 1)^2, x), 3)", "16"),
+        ("poly_eval([1, 2], a)", "2 * a + 1"),
+        ("poly_eval([a, b, c], x)", "(c * x + b) * x + a"),
+        ("poly_eval([], 2)", "0"),
+    ]"#,
+    categories = r#"[
+        "polynomials",
+    ]"#
+)]
+fn poly_eval(coefficients: Vector, x: Expression) -> Expression {
+    coefficients
+        .iter()
+        .rev()
+        .cloned()
+        .reduce(|value, coefficient| value * x.clone() + coefficient)
+        .unwrap_or_else(|| int(0))
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+//! Pseudo-random number generation. Unlike all other built-in functions,
+//! the functions in this module are stateful: Each instance of them shares
+//! a generator whose state advances with every invocation. The state lives
+//! in the function expressions stored in the evaluation context, so reseeding
+//! the context (see [`crate::evaluate::seed_context`]) makes results reproducible.
+
+use std::{cell::Cell, rc::Rc};
+
+use num::{BigUint, ToPrimitive};
+
+use crate::{
+    expression::{Expression, Integer, Matrix},
+    functions::{wrap_proxy, Function, Metadata, Parameter},
+};
+
+/// Seed used by the functions in the default context.
+pub(crate) const DEFAULT_SEED: u64 = 0;
+
+/// SplitMix64 pseudo-random number generator.
+/// This generator is fast and statistically sound,
+/// but must not be used for cryptographic purposes.
+struct Generator(Cell<u64>);
+
+impl Generator {
+    /// Returns the next 64 pseudo-random bits.
+    fn next_u64(&self) -> u64 {
+        let state = self.0.get().wrapping_add(0x9e3779b97f4a7c15);
+        self.0.set(state);
+
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed integer between `min` and `max` (inclusive),
+    /// which must satisfy `min <= max`.
+    fn integer(&self, min: &Integer, max: &Integer) -> Integer {
+        let size = (max - min + 1u8).to_biguint().unwrap();
+        let bits = size.bits();
+
+        // If `bits` is a multiple of 32, one more word than necessary is drawn,
+        // which is then shifted out entirely.
+        let words = bits / 32 + 1;
+
+        // Rejection sampling from the smallest power of two that is at least `size`
+        // avoids the bias that reducing a larger random number modulo `size` would have.
+        // Each attempt succeeds with a probability of at least 1/2.
+        loop {
+            let candidate = BigUint::new((0..words).map(|_| self.next_u64() as u32).collect())
+                >> (words * 32 - bits);
+
+            if candidate < size {
+                return min + Integer::from(candidate);
+            }
+        }
+    }
+}
+
+const RANDOM_METADATA: Metadata = Metadata {
+    name: "random",
+    aliases: &[],
+    description: "pseudo-random integer between `min` and `max` (inclusive)",
+    parameters: &[Parameter::Integer, Parameter::Integer],
+    examples: &[
+        ("random(1, 6)", "4"),
+        ("random(-100, 100)", "23"),
+        ("random(5, 5)", "5"),
+    ],
+    categories: &["random numbers"],
+    holds_bindings: false,
+};
+
+fn random(generator: &Generator, arguments: &[Expression]) -> Result<Expression, Expression> {
+    let min = Integer::try_from(arguments[0].clone())?;
+    let max = Integer::try_from(arguments[1].clone())?;
+
+    if min > max {
+        return Err(arguments[1].clone());
+    }
+
+    Ok(generator.integer(&min, &max).into())
+}
+
+const RANDOM_MATRIX_METADATA: Metadata = Metadata {
+    name: "random_matrix",
+    aliases: &[],
+    description: "matrix with the given numbers of rows and columns whose elements are pseudo-random integers between `min` and `max` (inclusive)",
+    parameters: &[
+        Parameter::NonNegativeInteger,
+        Parameter::NonNegativeInteger,
+        Parameter::Integer,
+        Parameter::Integer,
+    ],
+    examples: &[
+        ("random_matrix(2, 3, 0, 9)", "[[7, 7, 7], [8, 5, 1]]"),
+        ("random_matrix(1, 2, 1, 1)", "[[1, 1]]"),
+    ],
+    categories: &["random numbers", "linear algebra"],
+    holds_bindings: false,
+};
+
+fn random_matrix(
+    generator: &Generator,
+    arguments: &[Expression],
+) -> Result<Expression, Expression> {
+    let rows = Integer::try_from(arguments[0].clone())?
+        .to_usize()
+        .ok_or_else(|| arguments[0].clone())?;
+    let columns = Integer::try_from(arguments[1].clone())?
+        .to_usize()
+        .ok_or_else(|| arguments[1].clone())?;
+    let min = Integer::try_from(arguments[2].clone())?;
+    let max = Integer::try_from(arguments[3].clone())?;
+
+    if min > max {
+        return Err(arguments[3].clone());
+    }
+
+    Ok(Matrix::from_fn(rows, columns, |_, _| generator.integer(&min, &max).into()).into())
+}
+
+/// Returns the pseudo-random number generation functions,
+/// sharing a new generator initialized with the given seed.
+pub(crate) fn functions(seed: u64) -> Vec<Function> {
+    let generator = Rc::new(Generator(Cell::new(seed)));
+    let matrix_generator = generator.clone();
+
+    vec![
+        Function {
+            metadata: RANDOM_METADATA,
+            implementation: wrap_proxy(&RANDOM_METADATA, move |arguments| {
+                random(&generator, arguments).map_err(Into::into)
+            }),
+        },
+        Function {
+            metadata: RANDOM_MATRIX_METADATA,
+            implementation: wrap_proxy(&RANDOM_MATRIX_METADATA, move |arguments| {
+                random_matrix(&matrix_generator, arguments).map_err(Into::into)
+            }),
+        },
+    ]
+}
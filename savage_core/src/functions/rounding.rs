@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::collections::HashMap;
+
+use num::{pow, Signed, ToPrimitive, Zero};
+use savage_macros::function;
+
+use crate::{
+    expression::{Complex, Expression, Integer, Rational, RationalRepresentation},
+    functions::NonNegativeInteger,
+    helpers::*,
+};
+
+#[function(
+    name = "round_to",
+    description = "rational number rounded to the given number of decimal places, with ties rounded away from zero",
+    examples = r#"[
+        ("round_to(1/3, 2)", "0.33"),
+        ("round_to(2/3, 3)", "0.667"),
+        ("round_to(5/2, 0)", "3"),
+        ("round_to(-5/2, 0)", "-3"),
+        ("round_to(1.005, 2)", "1.01"),
+        ("round_to(1.2, 5)", "1.2"),
+    ]"#,
+    categories = r#"[
+        "rounding",
+    ]"#
+)]
+fn round_to(x: Rational, places: NonNegativeInteger) -> Result<Expression, Expression> {
+    let scale = Rational::from_integer(pow(
+        Integer::from(10),
+        places.to_usize().ok_or_else(|| int(places.clone()))?,
+    ));
+
+    // Ratio::round rounds half-way cases away from zero.
+    Ok(Expression::Rational(
+        (x * &scale).round() / scale,
+        RationalRepresentation::Decimal,
+    ))
+}
+
+/// Number of decimal places to which numerical approximations are rounded.
+const APPROXIMATION_PLACES: usize = 15;
+
+/// Symbolic constants that are replaced with their values by numerical approximation,
+/// with more decimal places than approximations are rounded to.
+const CONSTANTS: [(&str, &str); 2] = [
+    ("pi", "3.14159265358979323846264338327950288"),
+    ("e", "2.71828182845904523536028747135266250"),
+];
+
+/// Returns the expression with all rational numbers, as well as all real and imaginary
+/// parts of complex numbers, rounded to the number of decimal places given by `scale`
+/// (which is 10 raised to that number), and represented as decimals.
+fn rounded(expression: &Expression, scale: &Rational) -> Expression {
+    let round = |x: &Rational| (x * scale).round() / scale;
+
+    match expression {
+        Expression::Rational(x, _) => {
+            Expression::Rational(round(x), RationalRepresentation::Decimal)
+        }
+        Expression::Complex(z, _) => Expression::Complex(
+            Complex::new(round(&z.re), round(&z.im)),
+            RationalRepresentation::Decimal,
+        ),
+        _ => expression.map_children(&|child| rounded(child, scale)),
+    }
+}
+
+#[function(
+    name = "approx",
+    description = "numerical approximation of an expression, with the symbolic constants `pi` and `e` replaced with their values, and all numbers rounded to 15 decimal places",
+    examples = r#"[
+        ("approx(pi)", "3.141592653589793"),
+        ("approx(e)", "2.718281828459045"),
+        ("approx(pi / 3)", "1.047197551196598"),
+        ("approx(e^2)", "7.38905609893065"),
+        ("approx(1/3)", "0.333333333333333"),
+        ("approx(i * pi)", "3.141592653589793*i"),
+        ("approx(x + 2 * pi)", "x + 6.283185307179586"),
+    ]"#,
+    categories = r#"[
+        "rounding",
+    ]"#
+)]
+fn approx(x: Expression) -> Result<Expression, Expression> {
+    let mut approximation = x.clone();
+
+    for (identifier, value) in CONSTANTS {
+        approximation = approximation.substitute(identifier, &value.parse().unwrap());
+    }
+
+    // Functions have already been resolved when the argument was evaluated,
+    // so the approximation can be evaluated without a context.
+    let approximation = approximation
+        .evaluate(&HashMap::new())
+        .map_err(|_| x.clone())?;
+
+    let scale = Rational::from_integer(pow(Integer::from(10), APPROXIMATION_PLACES));
+
+    Ok(rounded(&approximation, &scale))
+}
+
+/// Returns the expression with all numbers whose absolute value is less than `tolerance`,
+/// as well as all real and imaginary parts of complex numbers with that property,
+/// replaced with zero.
+fn chopped(expression: &Expression, tolerance: &Rational) -> Expression {
+    let chop = |x: &Rational| {
+        if x.abs() < *tolerance {
+            Rational::zero()
+        } else {
+            x.clone()
+        }
+    };
+
+    match expression {
+        Expression::Integer(n) if chop(&Rational::from_integer(n.clone())).is_zero() => int(0),
+        Expression::Rational(x, _) if chop(x).is_zero() => int(0),
+        Expression::Complex(z, representation) => {
+            Expression::Complex(Complex::new(chop(&z.re), chop(&z.im)), *representation)
+        }
+        _ => expression.map_children(&|child| chopped(child, tolerance)),
+    }
+}
+
+#[function(
+    name = "chop",
+    description = "expression with all numbers whose absolute value is less than a non-negative tolerance replaced with zero, including the real and imaginary parts of complex numbers, and numbers in vectors, matrices, and sub-expressions",
+    examples = r#"[
+        ("chop(1/1000000, 1/1000)", "0"),
+        ("chop(1/100, 1/1000)", "1/100"),
+        ("chop([0.0000001, -0.0000001, 2], 0.001)", "[0, 0, 2]"),
+        ("chop(1 + 0.000000001 * i, 0.000001)", "1"),
+        ("chop(x + 0.0000001 * y, 0.001)", "x"),
+    ]"#,
+    categories = r#"[
+        "rounding",
+    ]"#
+)]
+fn chop(x: Expression, tolerance: Rational) -> Result<Expression, Expression> {
+    if tolerance.is_negative() {
+        return Err(tolerance.into());
+    }
+
+    Ok(chopped(&x, &tolerance))
+}
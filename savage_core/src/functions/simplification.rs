@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use savage_macros::function;
+
+use crate::expression::Expression;
+
+#[function(
+    name = "simplify",
+    description = "expression simplified using basic algebraic identities (such as `x + 0 = x`, `x * 1 = x`, `x * 0 = 0`, `x ^ 1 = x`, `x ^ 0 = 1`, and `x - x = 0`), with arithmetic on numbers carried out",
+    examples = r#"[
+        ("simplify(x + 0)", "x"),
+        ("simplify((x + 0) * (1 * y))", "x * y"),
+        ("simplify(x^1 - x)", "0"),
+        ("simplify(x * (1/2 + 1/3))", "x * 5/6"),
+        ("simplify(x + y)", "x + y"),
+    ]"#,
+    categories = r#"[
+        "simplification",
+    ]"#
+)]
+fn simplify(expression: Expression) -> Result<Expression, Expression> {
+    Ok(expression.simplify())
+}
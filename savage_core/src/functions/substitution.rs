@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::collections::HashMap;
+
+use savage_macros::function;
+
+use crate::expression::{Expression, Vector};
+
+#[function(
+    name = "partial",
+    description = "expression with the variables bound by equations of the form `x == value` replaced with their values (all at once, so that bindings do not apply to each other) and then evaluated, leaving all other variables symbolic",
+    examples = r#"[
+        ("partial(a * x + b, [a == 2, b == 1])", "2 * x + 1"),
+        ("partial(x^2 + y, [x == 3])", "9 + y"),
+        ("partial(x * y, [x == y + 1])", "(y + 1) * y"),
+        ("partial(x + y, [x == y, y == x])", "y + x"),
+        ("partial(x, [x == x + 1])", "x + 1"),
+        ("partial(a + b, [])", "a + b"),
+    ]"#,
+    categories = r#"[
+        "substitution",
+    ]"#
+)]
+fn partial(expression: Expression, bindings: Vector) -> Result<Expression, Expression> {
+    // Substituting all bound variables at once means that the values
+    // are never substituted into, so bindings cannot refer to each other
+    // or to themselves. The result is then evaluated like any other.
+    Ok(expression.substitute_all(&values(&bindings)?))
+}
+
+/// Returns the values of the variables bound by equations of the form `x == value`,
+/// or the first binding that is not such an equation or binds a variable again.
+fn values(bindings: &Vector) -> Result<HashMap<String, Expression>, Expression> {
+    let mut values = HashMap::new();
+
+    for binding in bindings.iter() {
+        match binding {
+            Expression::Equal(a, b) => match &**a {
+                Expression::Variable(identifier) if !values.contains_key(identifier) => {
+                    values.insert(identifier.clone(), (**b).clone());
+                }
+                _ => return Err(binding.clone()),
+            },
+            _ => return Err(binding.clone()),
+        }
+    }
+
+    Ok(values)
+}
+
+#[function(
+    name = "eval_with",
+    description = "value of an expression with the variables bound by equations of the form `x == value` taking the given values, while all other variables keep their values from the context (e.g. after entering `a = 5` and `b = 1`, `eval_with(a + b, [a == 1])` is `2`, and `a` is still `5`)",
+    examples = r#"[
+        ("eval_with(a + b, [a == 1, b == 2])", "3"),
+        ("eval_with(x^2 + y, [x == 3])", "9 + y"),
+        ("eval_with(diff(x^2, x), [x == 3])", "6"),
+        ("eval_with(x + y, [x == y, y == x])", "y + x"),
+    ]"#,
+    categories = r#"[
+        "substitution",
+    ]"#,
+    holds_bindings = true
+)]
+fn eval_with(expression: Expression, bindings: Vector) -> Result<Expression, Expression> {
+    // The arguments have been evaluated with the bound variables held
+    // (see `Function::holds_bindings`), so the bound variables have not been replaced
+    // with their values from the context, and can be substituted here.
+    Ok(expression.substitute_all(&values(&bindings)?))
+}
+
+#[function(
+    name = "subs",
+    description = "expression with all occurrences of a variable replaced with a value, which may itself be an expression (the bodies of user-defined functions are left untouched, e.g. after entering `f(x) = x + 1`, `subs(f(x), x, 2)` is `3`)",
+    examples = r#"[
+        ("subs(x^2 + x, x, 3)", "12"),
+        ("subs(x * y, x, y + 1)", "(y + 1) * y"),
+        ("subs(a + b, c, 1)", "a + b"),
+        ("subs(f(x), f, abs)", "abs(x)"),
+        ("subs(f(x), x, -2)", "f(-2)"),
+    ]"#,
+    categories = r#"[
+        "substitution",
+    ]"#
+)]
+fn subs(
+    expression: Expression,
+    variable: Expression,
+    value: Expression,
+) -> Result<Expression, Expression> {
+    match &variable {
+        Expression::Variable(identifier) => Ok(expression.substitute(identifier, &value)),
+        _ => Err(variable),
+    }
+}
@@ -0,0 +1,388 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use num::Zero;
+use savage_macros::function;
+
+use crate::{
+    expression::{Expression, Integer, Rational, Type},
+    functions::{function_expression, polynomials::polynomial},
+    helpers::*,
+};
+
+/// Returns the argument of the expression if it is the value of the function
+/// with the given name at a single argument, or `None` otherwise.
+/// Both built-in functions and undefined function symbols are recognized.
+fn argument<'a>(expression: &'a Expression, name: &str) -> Option<&'a Expression> {
+    use crate::expression::Expression::*;
+
+    if let FunctionValue(function, arguments) = expression {
+        if let Variable(identifier) | Function(identifier, _) = &**function {
+            if identifier == name && arguments.len() == 1 {
+                return Some(&arguments[0]);
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the argument of the expression if it is the square of the value
+/// of the function with the given name at a single argument, or `None` otherwise.
+fn squared_argument<'a>(expression: &'a Expression, name: &str) -> Option<&'a Expression> {
+    if let Expression::Power(a, b) = expression {
+        if **b == int(2) {
+            return argument(a, name);
+        }
+    }
+
+    None
+}
+
+/// Returns the factors of the expression if it is a (possibly nested) product,
+/// or the expression itself otherwise.
+fn factors(expression: &Expression) -> Vec<&Expression> {
+    if let Expression::Product(a, b) = expression {
+        let mut factors = factors(a);
+        factors.append(&mut self::factors(b));
+        factors
+    } else {
+        vec![expression]
+    }
+}
+
+/// Returns the arguments of the expression if it is the product of the values
+/// of the functions with the given names at single arguments (in either order),
+/// or `None` otherwise.
+fn product_arguments<'a>(
+    expression: &'a Expression,
+    name_1: &str,
+    name_2: &str,
+) -> Option<(&'a Expression, &'a Expression)> {
+    if let [a, b] = factors(expression)[..] {
+        for (a, b) in [(a, b), (b, a)] {
+            if let (Some(x), Some(y)) = (argument(a, name_1), argument(b, name_2)) {
+                return Some((x, y));
+            }
+        }
+    }
+
+    None
+}
+
+/// Applies trigonometric identities to the expression (but not to its
+/// sub-expressions), and returns the result.
+fn apply_identities(expression: Expression) -> Expression {
+    use crate::expression::Expression::*;
+
+    match &expression {
+        Sum(a, b) => {
+            // sin(x)^2 + cos(x)^2 = 1
+            for (a, b) in [(a, b), (b, a)] {
+                if let (Some(x), Some(y)) = (squared_argument(a, "sin"), squared_argument(b, "cos"))
+                {
+                    if x == y {
+                        return int(1);
+                    }
+                }
+            }
+
+            // sin(x) * cos(y) + cos(x) * sin(y) = sin(x + y)
+            if let (Some((x_1, y_1)), Some((y_2, x_2))) = (
+                product_arguments(a, "sin", "cos"),
+                product_arguments(b, "sin", "cos"),
+            ) {
+                if x_1 == x_2 && y_1 == y_2 {
+                    return fun(var("sin"), [x_1.clone() + y_1.clone()]);
+                }
+            }
+
+            expression
+        }
+        Difference(a, b) => {
+            // cos(x)^2 - sin(x)^2 = cos(2 * x)
+            if let (Some(x), Some(y)) = (squared_argument(a, "cos"), squared_argument(b, "sin")) {
+                if x == y {
+                    return fun(var("cos"), [int(2) * x.clone()]);
+                }
+            }
+
+            // 1 - sin(x)^2 = cos(x)^2 and 1 - cos(x)^2 = sin(x)^2
+            if **a == int(1) {
+                for (name, complement) in [("sin", "cos"), ("cos", "sin")] {
+                    if let Some(x) = squared_argument(b, name) {
+                        return pow(fun(var(complement), [x.clone()]), int(2));
+                    }
+                }
+            }
+
+            // cos(x) * cos(y) - sin(x) * sin(y) = cos(x + y)
+            if let (Some((x_1, y_1)), Some((x_2, y_2))) = (
+                product_arguments(a, "cos", "cos"),
+                product_arguments(b, "sin", "sin"),
+            ) {
+                if (x_1 == x_2 && y_1 == y_2) || (x_1 == y_2 && y_1 == x_2) {
+                    return fun(var("cos"), [x_1.clone() + y_1.clone()]);
+                }
+            }
+
+            expression
+        }
+        Product(_, _) => {
+            // 2 * sin(x) * cos(x) = sin(2 * x)
+            let factors = factors(&expression);
+
+            if factors.len() == 3 {
+                if let Some(i) = factors.iter().position(|&factor| *factor == int(2)) {
+                    let rest = factors
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(_, &factor)| factor.clone())
+                        .reduce(|a, b| a * b)
+                        .unwrap();
+
+                    if let Some((x, y)) = product_arguments(&rest, "sin", "cos") {
+                        if x == y {
+                            return fun(var("sin"), [int(2) * x.clone()]);
+                        }
+                    }
+                }
+            }
+
+            expression
+        }
+        _ => expression,
+    }
+}
+
+/// Applies trigonometric identities to the expression and all of its
+/// sub-expressions, and returns the result.
+fn rewrite(expression: &Expression) -> Expression {
+    apply_identities(expression.map_children(&rewrite))
+}
+
+/// Returns `q` if the expression is `q * pi` for a rational number `q`,
+/// or `None` otherwise.
+fn pi_multiple(x: &Expression) -> Option<Rational> {
+    if let Ok(q) = Rational::try_from(x.clone()) {
+        return q.is_zero().then(Rational::zero);
+    }
+
+    match polynomial(x, "pi").as_deref() {
+        Ok([constant, coefficient]) if *constant == int(0) => {
+            Rational::try_from(coefficient.clone()).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Returns `q` reduced modulo `period`, that is, the number in the interval
+/// `[0, period)` that differs from `q` by an integer multiple of `period`.
+fn reduce(q: &Rational, period: &Rational) -> Rational {
+    q - (q / period).floor() * period
+}
+
+/// Returns the value of `sin(q * pi)` if it is a rational number, or `None` otherwise.
+fn sin_pi_multiple(q: &Rational) -> Option<Rational> {
+    let one = Rational::from_integer(Integer::from(1));
+    let half = Rational::new(Integer::from(1), Integer::from(2));
+
+    let q = reduce(q, &(&one + &one));
+
+    // sin((q + 1) * pi) = -sin(q * pi)
+    let (q, sign) = if q >= one {
+        (q - &one, -&one)
+    } else {
+        (q, one.clone())
+    };
+
+    // sin((1 - q) * pi) = sin(q * pi)
+    let q = if q > half { &one - q } else { q };
+
+    let value = if q.is_zero() {
+        Rational::zero()
+    } else if q == Rational::new(Integer::from(1), Integer::from(6)) {
+        half
+    } else if q == half {
+        one
+    } else {
+        return None;
+    };
+
+    Some(value * sign)
+}
+
+/// Returns the value of `tan(q * pi)` if it is a rational number, `None` if it is not,
+/// or an error if the tangent is undefined.
+fn tan_pi_multiple(q: &Rational) -> Result<Option<Rational>, ()> {
+    let one = Rational::from_integer(Integer::from(1));
+
+    let q = reduce(q, &one);
+
+    if q.is_zero() {
+        Ok(Some(Rational::zero()))
+    } else if q == Rational::new(Integer::from(1), Integer::from(4)) {
+        Ok(Some(one))
+    } else if q == Rational::new(Integer::from(3), Integer::from(4)) {
+        Ok(Some(-one))
+    } else if q == Rational::new(Integer::from(1), Integer::from(2)) {
+        Err(())
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns the value of the trigonometric function with the given name at `x`
+/// if `value` returns it for the rational multiple of `pi` that `x` is,
+/// or the function value in unevaluated form otherwise.
+fn trigonometric_value(
+    name: &str,
+    x: Expression,
+    value: impl Fn(&Rational) -> Result<Option<Rational>, ()>,
+) -> Result<Expression, Expression> {
+    match x.typ() {
+        Type::Number(_, _) | Type::Arithmetic | Type::Unknown => (),
+        _ => return Err(x),
+    }
+
+    match pi_multiple(&x).map(|q| value(&q)) {
+        Some(Ok(Some(value))) => Ok(value.into()),
+        Some(Err(())) => Err(x),
+        _ => Ok(fun(function_expression(name).unwrap(), [x])),
+    }
+}
+
+#[function(
+    name = "sin",
+    description = "sine of an angle in radians, which is exact if the angle is a multiple of the symbol `pi` whose sine is a rational number, and remains unevaluated otherwise",
+    examples = r#"[
+        ("sin(pi / 6)", "1/2"),
+        ("sin(pi / 2)", "1"),
+        ("sin(7/6 * pi)", "-1/2"),
+        ("sin(-pi)", "0"),
+        ("sin(0)", "0"),
+        ("sin(x)", "sin(x)"),
+        ("sin(pi / 4)", "sin(pi / 4)"),
+    ]"#,
+    categories = r#"[
+        "trigonometry",
+    ]"#
+)]
+fn sin(x: Expression) -> Result<Expression, Expression> {
+    trigonometric_value("sin", x, |q| Ok(sin_pi_multiple(q)))
+}
+
+#[function(
+    name = "cos",
+    description = "cosine of an angle in radians, which is exact if the angle is a multiple of the symbol `pi` whose cosine is a rational number, and remains unevaluated otherwise",
+    examples = r#"[
+        ("cos(pi / 3)", "1/2"),
+        ("cos(pi)", "-1"),
+        ("cos(pi / 2)", "0"),
+        ("cos(-2/3 * pi)", "-1/2"),
+        ("cos(0)", "1"),
+        ("cos(x)", "cos(x)"),
+    ]"#,
+    categories = r#"[
+        "trigonometry",
+    ]"#
+)]
+fn cos(x: Expression) -> Result<Expression, Expression> {
+    // cos(x) = sin(x + pi/2)
+    trigonometric_value("cos", x, |q| {
+        Ok(sin_pi_multiple(
+            &(q + Rational::new(Integer::from(1), Integer::from(2))),
+        ))
+    })
+}
+
+#[function(
+    name = "tan",
+    description = "tangent of an angle in radians, which is exact if the angle is a multiple of the symbol `pi` whose tangent is a rational number, and remains unevaluated otherwise",
+    examples = r#"[
+        ("tan(pi / 4)", "1"),
+        ("tan(3/4 * pi)", "-1"),
+        ("tan(-5/4 * pi)", "-1"),
+        ("tan(pi)", "0"),
+        ("tan(x)", "tan(x)"),
+    ]"#,
+    categories = r#"[
+        "trigonometry",
+    ]"#
+)]
+fn tan(x: Expression) -> Result<Expression, Expression> {
+    trigonometric_value("tan", x, tan_pi_multiple)
+}
+
+#[function(
+    name = "simplify_trig",
+    description = "expression simplified using the Pythagorean, double-angle, and angle sum identities",
+    examples = r#"[
+        ("simplify_trig(sin(x)^2 + cos(x)^2)", "1"),
+        ("simplify_trig(y * (cos(x)^2 + sin(x)^2))", "y"),
+        ("simplify_trig(1 - sin(x)^2)", "cos(x) ^ 2"),
+        ("simplify_trig(2 * sin(x) * cos(x))", "sin(2 * x)"),
+        ("simplify_trig(cos(x)^2 - sin(x)^2)", "cos(2 * x)"),
+        ("simplify_trig(sin(a) * cos(b) + cos(a) * sin(b))", "sin(a + b)"),
+        ("simplify_trig(cos(a) * cos(b) - sin(a) * sin(b))", "cos(a + b)"),
+        ("simplify_trig(sin(2 * x) + x ^ 2)", "sin(2 * x) + x ^ 2"),
+    ]"#,
+    categories = r#"[
+        "trigonometry",
+        "simplification",
+    ]"#
+)]
+fn simplify_trig(expression: Expression) -> Expression {
+    let mut old_expression = expression;
+
+    // Rewriting until a fixed point is reached makes the function idempotent.
+    loop {
+        let new_expression = rewrite(&old_expression);
+
+        if new_expression == old_expression {
+            return new_expression;
+        }
+
+        old_expression = new_expression;
+    }
+}
+
+#[function(
+    name = "deg2rad",
+    description = "angle in degrees converted to radians, as a multiple of the symbol `pi`",
+    examples = r#"[
+        ("deg2rad(180)", "pi"),
+        ("deg2rad(90)", "1/2 * pi"),
+        ("deg2rad(-45)", "-1/4 * pi"),
+        ("deg2rad(1)", "1/180 * pi"),
+        ("deg2rad(a)", "a / 180 * pi"),
+    ]"#,
+    categories = r#"[
+        "trigonometry",
+    ]"#
+)]
+fn deg2rad(x: Expression) -> Expression {
+    x / int(180) * var("pi")
+}
+
+#[function(
+    name = "rad2deg",
+    description = "angle in radians converted to degrees, which is exact if the angle is a multiple of the symbol `pi`",
+    examples = r#"[
+        ("rad2deg(pi)", "180"),
+        ("rad2deg(pi / 3)", "60"),
+        ("rad2deg(-3/4 * pi)", "-135"),
+        ("rad2deg(deg2rad(30))", "30"),
+        ("rad2deg(1)", "180 / pi"),
+    ]"#,
+    categories = r#"[
+        "trigonometry",
+    ]"#
+)]
+fn rad2deg(x: Expression) -> Expression {
+    match polynomial(&x, "pi").as_deref() {
+        Ok([constant, coefficient]) if *constant == int(0) => coefficient.clone() * int(180),
+        _ => x * int(180) / var("pi"),
+    }
+}
@@ -0,0 +1,359 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use std::collections::HashMap;
+
+use num::{Signed, Zero};
+
+use savage_macros::function;
+
+use crate::{
+    expression::{Expression, Matrix, Rational, Type, Vector},
+    helpers::*,
+};
+
+/// Returns whether the expression can be used as a function in higher-order functions.
+/// Undefined function symbols are accepted as well, in which case the function values
+/// remain symbolic.
+fn is_callable(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Function(_, _) | Expression::Variable(_)
+    )
+}
+
+#[function(
+    name = "zip",
+    description = "vector of pairs (as vectors) of corresponding elements of two vectors with the same length",
+    examples = r#"[
+        ("zip([1, 2, 3], [a, b, c])", "[[1, a], [2, b], [3, c]]"),
+        ("zip([x], [y])", "[[x, y]]"),
+        ("zip([], [])", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn zip(u: Vector, v: Vector) -> Result<Vector, Expression> {
+    if u.len() != v.len() {
+        return Err(v.into());
+    }
+
+    Ok(Vector::from_iterator(
+        u.len(),
+        u.iter()
+            .zip(v.iter())
+            .map(|(a, b)| Vector::from_vec(vec![a.clone(), b.clone()]).into()),
+    ))
+}
+
+#[function(
+    name = "zip_with",
+    description = "vector of the values of a function with two arguments at corresponding elements of two vectors with the same length",
+    examples = r#"[
+        ("zip_with(div, [7, 9, -7], [2, 4, 2])", "[3, 2, -4]"),
+        ("zip_with(bitand, [12, 10], [10, 6])", "[8, 2]"),
+        ("zip_with(f, [1, 2], [a, b])", "[f(1, a), f(2, b)]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn zip_with(function: Expression, u: Vector, v: Vector) -> Result<Vector, Expression> {
+    if !is_callable(&function) {
+        return Err(function);
+    }
+
+    if u.len() != v.len() {
+        return Err(v.into());
+    }
+
+    Ok(Vector::from_iterator(
+        u.len(),
+        u.iter()
+            .zip(v.iter())
+            .map(|(a, b)| fun(function.clone(), [a.clone(), b.clone()])),
+    ))
+}
+
+#[function(
+    name = "reduce",
+    description = "result of repeatedly applying a function with two arguments to an accumulated value (starting with the given initial value) and the elements of a vector, from left to right (user-defined functions work as well, e.g. after entering `f(a, b) = a + b`, `reduce(f, [1, 2, 3], 0)` is `6`)",
+    examples = r#"[
+        ("reduce(div, [2, 3], 100)", "16"),
+        ("reduce(bitor, [1, 2, 8], 0)", "11"),
+        ("reduce(f, [a, b], x)", "f(f(x, a), b)"),
+        ("reduce(f, [], x)", "x"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn reduce(
+    function: Expression,
+    vector: Vector,
+    initial: Expression,
+) -> Result<Expression, Expression> {
+    if !is_callable(&function) {
+        return Err(function);
+    }
+
+    Ok(vector.iter().fold(initial, |accumulator, element| {
+        fun(function.clone(), [accumulator, element.clone()])
+    }))
+}
+
+#[function(
+    name = "filter",
+    description = "vector of the elements of a vector for which a function with one argument is `true` (elements for which the value of the function cannot be determined are kept)",
+    examples = r#"[
+        ("filter(is_prime, [2, 3, 4, 5])", "[2, 3, 5]"),
+        ("filter(is_prime, [4, a, 6])", "[a]"),
+        ("filter(p, [1, 2])", "[1, 2]"),
+        ("filter(is_prime, [])", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn filter(predicate: Expression, vector: Vector) -> Result<Vector, Expression> {
+    if !is_callable(&predicate) {
+        return Err(predicate);
+    }
+
+    let mut elements = Vec::new();
+
+    for element in vector.iter() {
+        // Functions have already been resolved when the arguments were evaluated,
+        // so the value can be evaluated without a context.
+        let value = fun(predicate.clone(), [element.clone()])
+            .evaluate(&HashMap::new())
+            .map_err(|_| element.clone())?;
+
+        match value.typ() {
+            Type::Boolean(Some(false)) => (),
+            Type::Boolean(_) | Type::Unknown => elements.push(element.clone()),
+            _ => return Err(predicate),
+        }
+    }
+
+    Ok(Vector::from_vec(elements))
+}
+
+#[function(
+    name = "total",
+    description = "sum of the elements of a vector",
+    examples = r#"[
+        ("total([1, 2, 3])", "6"),
+        ("total([1/2, 0.25, i])", "0.75 + i"),
+        ("total([a, b, 1])", "a + b + 1"),
+        ("total([])", "0"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn total(vector: Vector) -> Expression {
+    vector
+        .iter()
+        .cloned()
+        .reduce(|sum, element| sum + element)
+        .unwrap_or_else(|| int(0))
+}
+
+#[function(
+    name = "mean",
+    description = "arithmetic mean of the elements of a non-empty vector",
+    examples = r#"[
+        ("mean([1, 2, 3])", "2"),
+        ("mean([1, 2])", "3/2"),
+        ("mean([0.5, 1])", "0.75"),
+        ("mean([a, b])", "(a + b) / 2"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn mean(vector: Vector) -> Result<Expression, Expression> {
+    if vector.is_empty() {
+        return Err(vector.into());
+    }
+
+    let length = vector.len();
+
+    Ok(total(vector) / int(length))
+}
+
+#[function(
+    name = "tabulate",
+    description = "matrix whose rows contain the numbers from `start` to `stop` (inclusive) in increments of `step`, and the values of a function with one argument at those numbers",
+    examples = r#"[
+        ("tabulate(abs, -1, 1, 1)", "[[-1, 1], [0, 0], [1, 1]]"),
+        ("tabulate(factorial, 3, 1, -1)", "[[3, 6], [2, 2], [1, 1]]"),
+        ("tabulate(f, 0, 1, 1/2)", "[[0, f(0)], [1/2, f(1/2)], [1, f(1)]]"),
+        ("tabulate(g, 0, 1, 0.4)", "[[0, g(0)], [0.4, g(0.4)], [0.8, g(0.8)]]"),
+        ("tabulate(abs, 1, 0, 1)", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn tabulate(
+    function: Expression,
+    start: Rational,
+    stop: Rational,
+    step: Expression,
+) -> Result<Expression, Expression> {
+    if !is_callable(&function) {
+        return Err(function);
+    }
+
+    // The step is taken as an expression to preserve its representation,
+    // which the numbers in the table inherit.
+    let (step_value, representation) = match step.typ() {
+        Type::Number(z, representation) if z.im.is_zero() && !z.re.is_zero() => {
+            (z.re, representation)
+        }
+        _ => return Err(step),
+    };
+
+    let mut rows = Vec::new();
+    let mut x = start;
+
+    while (step_value.is_positive() && x <= stop) || (step_value.is_negative() && x >= stop) {
+        let x_expression = Expression::Rational(x.clone(), representation);
+
+        rows.push(nalgebra::RowDVector::from_vec(vec![
+            x_expression.clone(),
+            fun(function.clone(), [x_expression]),
+        ]));
+
+        x += &step_value;
+    }
+
+    if rows.is_empty() {
+        // An empty table is the empty vector, which is also what `[]` denotes.
+        Ok(Vector::from_vec(Vec::new()).into())
+    } else {
+        Ok(Matrix::from_rows(&rows).into())
+    }
+}
+
+#[function(
+    name = "counts",
+    description = "matrix whose rows contain the distinct elements of a vector, in the order of their first occurrence, and the number of times each of them occurs (elements are distinct if they are not structurally identical)",
+    examples = r#"[
+        ("counts([1, 1, 2, 3, 3, 3])", "[[1, 2], [2, 1], [3, 3]]"),
+        ("counts([b, a, b, a + 1])", "[[b, 2], [a, 1], [a + 1, 1]]"),
+        ("counts([2/4, 1/2, 0.5])", "[[1/2, 2], [0.5, 1]]"),
+        ("counts([])", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn counts(vector: Vector) -> Expression {
+    let mut counts: Vec<(Expression, usize)> = Vec::new();
+
+    for element in vector.iter() {
+        match counts.iter_mut().find(|(value, _)| value == element) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((element.clone(), 1)),
+        }
+    }
+
+    if counts.is_empty() {
+        // An empty table is the empty vector, which is also what `[]` denotes.
+        return Vector::from_vec(Vec::new()).into();
+    }
+
+    Matrix::from_rows(
+        &counts
+            .into_iter()
+            .map(|(value, count)| nalgebra::RowDVector::from_vec(vec![value, int(count)]))
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
+/// Returns the distinct elements, in the order of their first occurrence
+/// (elements are distinct if they are not structurally identical).
+fn distinct<'a>(elements: impl IntoIterator<Item = &'a Expression>) -> Expression {
+    let mut distinct_elements: Vec<Expression> = Vec::new();
+
+    for element in elements {
+        if !distinct_elements.contains(element) {
+            distinct_elements.push(element.clone());
+        }
+    }
+
+    Vector::from_vec(distinct_elements).into()
+}
+
+#[function(
+    name = "unique",
+    aliases = r#"[
+        "deduplicate",
+    ]"#,
+    description = "vector containing the distinct elements of a vector, in the order of their first occurrence (elements are distinct if they are not structurally identical)",
+    examples = r#"[
+        ("unique([1, 2, 2, 3])", "[1, 2, 3]"),
+        ("unique([b, a, b, a + 1])", "[b, a, a + 1]"),
+        ("deduplicate([2/4, 1/2, 0.5])", "[1/2, 0.5]"),
+        ("unique([])", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn unique(vector: Vector) -> Expression {
+    distinct(vector.iter())
+}
+
+#[function(
+    name = "union",
+    description = "vector containing the distinct elements that occur in either of two vectors treated as sets, in the order of their first occurrence",
+    examples = r#"[
+        ("union([1, 2, 3], [2, 3, 4])", "[1, 2, 3, 4]"),
+        ("union([a, a], [b, a])", "[a, b]"),
+        ("union([], [])", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn union(a: Vector, b: Vector) -> Expression {
+    distinct(a.iter().chain(b.iter()))
+}
+
+#[function(
+    name = "intersection",
+    description = "vector containing the distinct elements of the first of two vectors treated as sets that also occur in the second, in the order of their first occurrence",
+    examples = r#"[
+        ("intersection([1, 2, 3], [2, 3, 4])", "[2, 3]"),
+        ("intersection([b, a, b], [a, b])", "[b, a]"),
+        ("intersection([1, 2], [3])", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn intersection(a: Vector, b: Vector) -> Expression {
+    distinct(a.iter().filter(|element| b.iter().any(|e| e == *element)))
+}
+
+#[function(
+    name = "difference",
+    description = "vector containing the distinct elements of the first of two vectors treated as sets that do not occur in the second, in the order of their first occurrence",
+    examples = r#"[
+        ("difference([1, 2, 3], [2, 3, 4])", "[1]"),
+        ("difference([b, a, b, c], [a])", "[b, c]"),
+        ("difference([1, 2], [1, 2])", "[]"),
+    ]"#,
+    categories = r#"[
+        "vectors",
+    ]"#
+)]
+fn difference(a: Vector, b: Vector) -> Expression {
+    distinct(a.iter().filter(|element| !b.iter().any(|e| e == *element)))
+}
@@ -2,12 +2,20 @@
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
 //! Operators, conversions, and helper functions to make working with expressions easier.
+//!
+//! The logical operators `&&` and `||` cannot be overloaded in Rust, so conjunctions
+//! and disjunctions are built with `&` and `|` instead (e.g. `var("A") & var("B")`
+//! is the same as `and(var("A"), var("B"))`). Like `&&` and `||` in expressions,
+//! `&` has a higher precedence than `|`. Comparison operators cannot be overloaded
+//! to build expressions, so the functions [`eq`], [`ne`], [`lt`], [`le`], [`gt`],
+//! and [`ge`] must be used for comparisons.
 
 use std::ops::{
-    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Not, Rem, RemAssign, Sub, SubAssign,
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, Div, DivAssign, Mul, MulAssign, Neg,
+    Not, Rem, RemAssign, Sub, SubAssign,
 };
 
-use num::{One, Zero};
+use num::{Integer as _, One, ToPrimitive, Zero};
 
 use crate::expression::{
     Complex, Expression, Integer, Matrix, Rational, RationalRepresentation, Type, Vector,
@@ -99,6 +107,34 @@ impl RemAssign for Expression {
     }
 }
 
+impl BitAnd for Expression {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Expression::And(Box::new(self), Box::new(other))
+    }
+}
+
+impl BitAndAssign for Expression {
+    fn bitand_assign(&mut self, other: Self) {
+        *self = self.clone() & other;
+    }
+}
+
+impl BitOr for Expression {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Expression::Or(Box::new(self), Box::new(other))
+    }
+}
+
+impl BitOrAssign for Expression {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = self.clone() | other;
+    }
+}
+
 impl From<&Self> for Expression {
     fn from(expression: &Self) -> Self {
         expression.clone()
@@ -260,6 +296,27 @@ pub fn ratd(numerator: impl Into<Integer>, denominator: impl Into<Integer>) -> E
     )
 }
 
+/// Returns an expression representing the rational number with
+/// the given numerator and denominator, using unreduced fraction
+/// representation (i.e., the fraction is printed exactly as given,
+/// although its value is reduced to lowest terms). If the common factor
+/// of the numerator and denominator is too large to be stored,
+/// fraction representation is used instead.
+pub fn ratu(numerator: impl Into<Integer>, denominator: impl Into<Integer>) -> Expression {
+    let numerator = numerator.into();
+    let denominator = denominator.into();
+    let factor = numerator.gcd(&denominator);
+
+    Expression::Rational(
+        Rational::new(numerator, denominator),
+        factor
+            .to_u32()
+            .map_or(RationalRepresentation::Fraction, |factor| {
+                RationalRepresentation::Unreduced(factor)
+            }),
+    )
+}
+
 /// Returns an expression representing the complex number with
 /// real and imaginary parts being rational numbers described by
 /// the given numerators and denominators, using fraction representation.
@@ -342,3 +399,80 @@ pub fn and(a: impl Into<Expression>, b: impl Into<Expression>) -> Expression {
 pub fn or(a: impl Into<Expression>, b: impl Into<Expression>) -> Expression {
     Expression::Or(Box::new(a.into()), Box::new(b.into()))
 }
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{dmatrix, dvector};
+
+    use crate::expression::{Complex, Expression, Integer, Matrix, Rational, Vector};
+    use crate::helpers::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Integer::try_from(int(-3)), Ok(Integer::from(-3)));
+        assert_eq!(Integer::try_from(rat(6, 3)), Ok(Integer::from(2)));
+        assert_eq!(Integer::try_from(com(4, 1, 0, 1)), Ok(Integer::from(4)));
+        assert_eq!(Integer::try_from(rat(1, 2)), Err(rat(1, 2)));
+        assert_eq!(Integer::try_from(com(1, 1, 1, 1)), Err(com(1, 1, 1, 1)));
+        assert_eq!(Integer::try_from(var("a")), Err(var("a")));
+
+        assert_eq!(
+            Rational::try_from(ratd(3, 4)),
+            Ok(Rational::new(Integer::from(3), Integer::from(4))),
+        );
+        assert_eq!(
+            Rational::try_from(int(5)),
+            Ok(Rational::from(Integer::from(5)))
+        );
+        assert_eq!(Rational::try_from(com(1, 1, 1, 1)), Err(com(1, 1, 1, 1)));
+        assert_eq!(
+            Rational::try_from(Expression::Boolean(true)),
+            Err(Expression::Boolean(true))
+        );
+
+        assert_eq!(
+            Complex::try_from(com(1, 2, 3, 4)),
+            Ok(Complex::new(
+                Rational::new(Integer::from(1), Integer::from(2)),
+                Rational::new(Integer::from(3), Integer::from(4)),
+            )),
+        );
+        assert_eq!(
+            Complex::try_from(int(2)),
+            Ok(Complex::new(
+                Rational::from(Integer::from(2)),
+                Rational::from(Integer::from(0))
+            )),
+        );
+        assert_eq!(Complex::try_from(var("i")), Err(var("i")));
+
+        assert_eq!(
+            Vector::try_from(Expression::Vector(dvector![int(1), var("a")])),
+            Ok(dvector![int(1), var("a")]),
+        );
+        assert_eq!(
+            Vector::try_from(Expression::Matrix(dmatrix![int(1); int(2)])),
+            Ok(dvector![int(1), int(2)]),
+        );
+        assert_eq!(
+            Vector::try_from(Expression::Matrix(dmatrix![int(1), int(2)])),
+            Err(Expression::Matrix(dmatrix![int(1), int(2)])),
+        );
+        assert_eq!(Vector::try_from(int(1)), Err(int(1)));
+
+        assert_eq!(
+            Matrix::try_from(Expression::Matrix(dmatrix![int(1), int(2)])),
+            Ok(dmatrix![int(1), int(2)]),
+        );
+        assert_eq!(
+            Matrix::try_from(Expression::Vector(dvector![int(1), int(2)])),
+            Ok(dmatrix![int(1); int(2)]),
+        );
+        assert_eq!(Matrix::try_from(var("A")), Err(var("A")));
+
+        assert_eq!(bool::try_from(Expression::Boolean(true)), Ok(true));
+        assert_eq!(bool::try_from(Expression::Boolean(false)), Ok(false));
+        assert_eq!(bool::try_from(int(1)), Err(int(1)));
+        assert_eq!(bool::try_from(var("a")), Err(var("a")));
+    }
+}
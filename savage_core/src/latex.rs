@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+// Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
+
+use num::{One, Signed, Zero};
+
+use crate::expression::{Expression, Rational, RationalRepresentation};
+
+/// Returns the LaTeX representation of a name, which is set upright
+/// if it consists of more than one character and is not a number.
+fn latex_name(name: &str) -> String {
+    if name.chars().count() > 1 && !name.chars().all(|c| c.is_ascii_digit()) {
+        format!("\\mathrm{{{}}}", name.replace('_', "\\_"))
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Returns the LaTeX representation of an identifier, in which the part
+/// following the first underscore is a subscript (e.g. `x_{12}` for `x_12`).
+fn latex_identifier(identifier: &str) -> String {
+    match identifier.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() && !subscript.is_empty() => {
+            format!("{}_{{{}}}", latex_name(base), latex_identifier(subscript))
+        }
+        _ => latex_name(identifier),
+    }
+}
+
+/// Returns the LaTeX representation of a rational number, in which
+/// fractions are typeset using `\frac` (e.g. `-\frac{1}{2}` for `-1/2`).
+fn latex_rational(x: &Rational, representation: RationalRepresentation) -> String {
+    let string = Expression::Rational(x.abs(), representation).to_string();
+
+    format!(
+        "{}{}",
+        if x.is_negative() { "-" } else { "" },
+        match string.split_once('/') {
+            Some((numerator, denominator)) => format!("\\frac{{{}}}{{{}}}", numerator, denominator),
+            None => string,
+        },
+    )
+}
+
+impl Expression {
+    /// Returns the LaTeX representation of the expression, enclosed in parentheses if its
+    /// precedence is lower than `precedence`, or equal to it and `parenthesize_equal` is set.
+    fn to_latex_operand(&self, precedence: isize, parenthesize_equal: bool) -> String {
+        if self.precedence() < precedence || (self.precedence() == precedence && parenthesize_equal)
+        {
+            format!("\\left({}\\right)", self.to_latex())
+        } else {
+            self.to_latex()
+        }
+    }
+
+    /// Returns the LaTeX representation of the expression as a binary infix operator
+    /// with the minimally necessary parentheses.
+    fn to_latex_infix(&self, symbol: &str, a: &Self, b: &Self) -> String {
+        use crate::expression::Associativity::*;
+
+        format!(
+            "{} {} {}",
+            a.to_latex_operand(self.precedence(), self.associativity() == RightAssociative),
+            symbol,
+            b.to_latex_operand(self.precedence(), self.associativity() == LeftAssociative),
+        )
+    }
+
+    /// Returns the LaTeX representation of the expression, for use in math mode.
+    /// Identifiers containing an underscore are typeset with a subscript
+    /// (e.g. `x_{1}` for `x_1`), and identifiers of more than one character
+    /// are set upright.
+    pub fn to_latex(&self) -> String {
+        use crate::expression::Expression::*;
+
+        match self {
+            Variable(identifier) => latex_identifier(identifier),
+            Function(identifier, _) => latex_identifier(identifier),
+            FunctionValue(function, arguments) => format!(
+                "{}\\left({}\\right)",
+                match &**function {
+                    Variable(identifier) | Function(identifier, _) => {
+                        format!("\\operatorname{{{}}}", identifier.replace('_', "\\_"))
+                    }
+                    _ => function.to_latex_operand(isize::MAX, false),
+                },
+                arguments
+                    .iter()
+                    .map(|a| a.to_latex())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            Integer(n) => n.to_string(),
+            Rational(x, representation) => latex_rational(x, *representation),
+            Complex(z, representation) => {
+                // An unreduced fraction describes a single rational number,
+                // so it cannot apply to both parts of a complex number.
+                let part_representation =
+                    if let RationalRepresentation::Unreduced(_) = representation {
+                        RationalRepresentation::Fraction
+                    } else {
+                        *representation
+                    };
+
+                let imaginary_part = if z.im.abs().is_one() {
+                    "i".to_owned()
+                } else {
+                    format!("{} i", latex_rational(&z.im.abs(), part_representation))
+                };
+
+                if z.im.is_zero() {
+                    latex_rational(&z.re, *representation)
+                } else if z.re.is_zero() {
+                    format!(
+                        "{}{}",
+                        if z.im.is_negative() { "-" } else { "" },
+                        imaginary_part,
+                    )
+                } else {
+                    format!(
+                        "{} {} {}",
+                        latex_rational(&z.re, part_representation),
+                        if z.im.is_negative() { "-" } else { "+" },
+                        imaginary_part,
+                    )
+                }
+            }
+            Vector(v) => format!(
+                "\\begin{{pmatrix}}{}\\end{{pmatrix}}",
+                v.iter()
+                    .map(|element| element.to_latex())
+                    .collect::<Vec<_>>()
+                    .join(" \\\\ "),
+            ),
+            VectorElement(vector, i) => format!(
+                "{}_{{{}}}",
+                vector.to_latex_operand(isize::MAX, false),
+                i.to_latex(),
+            ),
+            Matrix(m) => format!(
+                "\\begin{{pmatrix}}{}\\end{{pmatrix}}",
+                m.row_iter()
+                    .map(|row| row
+                        .iter()
+                        .map(|element| element.to_latex())
+                        .collect::<Vec<_>>()
+                        .join(" & "))
+                    .collect::<Vec<_>>()
+                    .join(" \\\\ "),
+            ),
+            MatrixElement(matrix, i, j) => format!(
+                "{}_{{{}, {}}}",
+                matrix.to_latex_operand(isize::MAX, false),
+                i.to_latex(),
+                j.to_latex(),
+            ),
+            Boolean(boolean) => format!("\\mathrm{{{}}}", boolean),
+            Negation(a) => format!("-{}", a.to_latex_operand(self.precedence(), true)),
+            Not(a) => format!("\\neg {}", a.to_latex_operand(self.precedence(), true)),
+            // Adding a negative number is shown as subtracting its absolute value,
+            // and vice versa, like in the plain text representation.
+            Sum(a, b) => match b.absolute_value_if_negative() {
+                Some(b) => self.to_latex_infix("-", a, &b),
+                None => self.to_latex_infix("+", a, b),
+            },
+            Difference(a, b) => match b.absolute_value_if_negative() {
+                Some(b) => self.to_latex_infix("+", a, &b),
+                None => self.to_latex_infix("-", a, b),
+            },
+            Product(a, b) => self.to_latex_infix("\\cdot", a, b),
+            // The fraction bar groups the numerator and the denominator.
+            Quotient(a, b) => format!("\\frac{{{}}}{{{}}}", a.to_latex(), b.to_latex()),
+            Remainder(a, b) => self.to_latex_infix("\\bmod", a, b),
+            // The exponent is grouped by being set as a superscript.
+            Power(a, b) => format!(
+                "{}^{{{}}}",
+                a.to_latex_operand(self.precedence(), true),
+                b.to_latex(),
+            ),
+            Equal(a, b) => self.to_latex_infix("=", a, b),
+            NotEqual(a, b) => self.to_latex_infix("\\neq", a, b),
+            LessThan(a, b) => self.to_latex_infix("<", a, b),
+            LessThanOrEqual(a, b) => self.to_latex_infix("\\leq", a, b),
+            GreaterThan(a, b) => self.to_latex_infix(">", a, b),
+            GreaterThanOrEqual(a, b) => self.to_latex_infix("\\geq", a, b),
+            And(a, b) => self.to_latex_infix("\\land", a, b),
+            Or(a, b) => self.to_latex_infix("\\lor", a, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{dmatrix, dvector};
+
+    use crate::expression::{Expression, Expression::*};
+    use crate::helpers::*;
+
+    #[track_caller]
+    fn t(expression: Expression, string: &str) {
+        assert_eq!(expression.to_latex(), string);
+    }
+
+    #[test]
+    fn identifiers() {
+        t(var("x"), "x");
+        t(var("alpha"), "\\mathrm{alpha}");
+        t(var("x_1"), "x_{1}");
+        t(var("x_12"), "x_{12}");
+        t(var("x_max"), "x_{\\mathrm{max}}");
+        t(var("θ_1"), "θ_{1}");
+        t(var("a_b_c"), "a_{b_{c}}");
+        t(var("x_"), "\\mathrm{x\\_}");
+        t(var("_x"), "\\mathrm{\\_x}");
+        t(
+            fun(var("f"), [var("x_1"), var("x_12")]),
+            "\\operatorname{f}\\left(x_{1}, x_{12}\\right)",
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        t(int(-5), "-5");
+        t(rat(-1, 2), "-\\frac{1}{2}");
+        t(ratd(5, 2), "2.5");
+        t(ratd(1, 3), "\\frac{1}{3}");
+        t(ratu(2, 4), "\\frac{2}{4}");
+        t(com(0, 1, 1, 1), "i");
+        t(com(0, 1, -2, 1), "-2 i");
+        t(com(1, 2, -1, 1), "\\frac{1}{2} - i");
+        t(comd(1, 2, 3, 2), "0.5 + 1.5 i");
+    }
+
+    #[test]
+    fn operators() {
+        t(var("a") + int(-3), "a - 3");
+        t(
+            (var("a") + var("b")) * var("c"),
+            "\\left(a + b\\right) \\cdot c",
+        );
+        t(var("a") - (var("b") - var("c")), "a - \\left(b - c\\right)");
+        t((var("a") + int(1)) / var("b"), "\\frac{a + 1}{b}");
+        t(pow(var("x_1"), var("n") + int(1)), "x_{1}^{n + 1}");
+        t(
+            pow(pow(var("a"), var("b")), var("c")),
+            "\\left(a^{b}\\right)^{c}",
+        );
+        t(-(var("a") + var("b")), "-\\left(a + b\\right)");
+        t(eq(var("a"), int(1)), "a = 1");
+        t(
+            and(le(var("a"), int(1)), ne(var("b"), int(2))),
+            "a \\leq 1 \\land b \\neq 2",
+        );
+    }
+
+    #[test]
+    fn vectors_and_matrices() {
+        t(
+            Vector(dvector![int(1), var("x_1")]),
+            "\\begin{pmatrix}1 \\\\ x_{1}\\end{pmatrix}",
+        );
+        t(
+            Matrix(dmatrix![int(1), int(2); int(3), int(4)]),
+            "\\begin{pmatrix}1 & 2 \\\\ 3 & 4\\end{pmatrix}",
+        );
+        t(VectorElement(Box::new(var("v")), Box::new(int(0))), "v_{0}");
+    }
+}
@@ -1,10 +1,12 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
+mod canonical;
 pub mod evaluate;
 pub mod expression;
 pub mod functions;
 pub mod helpers;
+mod latex;
 pub mod parse;
-mod print;
+pub mod print;
 mod simplify;
@@ -1,12 +1,13 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
-use std::{ops::Range, str::FromStr};
+use std::{cell::RefCell, ops::Range, rc::Rc, str::FromStr};
 
 use chumsky::prelude::*;
 
 use crate::{
     expression::{Expression, Integer, Matrix, Vector},
+    functions::function_expression,
     helpers::*,
 };
 
@@ -16,15 +17,108 @@ pub type Error = chumsky::error::Simple<char, Range<usize>>;
 /// Reason why a parse error occurred.
 pub type ErrorReason = chumsky::error::SimpleReason<char, Range<usize>>;
 
+/// Spans in an input string that sub-expressions were parsed from.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct Spans(Vec<(Expression, Range<usize>)>);
+
+impl Spans {
+    /// Returns the span of the first part of the input that was parsed
+    /// into the given expression, or `None` if no such part exists.
+    pub fn get(&self, expression: &Expression) -> Option<Range<usize>> {
+        self.0
+            .iter()
+            .find(|(e, _)| e == expression)
+            .map(|(_, span)| span.clone())
+    }
+}
+
+/// Syntax accepted for operators by a parser.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Mode {
+    /// `^` denotes exponentiation.
+    Standard,
+    /// `^` denotes the bitwise exclusive or (XOR) of integers, which is parsed
+    /// as a call to the `bitxor` function, and `**` denotes exponentiation.
+    /// Like in most programming languages, `^` has a lower precedence than
+    /// all arithmetic operators, but a higher precedence than comparisons.
+    Programmer,
+}
+
+// Deriving `Default` for enums requires the `#[default]` attribute,
+// which is not available in Rust 1.56.
+#[allow(clippy::derivable_impls)]
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Standard
+    }
+}
+
 /// Returns a parser that produces expressions from character streams.
 ///
 /// The purpose of this function is to be a building block for parsers that parse
 /// expressions as parts of a more complex input language. If you simply want
 /// to turn strings into expressions, use `"a + b".parse::<Expression>()`.
-#[allow(clippy::let_and_return)]
 pub fn parser() -> impl Parser<char, Expression, Error = Error> {
+    spanned_parser(None, Mode::Standard)
+}
+
+/// Returns a parser that produces expressions from character streams using
+/// the syntax of the given mode, and records the spans that sub-expressions
+/// were parsed from in `spans`.
+///
+/// Note that because the parser backtracks, `spans` may contain entries
+/// from alternatives that were ultimately discarded. Those entries
+/// are still correct, in that they describe input that can be parsed
+/// into the recorded expression.
+pub fn parser_with_spans(
+    spans: Rc<RefCell<Spans>>,
+    mode: Mode,
+) -> impl Parser<char, Expression, Error = Error> {
+    spanned_parser(Some(spans), mode)
+}
+
+/// Returns the expression parsed from the string using the syntax of the given mode,
+/// and the spans in the string that sub-expressions were parsed from,
+/// or errors if the string cannot be parsed.
+pub fn parse_with_spans(string: &str, mode: Mode) -> Result<(Expression, Spans), Vec<Error>> {
+    let spans = Rc::new(RefCell::new(Spans::default()));
+
+    let expression = parser_with_spans(spans.clone(), mode)
+        .then_ignore(end())
+        .parse(string)?;
+
+    let spans = spans.borrow().clone();
+
+    Ok((expression, spans))
+}
+
+/// Returns a parser that produces identifiers, which consist of a letter or an underscore,
+/// followed by any number of letters, digits, and underscores. Letters and digits
+/// are not restricted to ASCII, so that e.g. `θ` and `α_1` are valid identifiers.
+pub fn identifier() -> impl Parser<char, String, Error = Error> + Clone {
+    filter(|c: &char| c.is_alphabetic() || *c == '_')
+        .chain(filter(|c: &char| c.is_alphanumeric() || *c == '_').repeated())
+        .collect()
+}
+
+#[allow(clippy::let_and_return)]
+fn spanned_parser(
+    spans: Option<Rc<RefCell<Spans>>>,
+    mode: Mode,
+) -> impl Parser<char, Expression, Error = Error> {
+    let record = move |expression: Expression, span: Range<usize>| {
+        if let Some(spans) = &spans {
+            spans
+                .borrow_mut()
+                .0
+                .push((expression.clone(), span.clone()));
+        }
+
+        (expression, span)
+    };
+
     recursive(|expression| {
-        let identifier = text::ident()
+        let identifier = self::identifier()
             .map(|identifier: String| match identifier.as_str() {
                 "true" => Expression::Boolean(true),
                 "false" => Expression::Boolean(false),
@@ -79,13 +173,35 @@ pub fn parser() -> impl Parser<char, Expression, Error = Error> {
             .labelled("vector_or_matrix")
             .boxed();
 
-        let atomic_expression = identifier
-            .or(number)
-            .or(vector_or_matrix)
-            .or(expression.clone().delimited_by(just('('), just(')')))
-            .padded()
+        // Bars delimit an absolute value only where an operand is expected,
+        // whereas the disjunction operator `||` only ever follows an operand.
+        // The built-in function is used directly, so that user-defined
+        // variables named `abs` do not change the meaning of `|x|`.
+        let abs = function_expression("abs").unwrap();
+
+        let absolute_value = expression
+            .clone()
+            .delimited_by(just('|'), just('|'))
+            .map(move |expression| fun(abs.clone(), [expression]))
+            .labelled("absolute_value")
             .boxed();
 
+        // From here on, parsers produce pairs of expressions and the spans
+        // they were parsed from, so that the spans of operator expressions
+        // can be computed from the spans of their operands.
+        let atomic_expression = choice((
+            identifier,
+            number,
+            vector_or_matrix,
+            expression.clone().delimited_by(just('('), just(')')),
+            absolute_value,
+        ))
+        .map_with_span(record.clone())
+        .padded()
+        .boxed();
+
+        let record_function_or_element = record.clone();
+
         let function_or_element = atomic_expression
             .then(
                 expression
@@ -101,54 +217,106 @@ pub fn parser() -> impl Parser<char, Expression, Error = Error> {
                         .at_most(2)
                         .delimited_by(just('['), just(']'))
                         .map(|indices| (None, Some(indices))))
+                    .map_with_span(|arguments_or_indices, span: Range<usize>| {
+                        (arguments_or_indices, span)
+                    })
                     .or_not(),
             )
-            .map(
-                |(expression, arguments_or_indices)| match arguments_or_indices {
-                    Some((Some(arguments), None)) => fun(expression, arguments),
-                    Some((None, Some(indices))) => {
-                        if indices.len() == 1 {
-                            Expression::VectorElement(
-                                Box::new(expression),
-                                Box::new(indices[0].clone()),
-                            )
-                        } else {
-                            Expression::MatrixElement(
-                                Box::new(expression),
-                                Box::new(indices[0].clone()),
-                                Box::new(indices[1].clone()),
-                            )
-                        }
+            .map(move |((expression, span), arguments_or_indices)| {
+                let (arguments_or_indices, end) = match arguments_or_indices {
+                    Some((arguments_or_indices, arguments_or_indices_span)) => {
+                        (arguments_or_indices, arguments_or_indices_span.end)
                     }
-                    None => expression,
-                    _ => unreachable!(),
-                },
-            )
+                    None => return (expression, span),
+                };
+
+                record_function_or_element(
+                    match arguments_or_indices {
+                        (Some(arguments), None) => fun(expression, arguments),
+                        (None, Some(indices)) => {
+                            if indices.len() == 1 {
+                                Expression::VectorElement(
+                                    Box::new(expression),
+                                    Box::new(indices[0].clone()),
+                                )
+                            } else {
+                                Expression::MatrixElement(
+                                    Box::new(expression),
+                                    Box::new(indices[0].clone()),
+                                    Box::new(indices[1].clone()),
+                                )
+                            }
+                        }
+                        _ => unreachable!(),
+                    },
+                    span.start..end,
+                )
+            })
             .padded()
             .boxed();
 
-        let power = function_or_element
-            .separated_by(just('^'))
+        let record_factorial = record.clone();
+
+        // Postfix `!` denotes the factorial, which is parsed as a call to the built-in
+        // `factorial` function, so that user-defined variables named `factorial`
+        // do not change its meaning. It is not followed by `=`, which would make it
+        // the `!=` operator.
+        let factorial_function = function_expression("factorial").unwrap();
+
+        let factorial = function_or_element
+            .then(
+                just('!')
+                    .map_with_span(|_, span: Range<usize>| span.end)
+                    .then_ignore(none_of('=').rewind().ignored().or(end()))
+                    .padded()
+                    .repeated(),
+            )
+            .foldl(move |(a, a_span), end| {
+                record_factorial(fun(factorial_function.clone(), [a]), a_span.start..end)
+            })
+            .labelled("factorial")
+            .boxed();
+
+        let record_power = record.clone();
+
+        let power_operator = match mode {
+            Mode::Standard => just('^').ignored().boxed(),
+            Mode::Programmer => just("**").ignored().boxed(),
+        };
+
+        let power = factorial
+            .separated_by(power_operator)
             .at_least(1)
-            .map(|expressions| {
+            .map(move |expressions| {
                 expressions
                     .into_iter()
                     .rev()
-                    .reduce(|a, b| pow(b, a))
+                    .reduce(|(a, a_span), (b, b_span)| {
+                        record_power(pow(b, a), b_span.start..a_span.end)
+                    })
                     .unwrap()
             })
             .labelled("power")
             .boxed();
 
+        let record_negation = record.clone();
+        let record_not = record.clone();
+
         let negation = just('-')
-            .ignore_then(power.clone())
-            .map(|a| -a)
-            .or(just('!').ignore_then(power.clone()).map(|a| !a))
+            .map_with_span(|_, span: Range<usize>| span.start)
+            .then(power.clone())
+            .map(move |(start, (a, a_span))| record_negation(-a, start..a_span.end))
+            .or(just('!')
+                .map_with_span(|_, span: Range<usize>| span.start)
+                .then(power.clone())
+                .map(move |(start, (a, a_span))| record_not(!a, start..a_span.end)))
             .labelled("negation")
             .or(power)
             .padded()
             .boxed();
 
+        let record_product_or_quotient_or_remainder = record.clone();
+
         let product_or_quotient_or_remainder = negation
             .clone()
             .then(
@@ -158,15 +326,22 @@ pub fn parser() -> impl Parser<char, Expression, Error = Error> {
                     .then(negation)
                     .repeated(),
             )
-            .foldl(|a, (operator, b)| match operator {
-                '*' => a * b,
-                '/' => a / b,
-                '%' => a % b,
-                _ => unreachable!(),
+            .foldl(move |(a, a_span), (operator, (b, b_span))| {
+                record_product_or_quotient_or_remainder(
+                    match operator {
+                        '*' => a * b,
+                        '/' => a / b,
+                        '%' => a % b,
+                        _ => unreachable!(),
+                    },
+                    a_span.start..b_span.end,
+                )
             })
             .labelled("product_or_quotient_or_remainder")
             .boxed();
 
+        let record_sum_or_difference = record.clone();
+
         let sum_or_difference = product_or_quotient_or_remainder
             .clone()
             .then(
@@ -175,15 +350,42 @@ pub fn parser() -> impl Parser<char, Expression, Error = Error> {
                     .then(product_or_quotient_or_remainder)
                     .repeated(),
             )
-            .foldl(|a, (operator, b)| match operator {
-                '+' => a + b,
-                '-' => a - b,
-                _ => unreachable!(),
+            .foldl(move |(a, a_span), (operator, (b, b_span))| {
+                record_sum_or_difference(
+                    match operator {
+                        '+' => a + b,
+                        '-' => a - b,
+                        _ => unreachable!(),
+                    },
+                    a_span.start..b_span.end,
+                )
             })
             .labelled("sum_or_difference")
             .boxed();
 
-        let comparison = sum_or_difference
+        let record_exclusive_or = record.clone();
+
+        let exclusive_or = match mode {
+            Mode::Standard => sum_or_difference,
+            Mode::Programmer => {
+                // The built-in function is used directly, so that user-defined
+                // variables named `bitxor` do not change the meaning of `^`.
+                let bitxor = function_expression("bitxor").unwrap();
+
+                sum_or_difference
+                    .clone()
+                    .then(just('^').ignore_then(sum_or_difference).repeated())
+                    .foldl(move |(a, a_span), (b, b_span)| {
+                        record_exclusive_or(fun(bitxor.clone(), [a, b]), a_span.start..b_span.end)
+                    })
+                    .labelled("exclusive_or")
+                    .boxed()
+            }
+        };
+
+        let record_comparison = record.clone();
+
+        let comparison = exclusive_or
             .clone()
             .then(
                 just('=')
@@ -194,21 +396,28 @@ pub fn parser() -> impl Parser<char, Expression, Error = Error> {
                     .or(just('>').chain(just('=')))
                     .or(just('>').to(vec!['>']))
                     .collect::<String>()
-                    .then(sum_or_difference)
+                    .then(exclusive_or)
                     .repeated(),
             )
-            .foldl(|a, (operator, b)| match operator.as_str() {
-                "==" => eq(a, b),
-                "!=" => ne(a, b),
-                "<" => lt(a, b),
-                "<=" => le(a, b),
-                ">" => gt(a, b),
-                ">=" => ge(a, b),
-                _ => unreachable!(),
+            .foldl(move |(a, a_span), (operator, (b, b_span))| {
+                record_comparison(
+                    match operator.as_str() {
+                        "==" => eq(a, b),
+                        "!=" => ne(a, b),
+                        "<" => lt(a, b),
+                        "<=" => le(a, b),
+                        ">" => gt(a, b),
+                        ">=" => ge(a, b),
+                        _ => unreachable!(),
+                    },
+                    a_span.start..b_span.end,
+                )
             })
             .labelled("comparison")
             .boxed();
 
+        let record_conjunction = record.clone();
+
         let conjunction = comparison
             .clone()
             .then(
@@ -217,10 +426,14 @@ pub fn parser() -> impl Parser<char, Expression, Error = Error> {
                     .ignore_then(comparison)
                     .repeated(),
             )
-            .foldl(and)
+            .foldl(move |(a, a_span), (b, b_span)| {
+                record_conjunction(and(a, b), a_span.start..b_span.end)
+            })
             .labelled("conjunction")
             .boxed();
 
+        let record_disjunction = record.clone();
+
         let disjunction = conjunction
             .clone()
             .then(
@@ -229,7 +442,10 @@ pub fn parser() -> impl Parser<char, Expression, Error = Error> {
                     .ignore_then(conjunction)
                     .repeated(),
             )
-            .foldl(or)
+            .foldl(move |(a, a_span), (b, b_span)| {
+                record_disjunction(or(a, b), a_span.start..b_span.end)
+            })
+            .map(|(expression, _)| expression)
             .labelled("disjunction")
             .boxed();
 
@@ -250,6 +466,7 @@ mod tests {
     use nalgebra::{dmatrix, dvector};
 
     use crate::expression::{Expression, Expression::*};
+    use crate::functions::function_expression;
     use crate::helpers::*;
 
     #[track_caller]
@@ -262,6 +479,12 @@ mod tests {
         t("a   ", var("a"));
         t("     A", var("A"));
         t("  Named_Variable ", var("Named_Variable"));
+        t(" θ", var("θ"));
+        t("x_12", var("x_12"));
+        t("α_1 ", var("α_1"));
+        t("_Δx2", var("_Δx2"));
+        t("θ^2*φ", pow(var("θ"), int(2)) * var("φ"));
+        assert!("1θ".parse::<Expression>().is_err());
     }
 
     #[test]
@@ -374,6 +597,20 @@ mod tests {
         t(" (1^2)  ^  3", pow(pow(int(1), int(2)), int(3)));
         t("1 ^2 ^3 ", pow(int(1), pow(int(2), int(3))));
 
+        let factorial = |a| fun(function_expression("factorial").unwrap(), [a]);
+
+        t("5!", factorial(int(5)));
+        t(" (n + 1) ! ", factorial(var("n") + int(1)));
+        t("3!!", factorial(factorial(int(3))));
+        t("2 ^ 3!", pow(int(2), factorial(int(3))));
+        t("3! ^ 2", pow(factorial(int(3)), int(2)));
+        t("-3!", -factorial(int(3)));
+        t("!a!", !factorial(var("a")));
+        t("f(x)!", factorial(fun(var("f"), [var("x")])));
+        t("a! != b", ne(factorial(var("a")), var("b")));
+        t("a != b!", ne(var("a"), factorial(var("b"))));
+        t("a!=b", ne(var("a"), var("b")));
+
         // TODO: Comparison operators!
 
         t("A&&B&&C", and(and(var("A"), var("B")), var("C")));
@@ -382,6 +619,62 @@ mod tests {
         t("A||B ||C", or(or(var("A"), var("B")), var("C")));
         t("A&& ( B|| C)", and(var("A"), or(var("B"), var("C"))));
         t("   A|| B &&C", or(var("A"), and(var("B"), var("C"))));
+
+        t("A&&B&&C", (var("A") & var("B")) & var("C"));
+        t("A  &&  B||C", var("A") & var("B") | var("C"));
+        t(" ( A || B ) && C ", (var("A") | var("B")) & var("C"));
+        t("A||B ||C", (var("A") | var("B")) | var("C"));
+        t("A&& ( B|| C)", var("A") & (var("B") | var("C")));
+        t("   A|| B &&C", var("A") | var("B") & var("C"));
+    }
+
+    #[test]
+    fn modes() {
+        use crate::parse::{parse_with_spans, Mode::*};
+
+        let p = |string, mode| parse_with_spans(string, mode).map(|(expression, _)| expression);
+        let xor = |a, b| fun(function_expression("bitxor").unwrap(), [a, b]);
+
+        assert_eq!(p("2 ^ 3", Standard), Ok(pow(int(2), int(3))));
+        assert_eq!(p("2 ^ 3", Programmer), Ok(xor(int(2), int(3))));
+        assert_eq!(p("2 ** 3", Programmer), Ok(pow(int(2), int(3))));
+        assert!(p("2 ** 3", Standard).is_err());
+
+        assert_eq!(
+            p("2**3 **2", Programmer),
+            Ok(pow(int(2), pow(int(3), int(2)))),
+        );
+        assert_eq!(p(" 2*3", Programmer), Ok(int(2) * int(3)));
+        assert_eq!(
+            p("a^b ^c", Programmer),
+            Ok(xor(xor(var("a"), var("b")), var("c"))),
+        );
+        assert_eq!(
+            p("1 + 2 ^ 3 * 4", Programmer),
+            Ok(xor(int(1) + int(2), int(3) * int(4))),
+        );
+        assert_eq!(
+            p("a ^ b == c", Programmer),
+            Ok(eq(xor(var("a"), var("b")), var("c"))),
+        );
+    }
+
+    #[test]
+    fn absolute_values() {
+        let abs = |a| fun(function_expression("abs").unwrap(), [a]);
+
+        t("|x|", abs(var("x")));
+        t(" | a +b | ", abs(var("a") + var("b")));
+        t("|a| + |b|", abs(var("a")) + abs(var("b")));
+        t("|a|*|b|", abs(var("a")) * abs(var("b")));
+        t("- |a|^2", -pow(abs(var("a")), int(2)));
+        t("||a| - 1|", abs(abs(var("a")) - int(1)));
+        t("| |a| |", abs(abs(var("a"))));
+        t("f(|a|, b)", fun(var("f"), [abs(var("a")), var("b")]));
+
+        t("a || b", or(var("a"), var("b")));
+        t("|a| || |b|", or(abs(var("a")), abs(var("b"))));
+        t("a||b", or(var("a"), var("b")));
     }
 
     // TODO: Replace with a real benchmark once `#[bench]` is stable.
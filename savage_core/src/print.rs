@@ -1,8 +1,8 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
-use std::cmp::max;
 use std::fmt::{Display, Formatter, Result};
+use std::{cmp::max, collections::HashMap};
 
 use num::{One, Signed, Zero};
 
@@ -38,65 +38,305 @@ fn decimal_representation(x: &Rational) -> Option<(Integer, usize)> {
     }
 }
 
+/// Maximum number of digits after the decimal separator
+/// (including those of the repetend) in a repeating decimal.
+const MAX_REPEATING_DECIMAL_DIGITS: usize = 100;
+
+/// Returns a string representing `x` as a repeating decimal, with the repetend
+/// in parentheses (e.g. `0.1(6)` for `1/6`), or `None` if `x` has a finite decimal
+/// representation or the repeating decimal would have too many digits.
+fn repeating_decimal_representation(x: &Rational) -> Option<String> {
+    let denominator = x.denom();
+
+    let mut string = format!(
+        "{}{}.",
+        if x.is_negative() { "-" } else { "" },
+        (x.numer().abs() / denominator),
+    );
+
+    // Long division, remembering the position at which each remainder occurred.
+    // When a remainder repeats, so do all digits following it.
+    let mut remainder = x.numer().abs() % denominator;
+    let mut positions = HashMap::new();
+    let mut digits = String::new();
+
+    while !remainder.is_zero() {
+        if let Some(&position) = positions.get(&remainder) {
+            string.push_str(&digits[..position]);
+            string.push('(');
+            string.push_str(&digits[position..]);
+            string.push(')');
+            return Some(string);
+        }
+
+        if digits.len() == MAX_REPEATING_DECIMAL_DIGITS {
+            return None;
+        }
+
+        positions.insert(remainder.clone(), digits.len());
+
+        remainder *= 10;
+        digits.push_str(&(&remainder / denominator).to_string());
+        remainder %= denominator;
+    }
+
+    None
+}
+
+/// Returns a string representing `x` as a decimal rounded to the given number
+/// of significant digits (e.g. `0.333` for `1/3` with 3 significant digits),
+/// without trailing zeros after the decimal separator. If the integer part
+/// has more digits than that, `x` is rounded to an integer instead.
+fn rounded_decimal_representation(x: &Rational, significant_digits: usize) -> String {
+    let significant_digits = max(significant_digits, 1);
+
+    if x.is_zero() {
+        return "0".to_owned();
+    }
+
+    let ten = Rational::from_integer(Integer::from(10));
+
+    // Exponent of the most significant digit, i.e. `10^exponent <= |x| < 10^(exponent + 1)`.
+    let mut exponent = 0;
+    let mut y = x.abs();
+
+    while y >= ten {
+        y /= ten.clone();
+        exponent += 1;
+    }
+
+    while y < Rational::one() {
+        y *= ten.clone();
+        exponent -= 1;
+    }
+
+    let mut digits = (y * ten.pow(significant_digits as i32 - 1))
+        .round()
+        .to_integer();
+
+    // Rounding can carry over into an additional digit (e.g. `9.99` to `10.0`).
+    if digits == Integer::from(10).pow(significant_digits as u32) {
+        digits /= 10;
+        exponent += 1;
+    }
+
+    let digits = digits.to_string();
+
+    let mut string = if exponent >= significant_digits as i32 - 1 {
+        // Replacing digits of the integer part with zeros would print
+        // a different integer that looks exact, so they are all kept.
+        x.abs().round().to_integer().to_string()
+    } else if exponent >= 0 {
+        let (integer_part, fractional_part) = digits.split_at(exponent as usize + 1);
+        format!("{}.{}", integer_part, fractional_part)
+    } else {
+        format!("0.{}{}", "0".repeat((-exponent - 1) as usize), digits)
+    };
+
+    if string.contains('.') {
+        string = string
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned();
+    }
+
+    format!("{}{}", if x.is_negative() { "-" } else { "" }, string)
+}
+
+/// Options controlling how expressions are formatted.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct DisplayOptions {
+    /// Maximum number of elements shown for each vector, and for the rows
+    /// and columns of each matrix. If there are more elements than that,
+    /// only the first and last few are shown, separated by `...`.
+    /// `None` means that all elements are shown.
+    pub max_elements: Option<usize>,
+    /// Whether rational numbers with decimal representation that have no finite
+    /// decimal representation are printed as repeating decimals, with the repetend
+    /// in parentheses (e.g. `0.(3)`), instead of falling back to fraction representation.
+    /// Numbers whose repeating decimals would be excessively long are always printed
+    /// as fractions.
+    pub repeating_decimals: bool,
+    /// Number of significant digits to which rational numbers with decimal representation
+    /// that have no finite decimal representation are rounded, instead of falling back
+    /// to fraction representation. Numbers whose integer part has more digits than that
+    /// are rounded to integers. Repeating decimals take precedence if enabled.
+    /// `None` means that such numbers are not rounded.
+    pub significant_digits: Option<usize>,
+    /// Whether every binary operation is enclosed in parentheses (e.g. `(1 + (2 * 3))`),
+    /// instead of only those that are necessary to preserve the structure of the expression.
+    pub fully_parenthesized: bool,
+}
+
+impl DisplayOptions {
+    /// Returns the elements formatted using the function `f` and separated by commas,
+    /// with the elements in the middle replaced by `...` if there are too many of them.
+    fn join<T>(
+        &self,
+        elements: impl ExactSizeIterator<Item = T>,
+        f: impl Fn(T) -> String,
+    ) -> String {
+        let length = elements.len();
+
+        let (head, tail) = match self.max_elements {
+            Some(max_elements) if length > max_elements => {
+                (max_elements - max_elements / 2, max_elements / 2)
+            }
+            _ => (length, 0),
+        };
+
+        elements
+            .enumerate()
+            .filter_map(|(i, element)| {
+                if i < head || i >= length - tail {
+                    Some(f(element))
+                } else if i == head {
+                    Some("...".to_owned())
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Expression that is formatted using specific display options.
+pub struct Displayed<'a> {
+    expression: &'a Expression,
+    options: DisplayOptions,
+}
+
+impl Display for Displayed<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.expression.fmt_with_options(f, self.options)
+    }
+}
+
 impl Expression {
+    /// Returns an object that formats the expression using the given display options.
+    pub fn display(&self, options: DisplayOptions) -> Displayed<'_> {
+        Displayed {
+            expression: self,
+            options,
+        }
+    }
+
+    /// Returns the absolute value of the expression if it is a negative
+    /// integer or rational number, or `None` otherwise.
+    pub(crate) fn absolute_value_if_negative(&self) -> Option<Self> {
+        use crate::expression::Expression::*;
+
+        match self {
+            Integer(n) if n.is_negative() => Some(Integer(-n)),
+            Rational(x, representation) if x.is_negative() => Some(Rational(-x, *representation)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the expression encloses itself in parentheses when formatted
+    /// using the given display options, which makes additional parentheses redundant.
+    fn is_self_parenthesized(&self, options: DisplayOptions) -> bool {
+        use crate::expression::Expression::*;
+
+        options.fully_parenthesized
+            && matches!(
+                self,
+                Sum(_, _)
+                    | Difference(_, _)
+                    | Product(_, _)
+                    | Quotient(_, _)
+                    | Remainder(_, _)
+                    | Power(_, _)
+                    | Equal(_, _)
+                    | NotEqual(_, _)
+                    | LessThan(_, _)
+                    | LessThanOrEqual(_, _)
+                    | GreaterThan(_, _)
+                    | GreaterThanOrEqual(_, _)
+                    | And(_, _)
+                    | Or(_, _)
+            )
+    }
+
     /// Formats the expression as a unary prefix operator with the minimally necessary parentheses.
-    fn fmt_prefix(&self, f: &mut Formatter<'_>, symbol: &str, a: &Self) -> Result {
-        let a_needs_parentheses = a.precedence() <= self.precedence();
+    fn fmt_prefix(
+        &self,
+        f: &mut Formatter<'_>,
+        options: DisplayOptions,
+        symbol: &str,
+        a: &Self,
+    ) -> Result {
+        let a_needs_parentheses =
+            !a.is_self_parenthesized(options) && a.precedence() <= self.precedence();
 
         write!(
             f,
             "{}{}{}{}",
             symbol,
             if a_needs_parentheses { "(" } else { "" },
-            a,
+            a.display(options),
             if a_needs_parentheses { ")" } else { "" },
         )
     }
 
-    /// Formats the expression as a binary infix operator with the minimally necessary parentheses.
-    fn fmt_infix(&self, f: &mut Formatter<'_>, symbol: &str, a: &Self, b: &Self) -> Result {
+    /// Formats the expression as a binary infix operator with the minimally necessary parentheses,
+    /// or enclosed in parentheses itself if the display options call for full parenthesization.
+    fn fmt_infix(
+        &self,
+        f: &mut Formatter<'_>,
+        options: DisplayOptions,
+        symbol: &str,
+        a: &Self,
+        b: &Self,
+    ) -> Result {
         use crate::expression::Associativity::*;
 
-        let a_needs_parentheses = (a.precedence() < self.precedence())
-            || ((a.precedence() == self.precedence())
-                && (self.associativity() == RightAssociative));
+        let a_needs_parentheses = !a.is_self_parenthesized(options)
+            && ((a.precedence() < self.precedence())
+                || ((a.precedence() == self.precedence())
+                    && (self.associativity() == RightAssociative)));
 
-        let b_needs_parentheses = (b.precedence() < self.precedence())
-            || ((b.precedence() == self.precedence()) && (self.associativity() == LeftAssociative));
+        let b_needs_parentheses = !b.is_self_parenthesized(options)
+            && ((b.precedence() < self.precedence())
+                || ((b.precedence() == self.precedence())
+                    && (self.associativity() == LeftAssociative)));
 
         write!(
             f,
-            "{}{}{} {} {}{}{}",
+            "{}{}{}{} {} {}{}{}{}",
+            if options.fully_parenthesized { "(" } else { "" },
             if a_needs_parentheses { "(" } else { "" },
-            a,
+            a.display(options),
             if a_needs_parentheses { ")" } else { "" },
             symbol,
             if b_needs_parentheses { "(" } else { "" },
-            b,
+            b.display(options),
             if b_needs_parentheses { ")" } else { "" },
+            if options.fully_parenthesized { ")" } else { "" },
         )
     }
-}
 
-impl Display for Expression {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+    /// Formats the expression using the given display options.
+    fn fmt_with_options(&self, f: &mut Formatter<'_>, options: DisplayOptions) -> Result {
         use crate::expression::{Expression::*, RationalRepresentation::*};
 
         match self {
             Variable(identifier) => write!(f, "{}", identifier),
             Function(identifier, _) => write!(f, "{}", identifier),
             FunctionValue(function, arguments) => {
-                let function_needs_parentheses = function.precedence() < isize::MAX;
+                let function_needs_parentheses =
+                    !function.is_self_parenthesized(options) && function.precedence() < isize::MAX;
 
                 write!(
                     f,
                     "{}{}{}({})",
                     if function_needs_parentheses { "(" } else { "" },
-                    function,
+                    function.display(options),
                     if function_needs_parentheses { ")" } else { "" },
                     arguments
                         .iter()
-                        .map(|a| a.to_string())
+                        .map(|a| a.display(options).to_string())
                         .collect::<Vec<_>>()
                         .join(", "),
                 )
@@ -110,13 +350,14 @@ impl Display for Expression {
                             let mut string = mantissa.abs().to_string();
 
                             if separator_position > 0 {
-                                if separator_position > string.len() - 1 {
-                                    // Left-pad the string with enough zeros to be able
-                                    // to insert the decimal separator at the indicated position.
+                                if string.len() <= separator_position {
+                                    // Left-pad the string with zeros so that at least one digit
+                                    // (a zero) precedes the decimal separator, e.g. "0.001"
+                                    // for a mantissa of "1" and a separator position of 3.
                                     string = format!(
-                                        "{}{}",
-                                        "0".repeat(separator_position - (string.len() - 1)),
+                                        "{:0>width$}",
                                         string,
+                                        width = separator_position + 1,
                                     );
                                 }
 
@@ -124,124 +365,167 @@ impl Display for Expression {
                             }
 
                             write!(f, "{}{}", if x.is_negative() { "-" } else { "" }, string)
+                        } else if let Some(string) = options
+                            .repeating_decimals
+                            .then(|| repeating_decimal_representation(x))
+                            .flatten()
+                        {
+                            write!(f, "{}", string)
+                        } else if let Some(significant_digits) = options.significant_digits {
+                            write!(
+                                f,
+                                "{}",
+                                rounded_decimal_representation(x, significant_digits),
+                            )
                         } else {
                             // Fall back to fraction representation.
                             write!(f, "{}", x)
                         }
                     }
+                    Unreduced(factor) => {
+                        write!(f, "{}/{}", x.numer() * factor, x.denom() * factor)
+                    }
                 }
             }
             Complex(z, representation) => {
+                // An unreduced fraction describes a single rational number,
+                // so it cannot apply to both parts of a complex number.
+                let part_representation = if let Unreduced(_) = representation {
+                    Fraction
+                } else {
+                    *representation
+                };
+
                 if z.im.is_zero() {
-                    write!(f, "{}", Rational(z.re.clone(), *representation))
+                    write!(
+                        f,
+                        "{}",
+                        Rational(z.re.clone(), *representation).display(options)
+                    )
                 } else if z.re.is_zero() {
                     if z.im.abs().is_one() {
                         write!(f, "{}i", if z.im.is_negative() { "-" } else { "" })
                     } else {
-                        write!(f, "{}*i", Rational(z.im.clone(), *representation))
+                        write!(
+                            f,
+                            "{}*i",
+                            Rational(z.im.clone(), part_representation).display(options)
+                        )
                     }
                 } else if z.re.is_negative() && z.im.is_positive() {
                     if z.im.is_one() {
-                        write!(f, "i - {}", Rational(z.re.abs(), *representation))
+                        write!(
+                            f,
+                            "i - {}",
+                            Rational(z.re.abs(), part_representation).display(options)
+                        )
                     } else {
                         write!(
                             f,
                             "{}*i - {}",
-                            Rational(z.im.clone(), *representation),
-                            Rational(z.re.abs(), *representation),
+                            Rational(z.im.clone(), part_representation).display(options),
+                            Rational(z.re.abs(), part_representation).display(options),
                         )
                     }
                 } else if z.im.abs().is_one() {
                     write!(
                         f,
                         "{} {} i",
-                        Rational(z.re.clone(), *representation),
+                        Rational(z.re.clone(), part_representation).display(options),
                         if z.im.is_negative() { "-" } else { "+" },
                     )
                 } else {
                     write!(
                         f,
                         "{} {} {}*i",
-                        Rational(z.re.clone(), *representation),
+                        Rational(z.re.clone(), part_representation).display(options),
                         if z.im.is_negative() { "-" } else { "+" },
-                        Rational(z.im.abs(), *representation),
+                        Rational(z.im.abs(), part_representation).display(options),
                     )
                 }
             }
             Vector(v) => write!(
                 f,
                 "[{}]",
-                v.iter()
-                    .map(|element| element.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
+                options.join(v.iter(), |element| element.display(options).to_string()),
             ),
             VectorElement(vector, i) => {
-                let vector_needs_parentheses = vector.precedence() < isize::MAX;
+                let vector_needs_parentheses =
+                    !vector.is_self_parenthesized(options) && vector.precedence() < isize::MAX;
 
                 write!(
                     f,
                     "{}{}{}[{}]",
                     if vector_needs_parentheses { "(" } else { "" },
-                    vector,
+                    vector.display(options),
                     if vector_needs_parentheses { ")" } else { "" },
-                    i,
+                    i.display(options),
                 )
             }
             Matrix(m) => write!(
                 f,
                 "[{}]",
-                m.row_iter()
-                    .map(|row| format!(
-                        "[{}]",
-                        row.iter()
-                            .map(|element| element.to_string())
-                            .collect::<Vec<_>>()
-                            .join(", "),
-                    ))
-                    .collect::<Vec<_>>()
-                    .join(", "),
+                options.join(m.row_iter(), |row| format!(
+                    "[{}]",
+                    options.join(row.iter(), |element| element.display(options).to_string()),
+                )),
             ),
             MatrixElement(matrix, i, j) => {
-                let matrix_needs_parentheses = matrix.precedence() < isize::MAX;
+                let matrix_needs_parentheses =
+                    !matrix.is_self_parenthesized(options) && matrix.precedence() < isize::MAX;
 
                 write!(
                     f,
                     "{}{}{}[{}, {}]",
                     if matrix_needs_parentheses { "(" } else { "" },
-                    matrix,
+                    matrix.display(options),
                     if matrix_needs_parentheses { ")" } else { "" },
-                    i,
-                    j,
+                    i.display(options),
+                    j.display(options),
                 )
             }
             Boolean(boolean) => write!(f, "{}", boolean),
-            Negation(a) => self.fmt_prefix(f, "-", a),
-            Not(a) => self.fmt_prefix(f, "!", a),
-            Sum(a, b) => self.fmt_infix(f, "+", a, b),
-            Difference(a, b) => self.fmt_infix(f, "-", a, b),
-            Product(a, b) => self.fmt_infix(f, "*", a, b),
-            Quotient(a, b) => self.fmt_infix(f, "/", a, b),
-            Remainder(a, b) => self.fmt_infix(f, "%", a, b),
-            Power(a, b) => self.fmt_infix(f, "^", a, b),
-            Equal(a, b) => self.fmt_infix(f, "==", a, b),
-            NotEqual(a, b) => self.fmt_infix(f, "!=", a, b),
-            LessThan(a, b) => self.fmt_infix(f, "<", a, b),
-            LessThanOrEqual(a, b) => self.fmt_infix(f, "<=", a, b),
-            GreaterThan(a, b) => self.fmt_infix(f, ">", a, b),
-            GreaterThanOrEqual(a, b) => self.fmt_infix(f, ">=", a, b),
-            And(a, b) => self.fmt_infix(f, "&&", a, b),
-            Or(a, b) => self.fmt_infix(f, "||", a, b),
+            Negation(a) => self.fmt_prefix(f, options, "-", a),
+            Not(a) => self.fmt_prefix(f, options, "!", a),
+            // Adding a negative number is shown as subtracting its absolute value,
+            // and vice versa, e.g. `a - 3` rather than `a + -3`.
+            Sum(a, b) => match b.absolute_value_if_negative() {
+                Some(b) => self.fmt_infix(f, options, "-", a, &b),
+                None => self.fmt_infix(f, options, "+", a, b),
+            },
+            Difference(a, b) => match b.absolute_value_if_negative() {
+                Some(b) => self.fmt_infix(f, options, "+", a, &b),
+                None => self.fmt_infix(f, options, "-", a, b),
+            },
+            Product(a, b) => self.fmt_infix(f, options, "*", a, b),
+            Quotient(a, b) => self.fmt_infix(f, options, "/", a, b),
+            Remainder(a, b) => self.fmt_infix(f, options, "%", a, b),
+            Power(a, b) => self.fmt_infix(f, options, "^", a, b),
+            Equal(a, b) => self.fmt_infix(f, options, "==", a, b),
+            NotEqual(a, b) => self.fmt_infix(f, options, "!=", a, b),
+            LessThan(a, b) => self.fmt_infix(f, options, "<", a, b),
+            LessThanOrEqual(a, b) => self.fmt_infix(f, options, "<=", a, b),
+            GreaterThan(a, b) => self.fmt_infix(f, options, ">", a, b),
+            GreaterThanOrEqual(a, b) => self.fmt_infix(f, options, ">=", a, b),
+            And(a, b) => self.fmt_infix(f, options, "&&", a, b),
+            Or(a, b) => self.fmt_infix(f, options, "||", a, b),
         }
     }
 }
 
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.fmt_with_options(f, DisplayOptions::default())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use nalgebra::{dmatrix, dvector};
 
     use crate::expression::{Expression, Expression::*};
     use crate::helpers::*;
+    use crate::print::DisplayOptions;
 
     #[track_caller]
     fn t(expression: Expression, string: &str) {
@@ -304,6 +588,30 @@ mod tests {
         t(ratd(123, -40), "-3.075");
         t(rat(-123, -40), "123/40");
         t(ratd(-123, -40), "3.075");
+        t(ratd(1, 10), "0.1");
+        t(ratd(1, 100), "0.01");
+        t(ratd(1, 1000), "0.001");
+        t(ratd(1, 10000), "0.0001");
+        t(ratd(3, 80), "0.0375");
+        t(ratd(1, 8), "0.125");
+        t(ratd(999, 1000), "0.999");
+        t(ratd(1000, 1000), "1");
+        t(ratd(1001, 1000), "1.001");
+        t(ratd(9, 10), "0.9");
+        t(ratd(10, 1), "10");
+        t(ratd(100, 10), "10");
+        t(ratd(1001, 10), "100.1");
+        t(ratd(-1, 10), "-0.1");
+        t(ratd(-1, 100), "-0.01");
+        t(ratd(-1, 1000), "-0.001");
+        t(ratd(-1, 10000), "-0.0001");
+        t(ratd(-1, 20), "-0.05");
+        t(ratd(-1001, 1000), "-1.001");
+        t(ratu(2, 4), "2/4");
+        t(ratu(-2, 4), "-2/4");
+        t(ratu(4, 2), "4/2");
+        t(ratu(1, 2), "1/2");
+        t(ratu(1u64 << 40, 1u64 << 41), "1/2");
     }
 
     #[test]
@@ -330,6 +638,7 @@ mod tests {
         t(comd(123, -40, 1, 3), "1/3*i - 3.075");
         t(com(1, 3, 123, 40), "1/3 + 123/40*i");
         t(comd(1, 3, 123, 40), "1/3 + 3.075*i");
+        t(comd(1, 1000, -1, 100), "0.001 - 0.01*i");
     }
 
     #[test]
@@ -368,6 +677,173 @@ mod tests {
         );
     }
 
+    #[test]
+    fn truncation() {
+        let options = DisplayOptions {
+            max_elements: Some(4),
+            ..DisplayOptions::default()
+        };
+
+        let v = Vector((1..=10).map(int).collect::<Vec<_>>().into());
+
+        assert_eq!(v.display(options).to_string(), "[1, 2, ..., 9, 10]");
+        assert_eq!(
+            v.display(DisplayOptions {
+                max_elements: Some(5),
+                ..DisplayOptions::default()
+            })
+            .to_string(),
+            "[1, 2, 3, ..., 9, 10]",
+        );
+        assert_eq!(
+            v.display(DisplayOptions {
+                max_elements: Some(10),
+                ..DisplayOptions::default()
+            })
+            .to_string(),
+            "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]",
+        );
+        assert_eq!(
+            v.display(DisplayOptions::default()).to_string(),
+            v.to_string(),
+        );
+        assert_eq!(
+            (v.clone() + var("a")).display(options).to_string(),
+            "[1, 2, ..., 9, 10] + a",
+        );
+
+        assert_eq!(
+            Matrix(nalgebra::DMatrix::from_fn(6, 5, |i, j| int(10 * i + j)))
+                .display(options)
+                .to_string(),
+            "[[0, 1, ..., 3, 4], [10, 11, ..., 13, 14], ..., [40, 41, ..., 43, 44], [50, 51, ..., 53, 54]]",
+        );
+    }
+
+    #[test]
+    fn repeating_decimals() {
+        let options = DisplayOptions {
+            repeating_decimals: true,
+            ..DisplayOptions::default()
+        };
+
+        let t = |expression: Expression, string: &str| {
+            assert_eq!(expression.display(options).to_string(), string);
+        };
+
+        // Terminating decimals are unaffected.
+        t(ratd(1, 4), "0.25");
+        t(ratd(-5, 2), "-2.5");
+        t(ratd(3, 1), "3");
+
+        // Single-digit repetends.
+        t(ratd(1, 3), "0.(3)");
+        t(ratd(-2, 3), "-0.(6)");
+        t(ratd(1, 6), "0.1(6)");
+        t(ratd(7, 3), "2.(3)");
+
+        // Multi-digit repetends.
+        t(ratd(1, 7), "0.(142857)");
+        t(ratd(22, 7), "3.(142857)");
+        t(ratd(1, 12), "0.08(3)");
+        t(ratd(5, 11), "0.(45)");
+        t(ratd(1, 81), "0.(012345679)");
+        t(comd(1, 3, -2, 3), "0.(3) - 0.(6)*i");
+
+        // Repetends that are too long fall back to fraction representation.
+        t(ratd(1, 109), "1/109");
+
+        // Only numbers with decimal representation are affected.
+        t(rat(1, 3), "1/3");
+        assert_eq!(ratd(1, 3).to_string(), "1/3");
+    }
+
+    #[test]
+    fn significant_digits() {
+        let options = DisplayOptions {
+            significant_digits: Some(3),
+            ..DisplayOptions::default()
+        };
+
+        let t = |expression: Expression, string: &str| {
+            assert_eq!(expression.display(options).to_string(), string);
+        };
+
+        // Terminating decimals are unaffected.
+        t(ratd(1, 4), "0.25");
+        t(ratd(12345, 1), "12345");
+        t(ratd(123456, 1000), "123.456");
+
+        t(ratd(1, 3), "0.333");
+        t(ratd(-2, 3), "-0.667");
+        t(ratd(1, 30), "0.0333");
+        t(ratd(1, 3000), "0.000333");
+        t(ratd(100, 3), "33.3");
+        t(ratd(10000, 3), "3333");
+        t(ratd(-20000, 3), "-6667");
+        t(ratd(1, 6), "0.167");
+        t(ratd(1000, 1001), "0.999");
+        t(ratd(2, 3) - var("a"), "0.667 - a");
+        t(comd(1, 3, -2, 3), "0.333 - 0.667*i");
+
+        // Rounding that carries over into an additional digit.
+        t(ratd(29999, 3000), "10");
+        t(ratd(-9999, 1001), "-9.99");
+        t(ratd(-99999, 10001), "-10");
+
+        // Trailing zeros are omitted.
+        t(ratd(3001, 3000), "1");
+        t(ratd(30001, 3000), "10");
+
+        // Repeating decimals take precedence.
+        assert_eq!(
+            ratd(1, 3)
+                .display(DisplayOptions {
+                    repeating_decimals: true,
+                    ..options
+                })
+                .to_string(),
+            "0.(3)",
+        );
+
+        // Only numbers with decimal representation are affected.
+        t(rat(1, 3), "1/3");
+    }
+
+    #[test]
+    fn full_parenthesization() {
+        let options = DisplayOptions {
+            fully_parenthesized: true,
+            ..DisplayOptions::default()
+        };
+
+        let t = |expression: &str, string: &str| {
+            assert_eq!(
+                expression
+                    .parse::<Expression>()
+                    .unwrap()
+                    .display(options)
+                    .to_string(),
+                string,
+            );
+        };
+
+        t("1 + 2 * 3", "(1 + (2 * 3))");
+        t("(1 + 2) * 3", "((1 + 2) * 3)");
+        t("a - b - c", "((a - b) - c)");
+        t("a - (b - c)", "(a - (b - c))");
+        t("a ^ b ^ c", "(a ^ (b ^ c))");
+        t("-(a + b)", "-(a + b)");
+        t("-(-a) * b", "(-(-a) * b)");
+        t("(-3) ^ 2", "((-3) ^ 2)");
+        t("a == b && !c || d", "(((a == b) && !c) || d)");
+        t("f(a * b, [c + 1])", "f((a * b), [(c + 1)])");
+        t("(f + g)(x)", "(f + g)(x)");
+        t("[a, b][1 + 1]", "[a, b][(1 + 1)]");
+        t("1", "1");
+        t("a", "a");
+    }
+
     #[test]
     fn booleans() {
         t(Boolean(true), "true");
@@ -421,6 +897,16 @@ mod tests {
         t(com(1, 1, -1, 1) - int(2), "1 - i - 2");
         t(int(2) - com(1, 1, -1, 1), "2 - (1 - i)");
 
+        t(var("a") + int(-3), "a - 3");
+        t(var("a") - int(-3), "a + 3");
+        t(var("a") + rat(-3, 4), "a - 3/4");
+        t(var("a") - ratd(-3, 4), "a + 0.75");
+        t(var("a") + ratu(-2, 4), "a - 2/4");
+        t(int(-3) + var("a"), "-3 + a");
+        t(var("a") * int(2) + int(-1), "a * 2 - 1");
+        t((var("a") + int(-1)) * var("b"), "(a - 1) * b");
+        t(var("a") - (var("b") + int(-1)), "a - (b - 1)");
+
         // TODO: Comparison operators!
 
         t(and(and(var("A"), var("B")), var("C")), "A && B && C");
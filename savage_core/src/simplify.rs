@@ -1,26 +1,169 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 // Copyright (C) 2021-2022  Philipp Emanuel Weidmann <pew@worldwidemann.com>
 
+use std::collections::HashMap;
+
 use crate::{expression::Expression, helpers::*};
 
 impl Expression {
+    /// Returns the base and the exponent of the expression if it is a power,
+    /// and otherwise the expression itself as base with an exponent of 1.
+    fn base_and_exponent(&self) -> (Self, Self) {
+        match self {
+            Self::Power(a, b) => (*a.clone(), *b.clone()),
+            _ => (self.clone(), int(1)),
+        }
+    }
+
+    /// Returns whether the expression contains variables. Cancellation rules are only
+    /// applied to divisors with variables, which are assumed to be nonzero, as is
+    /// customary in computer algebra systems. Divisors without variables are left
+    /// for evaluation to compute, which detects division by zero.
+    fn is_symbolic(&self) -> bool {
+        !self.variables().is_empty()
+    }
+
+    /// Returns the result of combining the expression with `other` using `operation`
+    /// after factoring out negations of non-numeric operands, placing a single remaining
+    /// negation in front of the combined expression, or `None` if neither operand
+    /// is such a negation.
+    ///
+    /// Negations of numbers are left alone because evaluation folds them into the number.
+    fn factor_negations(
+        &self,
+        other: &Self,
+        operation: impl Fn(Self, Self) -> Self,
+    ) -> Option<Self> {
+        use crate::expression::Expression::*;
+
+        let negated_operand = |expression: &Self| match expression {
+            Negation(a) if !matches!(**a, Integer(_) | Rational(_, _) | Complex(_, _)) => {
+                Some(*a.clone())
+            }
+            _ => None,
+        };
+
+        match (negated_operand(self), negated_operand(other)) {
+            (Some(a), Some(b)) => Some(operation(a, b)),
+            (Some(a), None) => Some(-operation(a, other.clone())),
+            (None, Some(b)) => Some(-operation(self.clone(), b)),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the result of rewriting the expression itself (but not its sub-expressions)
+    /// once, either by applying a standard algebraic identity or by folding an arithmetic
+    /// operation on numbers. If no rewrite applies, the expression is returned unchanged.
+    fn rewrite(&self) -> Self {
+        use crate::expression::Expression::*;
+
+        let simplified_expression = self.simplify_step();
+
+        if simplified_expression != *self {
+            return simplified_expression;
+        }
+
+        let is_number =
+            |expression: &Self| matches!(expression, Integer(_) | Rational(_, _) | Complex(_, _));
+
+        let foldable = match self {
+            Negation(a) => is_number(a),
+            Sum(a, b) | Difference(a, b) | Product(a, b) | Quotient(a, b) | Remainder(a, b) => {
+                is_number(a) && is_number(b)
+            }
+            // Evaluation of powers with non-integer exponents is not exact.
+            Power(a, b) => is_number(a) && matches!(**b, Integer(_)),
+            _ => false,
+        };
+
+        if foldable {
+            // Operations that are undefined (e.g. division by zero) are left alone.
+            self.evaluate(&HashMap::new())
+                .unwrap_or_else(|_| self.clone())
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Returns the result of rewriting the first sub-expression that can be rewritten,
+    /// searching from the bottom up and from left to right, or `None` if no rewrite
+    /// applies anywhere in the expression.
+    fn simplification_step(&self) -> Option<Self> {
+        let mut index = 0;
+
+        while let Some(child) = self.child(index) {
+            if let Some(child) = child.simplification_step() {
+                let mut expression = self.clone();
+                *expression.child_mut(index).unwrap() = child;
+                return Some(expression);
+            }
+
+            index += 1;
+        }
+
+        let rewritten_expression = self.rewrite();
+
+        if rewritten_expression != *self {
+            Some(rewritten_expression)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the sequence of expressions obtained by simplifying the expression
+    /// one rewrite at a time, starting with the expression itself and ending with
+    /// a fully simplified expression, like the result of [`Expression::simplify`].
+    pub fn simplify_steps(&self) -> Vec<Self> {
+        let mut steps = vec![self.clone()];
+
+        while let Some(step) = steps.last().unwrap().simplification_step() {
+            steps.push(step);
+        }
+
+        steps
+    }
+
+    /// Returns the result of simplifying the expression using standard algebraic
+    /// identities (e.g. `x + 0 = x`, `x * 0 = 0`, `x - x = 0`), applied to
+    /// all sub-expressions from the bottom up, and of folding arithmetic operations
+    /// on numbers. Unlike evaluation, simplification neither substitutes variables
+    /// nor calls functions. Simplifying the result again does not change it.
+    pub fn simplify(&self) -> Self {
+        // Unlike `simplify_steps`, this rewrites all sub-expressions in a single pass,
+        // rather than searching the whole expression again for each rewrite.
+        let expression = self.map_children(&Self::simplify);
+
+        let rewritten_expression = expression.rewrite();
+
+        if rewritten_expression != expression {
+            // The rewrite may have created new sub-expressions
+            // (e.g. `a ^ (2 + 3)` from `a ^ 2 * a ^ 3`), which need to be simplified as well.
+            rewritten_expression.simplify()
+        } else {
+            expression
+        }
+    }
+
     /// Applies standard algebraic simplification rules to the expression,
     /// and returns the result.
     ///
     /// Note that this function does not itself recurse into sub-expressions;
     /// but since it is called from `evaluate_step`, which *does* recurse,
     /// simplifications are applied to the entire expression tree during evaluation.
-    pub(crate) fn simplify(&self) -> Self {
+    pub(crate) fn simplify_step(&self) -> Self {
         use crate::expression::Expression::*;
 
         match self {
-            Negation(a) => {
-                if let Negation(a) = &**a {
-                    *a.clone()
-                } else {
-                    self.clone()
+            Negation(a) => match &**a {
+                Negation(a) => *a.clone(),
+                Difference(a, b) => *b.clone() - *a.clone(),
+                // Moving the negation into a numeric factor allows evaluation
+                // to fold it into the number.
+                Product(a, b) if matches!(**a, Integer(_) | Rational(_, _) | Complex(_, _)) => {
+                    -*a.clone() * *b.clone()
                 }
-            }
+                _ => self.clone(),
+            },
             Not(a) => {
                 if let Not(a) = &**a {
                     *a.clone()
@@ -64,7 +207,9 @@ impl Expression {
                 let a = *a.clone();
                 let b = *b.clone();
 
-                if a == int(1) {
+                if let Some(product) = a.factor_negations(&b, |a, b| a * b) {
+                    product
+                } else if a == int(1) {
                     b
                 } else if b == int(1) {
                     a
@@ -72,34 +217,51 @@ impl Expression {
                     int(0)
                 } else if a == b {
                     pow(a, int(2))
-                } else if a == int(1) / b.clone() || b == int(1) / a {
+                } else if (a == int(1) / b.clone() && b.is_symbolic())
+                    || (b == int(1) / a.clone() && a.is_symbolic())
+                {
                     int(1)
                 } else {
-                    self.clone()
+                    let (a_base, m) = a.base_and_exponent();
+                    let (b_base, n) = b.base_and_exponent();
+
+                    if a_base == b_base {
+                        pow(a_base, m + n)
+                    } else {
+                        self.clone()
+                    }
                 }
             }
             Quotient(a, b) => {
                 let a = *a.clone();
                 let b = *b.clone();
 
-                if b == int(1) {
+                if let Some(quotient) = a.factor_negations(&b, |a, b| a / b) {
+                    quotient
+                } else if b == int(1) {
                     a
+                } else if !b.is_symbolic() {
+                    self.clone()
                 } else if a == int(0) {
-                    // FIXME: This is incorrect if `b` evaluates to zero!
                     int(0)
                 } else if a == b {
-                    // FIXME: This is incorrect if `b` evaluates to zero!
                     int(1)
                 } else {
-                    self.clone()
+                    let (a_base, m) = a.base_and_exponent();
+                    let (b_base, n) = b.base_and_exponent();
+
+                    if a_base == b_base {
+                        pow(a_base, m - n)
+                    } else {
+                        self.clone()
+                    }
                 }
             }
             Remainder(a, b) => {
                 let a = *a.clone();
                 let b = *b.clone();
 
-                if a == int(0) || a == b {
-                    // FIXME: This is incorrect if `b` evaluates to zero!
+                if b.is_symbolic() && (a == int(0) || a == b) {
                     int(0)
                 } else {
                     self.clone()
@@ -120,7 +282,12 @@ impl Expression {
                     // FIXME: This is incorrect if `a` evaluates to zero!
                     int(1)
                 } else {
-                    self.clone()
+                    match (a, b) {
+                        // `(x^m)^n = x^(m * n)` only holds in general if `n` is an integer.
+                        // For example, `(x^2)^(1/2)` is `-x` rather than `x` if `x` is negative.
+                        (Power(x, m), Integer(n)) => pow(*x, *m * Integer(n)),
+                        _ => self.clone(),
+                    }
                 }
             }
             Equal(a, b) | LessThanOrEqual(a, b) | GreaterThanOrEqual(a, b) => {
@@ -188,15 +355,26 @@ mod tests {
             expression
                 .parse::<Expression>()
                 .unwrap()
-                .simplify()
+                .simplify_step()
                 .to_string(),
             result,
         );
     }
 
+    #[track_caller]
+    fn s(expression: &str, result: &str) {
+        let simplified_expression = expression.parse::<Expression>().unwrap().simplify();
+
+        assert_eq!(simplified_expression.to_string(), result);
+        assert_eq!(simplified_expression.simplify(), simplified_expression);
+    }
+
     #[test]
     fn arithmetic() {
         t("-(-a)", "a");
+        t("-(a - b)", "b - a");
+        t("-(2 * a)", "-2 * a");
+        t("-(a * 2)", "-(a * 2)");
 
         t("0 + a", "a");
         t("a + 0", "a");
@@ -221,14 +399,43 @@ mod tests {
         t("a / 1", "a");
         t("0 / a", "0");
         t("a / a", "1");
+        t("(a + b) / (a + b)", "1");
+        t("a / b", "a / b");
+        t("0 / 0", "0 / 0");
+        t("(1 - 1) / (1 - 1)", "(1 - 1) / (1 - 1)");
 
         t("0 % a", "0");
         t("a % a", "0");
+        t("0 % 0", "0 % 0");
+
+        t("(-a) * b", "-(a * b)");
+        t("a * (-b)", "-(a * b)");
+        t("(-a) * (-b)", "a * b");
+        t("(-2) * a", "-2 * a");
+
+        t("(-a) / b", "-(a / b)");
+        t("a / (-b)", "-(a / b)");
+        t("(-a) / (-b)", "a / b");
+
+        t("a ^ 2 * a ^ 3", "a ^ (2 + 3)");
+        t("a * a ^ b", "a ^ (1 + b)");
+        t("a ^ b * a ^ c", "a ^ (b + c)");
+        t("a ^ 2 * b ^ 2", "a ^ 2 * b ^ 2");
+
+        t("a ^ 3 / a", "a ^ (3 - 1)");
+        t("a ^ b / a ^ c", "a ^ (b - c)");
+        t("2 ^ 3 / 2", "2 ^ 3 / 2");
+        t("a ^ 2 / b ^ 2", "a ^ 2 / b ^ 2");
 
         t("1 ^ a", "1");
         t("a ^ 1", "a");
         t("0 ^ a", "0");
         t("a ^ 0", "1");
+
+        t("(a ^ 2) ^ 3", "a ^ (2 * 3)");
+        t("(a ^ b) ^ 2", "a ^ (b * 2)");
+        t("(a ^ 2) ^ (1 / 2)", "(a ^ 2) ^ (1 / 2)");
+        t("(a ^ 2) ^ b", "(a ^ 2) ^ b");
     }
 
     #[test]
@@ -261,4 +468,60 @@ mod tests {
         t("a > a", "false");
         t("a >= a", "true");
     }
+
+    #[test]
+    fn identities() {
+        s("x + 0", "x");
+        s("0 + x", "x");
+        s("x * 1", "x");
+        s("1 * x", "x");
+        s("x * 0", "0");
+        s("0 * x", "0");
+        s("x ^ 1", "x");
+        s("x ^ 0", "1");
+        s("x - x", "0");
+
+        s("(x + 0) * (1 * y)", "x * y");
+        s("(x - x) + y ^ (2 - 1)", "y");
+        s("x ^ 2 * x ^ 3", "x ^ 5");
+        s("x * (y - y) + f(x + 0)", "f(x)");
+        s("[x * 1, 0 + y]", "[x, y]");
+    }
+
+    #[test]
+    fn constant_folding() {
+        s("1 + 2", "3");
+        s("x + 1 / 2 + 1 / 3", "x + 1/2 + 1/3");
+        s("x + (1 / 2 + 1 / 3)", "x + 5/6");
+        s("2 * 3 * x", "6 * x");
+        s("x * (0.5 + 0.25)", "x * 0.75");
+        s("-(2 * 3) + x", "-6 + x");
+        s("2 ^ 10 * x", "1024 * x");
+
+        s("1 / 0 + x", "1 / 0 + x");
+        s("2 ^ (1 / 2) * x", "2 ^ (1/2) * x");
+    }
+
+    #[test]
+    fn steps() {
+        let steps = |expression: &str| {
+            expression
+                .parse::<Expression>()
+                .unwrap()
+                .simplify_steps()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(steps("x"), ["x"]);
+        assert_eq!(
+            steps("(x + 0) * (2 + 3)"),
+            ["(x + 0) * (2 + 3)", "x * (2 + 3)", "x * 5"],
+        );
+        assert_eq!(
+            steps("x ^ 2 * x ^ 3"),
+            ["x ^ 2 * x ^ 3", "x ^ (2 + 3)", "x ^ 5"],
+        );
+    }
 }
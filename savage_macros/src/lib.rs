@@ -6,15 +6,19 @@ use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
     parse_macro_input, punctuated::Punctuated, token::Comma, AttributeArgs, ExprArray, FnArg,
-    ItemFn, Path, Type,
+    ItemFn, Path, ReturnType, Type,
 };
 
 #[derive(FromMeta)]
 struct Arguments {
     name: String,
+    #[darling(default)]
+    aliases: Option<ExprArray>,
     description: String,
     examples: ExprArray,
     categories: ExprArray,
+    #[darling(default)]
+    holds_bindings: bool,
 }
 
 /// Generates code required for the marked function to be usable in a function expression.
@@ -27,9 +31,14 @@ pub fn function(attr: TokenStream, item: TokenStream) -> TokenStream {
     };
 
     let name_argument = arguments.name;
+    let aliases_argument = match arguments.aliases {
+        Some(aliases) => quote! { &#aliases },
+        None => quote! { &[] },
+    };
     let description_argument = arguments.description;
     let examples_argument = arguments.examples;
     let categories_argument = arguments.categories;
+    let holds_bindings_argument = arguments.holds_bindings;
 
     let item_fn = parse_macro_input!(item as ItemFn);
 
@@ -65,23 +74,57 @@ pub fn function(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     });
 
-    let arguments =
-        (0..item_fn.sig.inputs.len()).map(|i| quote! { arguments[#i].clone().try_into()? });
+    let arguments = item_fn.sig.inputs.iter().enumerate().map(|(i, fn_arg)| {
+        if let FnArg::Typed(pat_type) = fn_arg {
+            if let Type::Path(type_path) = &*pat_type.ty {
+                if type_path.path.is_ident("Expression") {
+                    // Any expression is a valid argument, so no conversion is necessary.
+                    return quote! { arguments[#i].clone() };
+                }
+            }
+        }
+
+        quote! { arguments[#i].clone().try_into()? }
+    });
+
+    // Functions returning a `Result` signal an invalid argument by returning it as the error,
+    // or another failure by returning a `FunctionError` (see `savage_core::functions`).
+    let returns_result = if let ReturnType::Type(_, return_type) = &item_fn.sig.output {
+        if let Type::Path(type_path) = &**return_type {
+            type_path.path.segments.last().unwrap().ident == "Result"
+        } else {
+            false
+        }
+    } else {
+        false
+    };
+
+    let result = if returns_result {
+        quote! {
+            #name(#(#arguments),*)
+                .map(::std::convert::Into::into)
+                .map_err(::std::convert::Into::into)
+        }
+    } else {
+        quote! { ::std::result::Result::Ok(#name(#(#arguments),*).into()) }
+    };
 
     let tokens = quote! {
         #item_fn
 
         pub(crate) const #metadata_name: crate::functions::Metadata = crate::functions::Metadata {
             name: #name_argument,
+            aliases: #aliases_argument,
             description: #description_argument,
             parameters: &[#(#parameters),*],
             examples: &#examples_argument,
             categories: &#categories_argument,
+            holds_bindings: #holds_bindings_argument,
         };
 
         pub(crate) fn #proxy_name(arguments: &[crate::expression::Expression]) ->
-            ::std::result::Result<crate::expression::Expression, crate::expression::Expression> {
-            ::std::result::Result::Ok(#name(#(#arguments),*).into())
+            ::std::result::Result<crate::expression::Expression, crate::functions::FunctionError> {
+            #result
         }
     };
 
@@ -106,7 +149,7 @@ pub fn functions(input: TokenStream) -> TokenStream {
         statements.push(quote! {
             functions.push(Function {
                 metadata: #metadata_path,
-                implementation: wrap_proxy(#metadata_path.parameters, #proxy_path),
+                implementation: wrap_proxy(&#metadata_path, #proxy_path),
             });
         });
     }